@@ -0,0 +1,155 @@
+use bevy::prelude::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use game_lib::{
+    animation::AnimationPlugin,
+    engagements::{engagement_builder, EngagementManagerPlugin},
+    gladiator::{
+        gladiator::{ArenaPopulation, GladiatorPlugin},
+        gladiator_movement::gladiator_movement,
+        gladiator_rolls::CombatRng,
+    },
+    grid::{ArenaGrid, GridPlugin},
+    headless::SteppedApp,
+    team::TeamPlugin,
+    WINDOW_HEIGHT, WINDOW_WIDTH,
+};
+
+/// Benchmarks of the core simulation, headless so they run anywhere:
+///  cargo bench -p game
+/// or just one of them:
+///  cargo bench -p game -- battle_step
+///
+/// The system benchmarks run a single system against an arena that was populated by the
+/// game's own startup systems. The battle step runs the whole schedule, with the clock
+/// advanced by one 60 FPS frame per step so the fixed timestep systems run like in the game.
+
+const ARENA_SIZES: [usize; 3] = [300, 1_000, 10_000];
+/// Frames to run before measuring, the gladiators are spawned and spread out by then
+const WARMUP_FRAMES: usize = 10;
+const BENCH_SEED: u64 = 7;
+
+fn bench_get_grid_location(c: &mut Criterion) {
+    let positions = random_positions(1_000);
+    c.bench_function("grid/get_grid_location", |b| {
+        b.iter(|| {
+            for position in &positions {
+                black_box(ArenaGrid::get_grid_location(position.x, position.y));
+            }
+        })
+    });
+}
+
+fn bench_grid_updates(c: &mut Criterion) {
+    let mut group = c.benchmark_group("grid/place");
+    for n_gladiators in ARENA_SIZES {
+        let mut arena_grid = ArenaGrid::default();
+        let positions = random_positions(n_gladiators);
+        for (idx, position) in positions.iter().enumerate() {
+            arena_grid.place(
+                Entity::from_raw(idx as u32),
+                ArenaGrid::get_grid_location(position.x, position.y),
+            );
+        }
+
+        // every gladiator moves to where the next one was, most of them change location
+        let mut offset = 0;
+        group.bench_function(BenchmarkId::from_parameter(n_gladiators), |b| {
+            b.iter(|| {
+                offset = (offset + 1) % n_gladiators;
+                for idx in 0..n_gladiators {
+                    let position = positions[(idx + offset) % n_gladiators];
+                    arena_grid.place(
+                        Entity::from_raw(idx as u32),
+                        ArenaGrid::get_grid_location(position.x, position.y),
+                    );
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_engagement_builder(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engagement_builder");
+    for n_gladiators in ARENA_SIZES {
+        let mut arena = populated_arena(n_gladiators);
+        let mut stage = SystemStage::single_threaded().with_system(engagement_builder);
+        group.bench_function(BenchmarkId::from_parameter(n_gladiators), |b| {
+            b.iter(|| {
+                // the worst case, a sweep over every grid location
+                arena.app.world.resource_mut::<ArenaGrid>().mark_all_dirty();
+                stage.run(&mut arena.app.world);
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_gladiator_movement(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gladiator_movement");
+    for n_gladiators in ARENA_SIZES {
+        let mut arena = populated_arena(n_gladiators);
+        let mut stage = SystemStage::single_threaded().with_system(gladiator_movement);
+        group.bench_function(BenchmarkId::from_parameter(n_gladiators), |b| {
+            b.iter(|| stage.run(&mut arena.app.world))
+        });
+    }
+    group.finish();
+}
+
+fn bench_battle_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("battle_step");
+    group.sample_size(20);
+    for n_gladiators in ARENA_SIZES {
+        let mut arena = populated_arena(n_gladiators);
+        group.bench_function(BenchmarkId::from_parameter(n_gladiators), |b| {
+            b.iter(|| arena.step())
+        });
+    }
+    group.finish();
+}
+
+/// A headless free-for-all, populated by the game's startup systems
+fn populated_arena(n_gladiators: usize) -> SteppedApp {
+    let mut arena = SteppedApp::default();
+    arena
+        .app
+        .insert_resource(CombatRng::new(BENCH_SEED))
+        .insert_resource(ArenaPopulation {
+            player: false,
+            n_gladiators: Some(n_gladiators),
+            ..default()
+        })
+        .add_plugin(AnimationPlugin)
+        .add_plugin(EngagementManagerPlugin)
+        .add_plugin(GridPlugin)
+        .add_plugin(GladiatorPlugin)
+        .add_plugin(TeamPlugin);
+
+    arena.steps(WARMUP_FRAMES);
+    arena
+}
+
+fn random_positions(n: usize) -> Vec<Vec2> {
+    let mut rng = StdRng::seed_from_u64(BENCH_SEED);
+    (0..n)
+        .map(|_| {
+            Vec2::new(
+                rng.gen_range(-WINDOW_WIDTH..WINDOW_WIDTH),
+                rng.gen_range(-WINDOW_HEIGHT..WINDOW_HEIGHT),
+            )
+        })
+        .collect()
+}
+
+criterion_group!(
+    benches,
+    bench_get_grid_location,
+    bench_grid_updates,
+    bench_engagement_builder,
+    bench_gladiator_movement,
+    bench_battle_step
+);
+criterion_main!(benches);
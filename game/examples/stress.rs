@@ -0,0 +1,95 @@
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+
+use game_lib::{
+    animation::AnimationPlugin,
+    engagements::EngagementManagerPlugin,
+    gladiator::{
+        gladiator::{ArenaPopulation, GladiatorPlugin},
+        gladiator_rolls::CombatRng,
+    },
+    grid::GridPlugin,
+    headless::HeadlessPlugins,
+    helper_functions::arg_value,
+    team::TeamPlugin,
+};
+
+/// Stress test for the simulation: a headless free-for-all with a lot of gladiators, paced
+/// at 60 frames per second like the game. It measures how long each frame keeps the CPU
+/// busy and fails when the average is over the frame budget.
+///
+/// The target is 10,000 gladiators at 60 FPS headless, run it with:
+///  cargo run --release --example stress
+/// or pick the numbers with:
+///  cargo run --release --example stress -- --gladiators 1000 --frames 300
+
+const TARGET_FPS: f64 = 60.0;
+const DEFAULT_GLADIATORS: usize = 10_000;
+const DEFAULT_FRAMES: usize = 600;
+/// Frames that are not measured while spawning settles down
+const WARMUP_FRAMES: usize = 60;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let n_gladiators = number_arg(&args, "--gladiators").unwrap_or(DEFAULT_GLADIATORS);
+    let n_frames = number_arg(&args, "--frames")
+        .unwrap_or(DEFAULT_FRAMES)
+        .max(1);
+    let frame_budget = Duration::from_secs_f64(1.0 / TARGET_FPS);
+
+    let mut app = App::new();
+    app.add_plugins(HeadlessPlugins)
+        .insert_resource(CombatRng::from_args(args.iter().cloned()))
+        .insert_resource(ArenaPopulation {
+            player: false,
+            n_gladiators: Some(n_gladiators),
+            ..default()
+        })
+        .add_plugin(AnimationPlugin)
+        .add_plugin(EngagementManagerPlugin)
+        .add_plugin(GridPlugin)
+        .add_plugin(GladiatorPlugin)
+        .add_plugin(TeamPlugin);
+
+    println!(
+        "Running {} gladiators for {} frames...",
+        n_gladiators, n_frames
+    );
+
+    let mut frame_times = Vec::with_capacity(n_frames);
+    for frame in 0..WARMUP_FRAMES + n_frames {
+        let start = Instant::now();
+        app.update();
+        let busy = start.elapsed();
+
+        if frame >= WARMUP_FRAMES {
+            frame_times.push(busy);
+        }
+        // keep the pace of the game, the fixed timestep systems depend on it
+        if let Some(idle) = frame_budget.checked_sub(busy) {
+            std::thread::sleep(idle);
+        }
+    }
+
+    frame_times.sort();
+    let total: Duration = frame_times.iter().sum();
+    let average = total / frame_times.len().max(1) as u32;
+    let p99 = frame_times[(frame_times.len() * 99 / 100).min(frame_times.len() - 1)];
+    let worst = frame_times[frame_times.len() - 1];
+
+    println!(
+        "{} gladiators: average frame {:.2?}, 99th percentile {:.2?}, worst {:.2?} (budget {:.2?})",
+        n_gladiators, average, p99, worst, frame_budget
+    );
+
+    if average > frame_budget {
+        println!("Over the frame budget for {} FPS.", TARGET_FPS);
+        std::process::exit(1);
+    }
+}
+
+/// Value of a numeric command line argument, e.g. `--frames 300`
+fn number_arg(args: &[String], flag: &str) -> Option<usize> {
+    arg_value(args.iter().cloned(), flag).and_then(|value| value.parse().ok())
+}
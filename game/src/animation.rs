@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::gladiator::{gladiator_components::*, gladiator_movement::*};
 
@@ -10,7 +11,10 @@ pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(animate_sprites);
+        app.register_type::<Animation>()
+            .register_type::<AnimationType>()
+            .register_type::<AnimationTimer>()
+            .add_system(animate_sprites);
     }
 }
 
@@ -46,7 +50,8 @@ pub fn animate_sprites(
 /// Structs and Enums
 ///////////////////////////////////////////////////////
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Animation {
     /// Describes which animation is happening
     pub animation_type: AnimationType,
@@ -72,8 +77,10 @@ impl Animation {
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
 pub enum AnimationType {
+    #[default]
     Idle,
     Walk,
     Sword,
@@ -0,0 +1,575 @@
+//! The arena floor isn't empty: pillars and walls stand in the way, sand slows gladiators
+//! down and water can't be crossed at all. The layout comes from a map file in
+//! assets/maps/ and is marked in the ArenaGrid, one Terrain per grid location, so moving
+//! gladiators (see terrain_step) only need to look up the grid location they step into.
+//!
+//! Maps can also be drawn in Tiled (see tiled_map), those bring their own background,
+//! spawn points and item spawners. Item spawners put an item on the floor, the first
+//! gladiator to step on it equips it, and a new one is put down a while later.
+//!
+//! Pick the map with:
+//!  cargo run -- --map colosseum   (default)
+//!  cargo run -- --map empty       (the old empty rectangle)
+//!  cargo run -- --map ludus.tmj   (a Tiled map, .tmj or .tmx)
+
+use bevy::{asset::HandleId, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gladiator::{gladiator_components::Gladiator, gladiator_equipment::*},
+    grid::*,
+    helper_functions::*,
+    team::*,
+    tiled_map::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+pub struct ArenaPlugin {
+    /// Name of the map file in assets/maps/, without the extension for .ron maps and with
+    /// it for Tiled maps
+    pub map: String,
+}
+
+impl ArenaPlugin {
+    /// Reads the map from `--map <name>` in the command line arguments, DEFAULT_MAP without it
+    /// * `args` - command line arguments, usually std::env::args()
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        Self {
+            map: arg_value(args, "--map").unwrap_or_else(|| DEFAULT_MAP.to_string()),
+        }
+    }
+}
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        let arena_map = if self.map.ends_with(".tmx") || self.map.ends_with(".tmj") {
+            load_tiled_map(&format!("{}{}", MAPS_PATH, self.map))
+        } else {
+            load_data_file(&format!("{}{}.ron", MAPS_PATH, self.map))
+        }
+        .expect("Arena map should be valid.");
+
+        app.register_type::<Terrain>()
+            .register_type::<TerrainTile>()
+            .register_type::<ItemSpawner>()
+            .register_type::<ItemPickup>()
+            .insert_resource(arena_map)
+            // the terrain has to be in the grid before anybody is spawned on it
+            .add_startup_system_to_stage(StartupStage::PreStartup, mark_terrain)
+            .add_startup_system(draw_background)
+            .add_startup_system(draw_terrain)
+            .add_startup_system(place_item_spawners)
+            .add_system(spawn_items)
+            .add_system(pick_up_items);
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Marks the terrain of the map in the ArenaGrid
+fn mark_terrain(arena_map: Res<ArenaMap>, mut arena_grid: ResMut<ArenaGrid>) {
+    arena_grid.clear_terrain();
+    for (location, terrain) in arena_map.locations() {
+        arena_grid.set_terrain(location, terrain);
+    }
+}
+
+/// Draws the tiles of a Tiled map, or the background image of any other map
+fn draw_background(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    arena_map: Res<ArenaMap>,
+) {
+    if arena_map.tiles.is_empty() {
+        if let Some(background) = &arena_map.background {
+            commands.spawn(SpriteBundle {
+                texture: asset_server.load(background.as_str()),
+                transform: Transform {
+                    translation: Vec3::new(0.0, 0.0, BACKGROUND_Z),
+                    scale: Vec3::new(
+                        WINDOW_WIDTH / BACKGROUND_WIDTH,
+                        WINDOW_HEIGHT / BACKGROUND_HEIGHT,
+                        1.0,
+                    ),
+                    ..default()
+                },
+                ..default()
+            });
+        }
+        return;
+    }
+
+    for tile in &arena_map.tiles {
+        // every layer gets a bit closer to the camera than the one under it
+        let transform = Transform::from_translation(
+            tile.position
+                .extend(BACKGROUND_Z + tile.depth as f32 * TILE_LAYER_Z_STEP),
+        );
+        match &tile.image {
+            TileImage::Single(image) => {
+                commands.spawn(SpriteBundle {
+                    texture: asset_server.load(image.as_str()),
+                    sprite: Sprite {
+                        custom_size: Some(tile.size),
+                        ..default()
+                    },
+                    transform,
+                    ..default()
+                });
+            }
+            TileImage::Sheet {
+                image,
+                tile_size,
+                columns,
+                rows,
+                spacing,
+                margin,
+                index,
+            } => {
+                // one atlas per tileset image, shared by all its tiles
+                let atlas_id = HandleId::from(format!("{}#atlas", image).as_str());
+                let texture_atlas = if texture_atlases.contains(&Handle::weak(atlas_id)) {
+                    texture_atlases.get_handle(atlas_id)
+                } else {
+                    let texture_atlas = TextureAtlas::from_grid(
+                        asset_server.load(image.as_str()),
+                        *tile_size,
+                        *columns as usize,
+                        *rows as usize,
+                        Some(Vec2::splat(*spacing)),
+                        Some(Vec2::splat(*margin)),
+                    );
+                    texture_atlases.set(atlas_id, texture_atlas)
+                };
+                commands.spawn(SpriteSheetBundle {
+                    texture_atlas,
+                    sprite: TextureAtlasSprite {
+                        index: *index,
+                        custom_size: Some(tile.size),
+                        ..default()
+                    },
+                    transform,
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+/// Draws a tile over the background for every grid location that isn't open floor. Tiled
+/// maps draw their own terrain.
+fn draw_terrain(mut commands: Commands, arena_map: Res<ArenaMap>) {
+    if !arena_map.tiles.is_empty() {
+        return;
+    }
+    let cell_size = ArenaGrid::cell_size();
+    for (location, terrain) in arena_map.locations() {
+        if terrain == Terrain::Open {
+            continue;
+        }
+        let center = ArenaGrid::center_of(location);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: terrain.color(),
+                    custom_size: Some(cell_size),
+                    ..default()
+                },
+                transform: Transform::from_translation(center.extend(TERRAIN_Z)),
+                ..default()
+            },
+            TerrainTile,
+        ));
+    }
+}
+
+/// Puts an ItemSpawner wherever the map has one, leaving out items that don't exist
+fn place_item_spawners(
+    mut commands: Commands,
+    arena_map: Res<ArenaMap>,
+    item_catalog: Option<Res<ItemCatalog>>,
+) {
+    for definition in &arena_map.item_spawners {
+        if !item_catalog
+            .as_ref()
+            .map_or(false, |catalog| catalog.get(&definition.item).is_some())
+        {
+            println!(
+                "There is no item called {}, leaving out its spawner.",
+                definition.item
+            );
+            continue;
+        }
+        let interval = definition.interval.unwrap_or(ITEM_RESPAWN_INTERVAL);
+        commands.spawn((
+            SpatialBundle::from_transform(Transform::from_translation(
+                definition.position.extend(ITEM_Z),
+            )),
+            ItemSpawner {
+                item: definition.item.clone(),
+                // the first item is there from the start
+                timer: Timer::from_seconds(0.0, TimerMode::Once),
+                interval,
+                pickup: None,
+            },
+        ));
+    }
+}
+
+/// Puts an item down at every spawner whose last item was picked up long enough ago
+fn spawn_items(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut spawners: Query<(Entity, &Transform, &mut ItemSpawner)>,
+) {
+    for (spawner_entity, transform, mut spawner) in spawners.iter_mut() {
+        if spawner.pickup.is_some() || !spawner.timer.tick(time.delta()).finished() {
+            continue;
+        }
+        let pickup = spawn_item_pickup(
+            &mut commands,
+            &spawner.item,
+            transform.translation.truncate(),
+            Some(spawner_entity),
+        );
+        spawner.pickup = Some(pickup);
+    }
+}
+
+/// Puts an item on the floor for gladiators to pick up and returns its Entity
+/// * `item` - name of the item in the ItemCatalog
+/// * `position` - where it lies in the arena
+/// * `spawner` - the ItemSpawner that put it there, None for anything else, e.g. the crowd
+pub fn spawn_item_pickup(
+    commands: &mut Commands,
+    item: &str,
+    position: Vec2,
+    spawner: Option<Entity>,
+) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: ITEM_COLOR,
+                    custom_size: Some(ArenaGrid::cell_size() * ITEM_SIZE),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(ITEM_Z)),
+                ..default()
+            },
+            ItemPickup {
+                item: item.to_string(),
+                spawner,
+            },
+        ))
+        .id()
+}
+
+/// The first gladiator in the grid location of an item picks it up and equips it, in place
+/// of whatever it had in that slot
+fn pick_up_items(
+    mut commands: Commands,
+    arena_grid: Res<ArenaGrid>,
+    item_catalog: Option<Res<ItemCatalog>>,
+    pickups: Query<(Entity, &Transform, &ItemPickup)>,
+    mut gladiators: Query<Option<&mut Equipment>, With<Gladiator>>,
+    mut spawners: Query<&mut ItemSpawner>,
+) {
+    let item_catalog = match item_catalog {
+        Some(item_catalog) => item_catalog,
+        None => return,
+    };
+    for (pickup_entity, transform, pickup) in pickups.iter() {
+        let location =
+            ArenaGrid::get_grid_location(transform.translation.x, transform.translation.y);
+        let gladiator = arena_grid
+            .entities_at(&location)
+            .find(|entity| gladiators.contains(*entity));
+        let (gladiator, item) = match (gladiator, item_catalog.get(&pickup.item)) {
+            (Some(gladiator), Some(item)) => (gladiator, item),
+            _ => continue,
+        };
+
+        match gladiators.get_mut(gladiator) {
+            Ok(Some(mut equipment)) => *equipment.slot_mut(item.slot) = Some(pickup.item.clone()),
+            Ok(None) => {
+                let mut equipment = Equipment::default();
+                *equipment.slot_mut(item.slot) = Some(pickup.item.clone());
+                commands.entity(gladiator).insert(equipment);
+            }
+            Err(_) => continue,
+        }
+        commands.entity(pickup_entity).despawn();
+
+        if let Some(mut spawner) = pickup
+            .spawner
+            .and_then(|spawner| spawners.get_mut(spawner).ok())
+        {
+            let interval = spawner.interval;
+            spawner.timer = Timer::from_seconds(interval, TimerMode::Once);
+            spawner.pickup = None;
+        }
+    }
+}
+
+/// How far a gladiator at `position` gets when it tries to move by `delta`. The terrain it
+/// is standing on changes its speed, and it can't step into a blocked grid location. When
+/// the way is blocked it sidesteps along the obstacle instead, and stays put if that is
+/// blocked too. A gladiator that is already in a blocked grid location, e.g. spawned
+/// there, can always walk out.
+/// * `arena_grid` - where the terrain is
+/// * `position` - where the gladiator is
+/// * `delta` - where it wants to move, relative to its position
+pub fn terrain_step(arena_grid: &ArenaGrid, position: Vec2, delta: Vec2) -> Vec2 {
+    let current = ArenaGrid::get_grid_location(position.x, position.y);
+    let delta = delta * arena_grid.terrain_at(&current).speed_modifier();
+
+    let can_step = |step: Vec2| {
+        let next = position + step;
+        let next = ArenaGrid::get_grid_location(next.x, next.y);
+        next == current || !arena_grid.terrain_at(&next).is_blocked()
+    };
+
+    let around = if delta.x == 0.0 || delta.y == 0.0 {
+        // heading straight at it, sidestep until it is out of the way
+        [delta.perp(), -delta.perp()]
+    } else {
+        // heading at it diagonally, slide along it
+        [Vec2::new(delta.x, 0.0), Vec2::new(0.0, delta.y)]
+    };
+
+    [delta, around[0], around[1]]
+        .into_iter()
+        .find(|step| can_step(*step))
+        .unwrap_or(Vec2::ZERO)
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// What the floor of a grid location is like
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum Terrain {
+    #[default]
+    Open,
+    /// Slows gladiators down
+    Sand,
+    /// Can't be crossed
+    Water,
+    Pillar,
+    Wall,
+}
+
+impl Terrain {
+    /// Whether gladiators can't walk into it
+    pub fn is_blocked(&self) -> bool {
+        matches!(self, Self::Water | Self::Pillar | Self::Wall)
+    }
+
+    /// Multiplier of the speed of gladiators walking on it. Gladiators only stand on
+    /// blocked terrain when they were put there, e.g. spawned after running out of
+    /// SPAWN_ATTEMPTS, and walk out of it at full speed.
+    pub fn speed_modifier(&self) -> f32 {
+        match self {
+            Self::Sand => SAND_SPEED_MODIFIER,
+            Self::Open | Self::Water | Self::Pillar | Self::Wall => 1.0,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Open => Color::NONE,
+            Self::Sand => SAND_COLOR,
+            Self::Water => WATER_COLOR,
+            Self::Pillar => PILLAR_COLOR,
+            Self::Wall => WALL_COLOR,
+        }
+    }
+}
+
+/// Sprite of a grid location with terrain, see draw_terrain
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct TerrainTile;
+
+/// Puts an item on the floor, and a new one `interval` seconds after it is picked up
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct ItemSpawner {
+    /// Name of the item in the ItemCatalog
+    pub item: String,
+    pub interval: f32,
+    /// Time until the next item is put down
+    pub timer: Timer,
+    /// The item on the floor, None once it is picked up
+    #[reflect(ignore)]
+    #[serde(skip)]
+    pub pickup: Option<Entity>,
+}
+
+/// An item lying on the floor, waiting for a gladiator to pick it up
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct ItemPickup {
+    /// Name of the item in the ItemCatalog
+    pub item: String,
+    /// The ItemSpawner that put it there, None when it was thrown in by the crowd
+    #[reflect(ignore)]
+    #[serde(skip)]
+    pub spawner: Option<Entity>,
+}
+
+/// Layout of the arena from a map file in assets/maps/. Everything in a .ron map is in
+/// grid locations, with (x: 0, y: 0) in the middle of the arena. Pillars are put down last
+/// and walls before them, so both stand on top of any terrain area.
+#[derive(Resource, Default, Deserialize)]
+pub struct ArenaMap {
+    /// Image in the assets folder drawn under the arena, stretched to fill it
+    #[serde(default)]
+    pub background: Option<String>,
+    /// Tiles drawn in place of the background, only Tiled maps have them
+    #[serde(skip)]
+    pub tiles: Vec<BackgroundTile>,
+    /// Where gladiators spawn, anywhere in the arena when there are none
+    #[serde(default)]
+    pub spawn_points: Vec<SpawnPoint>,
+    #[serde(default)]
+    pub item_spawners: Vec<ItemSpawnerDefinition>,
+    /// Rectangles of sand or water
+    #[serde(default)]
+    pub terrain: Vec<TerrainArea>,
+    /// Straight lines of wall, horizontal, vertical or diagonal
+    #[serde(default)]
+    pub walls: Vec<Wall>,
+    #[serde(default)]
+    pub pillars: Vec<GridLocation>,
+}
+
+impl ArenaMap {
+    /// Every grid location the map puts something in, with what it puts there. A grid
+    /// location can come up more than once, the last one wins.
+    pub fn locations(&self) -> impl Iterator<Item = (GridLocation, Terrain)> + '_ {
+        let areas = self.terrain.iter().flat_map(|area| {
+            area.locations()
+                .map(move |location| (location, area.terrain))
+        });
+        let walls = self
+            .walls
+            .iter()
+            .flat_map(|wall| wall.locations().map(|location| (location, Terrain::Wall)));
+        let pillars = self
+            .pillars
+            .iter()
+            .map(|location| (*location, Terrain::Pillar));
+        areas.chain(walls).chain(pillars)
+    }
+}
+
+/// Where gladiators can spawn
+#[derive(Clone, Deserialize)]
+pub struct SpawnPoint {
+    pub position: Vec2,
+    /// Only gladiators of this team spawn here, any gladiator when None
+    #[serde(default)]
+    pub team: Option<Team>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ItemSpawnerDefinition {
+    pub position: Vec2,
+    /// Name of the item in the ItemCatalog
+    pub item: String,
+    /// Seconds until a picked up item is replaced, ITEM_RESPAWN_INTERVAL when None
+    #[serde(default)]
+    pub interval: Option<f32>,
+}
+
+/// A tile of a Tiled map
+pub struct BackgroundTile {
+    pub image: TileImage,
+    /// Where its center goes in the arena
+    pub position: Vec2,
+    pub size: Vec2,
+    /// Layer the tile is in, 0 for the bottom one
+    pub depth: usize,
+}
+
+pub enum TileImage {
+    /// A tile with an image of its own
+    Single(String),
+    /// A tile cut from an image with many tiles
+    Sheet {
+        image: String,
+        tile_size: Vec2,
+        columns: u32,
+        rows: u32,
+        spacing: f32,
+        margin: f32,
+        index: usize,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct TerrainArea {
+    pub terrain: Terrain,
+    /// One corner of the rectangle
+    pub from: GridLocation,
+    /// The opposite corner, included in the rectangle
+    pub to: GridLocation,
+}
+
+impl TerrainArea {
+    /// The area of every grid location a rectangle in the arena overlaps
+    /// * `corner` - one corner of the rectangle
+    /// * `opposite` - the opposite corner
+    pub fn covering(terrain: Terrain, corner: Vec2, opposite: Vec2) -> Self {
+        // a rectangle that ends exactly on the edge of a grid location doesn't overlap it
+        let inset = (opposite - corner).signum() * 0.01;
+        let (from, to) = (corner + inset, opposite - inset);
+        Self {
+            terrain,
+            from: ArenaGrid::get_grid_location(from.x, from.y),
+            to: ArenaGrid::get_grid_location(to.x, to.y),
+        }
+    }
+
+    pub fn locations(&self) -> impl Iterator<Item = GridLocation> {
+        let (min_x, max_x) = (self.from.x.min(self.to.x), self.from.x.max(self.to.x));
+        let (min_y, max_y) = (self.from.y.min(self.to.y), self.from.y.max(self.to.y));
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| GridLocation { x, y }))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Wall {
+    pub from: GridLocation,
+    /// The other end, part of the wall
+    pub to: GridLocation,
+}
+
+impl Wall {
+    pub fn locations(&self) -> impl Iterator<Item = GridLocation> {
+        let (from, to) = (self.from, self.to);
+        let n_steps = (to.x - from.x).abs().max((to.y - from.y).abs());
+        (0..=n_steps).map(move |step| {
+            let fraction = if n_steps == 0 {
+                0.0
+            } else {
+                step as f32 / n_steps as f32
+            };
+            GridLocation {
+                x: from.x + ((to.x - from.x) as f32 * fraction).round() as i32,
+                y: from.y + ((to.y - from.y) as f32 * fraction).round() as i32,
+            }
+        })
+    }
+}
@@ -0,0 +1,529 @@
+//! Career mode keeps a roster of gladiators between runs of the game. Every battle takes
+//! gladiators from the roster into the arena against a team of strangers. Afterwards the
+//! XP, levels and win/loss records are written back to the save file, and gladiators that
+//! died are gone from the roster for good (they are remembered with the fallen).
+//!
+//! The first gladiator on the roster is the one the player controls. When they fall,
+//! the next one in line that is still standing takes over, with the healing items they
+//! carry. Gladiators earn gold for their kills and for surviving a battle, and between
+//! battles the player spends theirs in the shop. Once everybody on the roster has fallen
+//! the career is over, and space starts a new one.
+//!
+//! Play a career battle with:
+//!  cargo run -- --career
+
+use std::{fs, path::Path};
+
+use bevy::{app::AppExit, prelude::*};
+use rand::{self, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    director::*,
+    gladiator::{gladiator::*, gladiator_components::*, gladiator_equipment::*},
+    grid::*,
+    helper_functions::*,
+    player::player_components::*,
+    shop::*,
+    team::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+pub struct CareerPlugin {
+    /// Close the app once the battle is over and saved, used when running headless
+    pub exit_when_done: bool,
+    /// Where the career is saved, usually CAREER_SAVE_PATH
+    pub save_path: String,
+}
+
+impl Plugin for CareerPlugin {
+    fn build(&self, app: &mut App) {
+        let save = match CareerSave::load(&self.save_path) {
+            Ok(save) => save,
+            Err(err) => {
+                println!("{} Starting a new career.", err);
+                CareerSave::new_career()
+            }
+        };
+
+        let catalog: ShopCatalog =
+            load_data_file(SHOP_CATALOG_PATH).expect("Shop catalog should be valid.");
+
+        // a career whose roster has all fallen picks up where it ended
+        let state = if save.roster.is_empty() {
+            CareerState::GameOver
+        } else {
+            CareerState::Battle
+        };
+
+        app.register_type::<CareerGladiator>()
+            .add_state(state)
+            .insert_resource(Shop::new(catalog))
+            .insert_resource(Career {
+                save,
+                participants: Vec::new(),
+                battle_over: false,
+                exit_when_done: self.exit_when_done,
+                save_path: self.save_path.clone(),
+            })
+            .insert_resource(ArenaPopulation {
+                random_gladiators: false,
+                player: false,
+                ..default()
+            })
+            .add_system_set(
+                SystemSet::on_enter(CareerState::Battle)
+                    .with_system(start_career_battle)
+                    .with_system(restart_director),
+            )
+            .add_system_set(
+                SystemSet::on_update(CareerState::Battle)
+                    .with_system(hand_over_player)
+                    .with_system(end_career_battle),
+            )
+            .add_system_set(
+                SystemSet::on_exit(CareerState::Battle)
+                    .with_system(clear_arena)
+                    .with_system(clear_hazards),
+            )
+            .add_system_set(SystemSet::on_enter(CareerState::Shop).with_system(open_shop))
+            .add_system_set(SystemSet::on_update(CareerState::Shop).with_system(shop_input))
+            .add_system_set(SystemSet::on_exit(CareerState::Shop).with_system(save_career))
+            .add_system_set(
+                SystemSet::on_enter(CareerState::GameOver)
+                    .with_system(game_over)
+                    .with_system(clear_hazards),
+            )
+            .add_system_set(
+                SystemSet::on_update(CareerState::GameOver).with_system(new_career_input),
+            );
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Sends the first CAREER_BATTLE_SIZE gladiators of the roster into the arena against as
+/// many strangers of about the same level.
+fn start_career_battle(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    item_catalog: Res<ItemCatalog>,
+    mut career: ResMut<Career>,
+) {
+    let career = &mut *career;
+    career.participants.clear();
+    career.battle_over = false;
+    if career.save.roster.is_empty() {
+        career.battle_over = true;
+        return; // never happens, the career is over before it gets here (see game_over)
+    }
+
+    career.save.battles += 1;
+    println!("Career battle {}!", career.save.battles);
+
+    let mut rng = rand::thread_rng();
+    let n_gladiators = career.save.roster.len().min(CAREER_BATTLE_SIZE);
+    for (idx, roster_gladiator) in career.save.roster[..n_gladiators].iter().enumerate() {
+        let gladiator_spawn = GladiatorSpawn {
+            class: Some(roster_gladiator.class),
+            team: Some(Team(CAREER_TEAM)),
+            level: roster_gladiator.level,
+            xp: roster_gladiator.xp,
+        };
+        let location = Vec2::new(
+            rng.gen_range(-WINDOW_WIDTH / 2.0..0.0),
+            rng.gen_range(-WINDOW_HEIGHT / 2.0..WINDOW_HEIGHT / 2.0),
+        );
+
+        let entity = spawn_one_gladiator(
+            location,
+            idx,
+            &gladiator_spawn,
+            &mut commands,
+            &asset_server,
+            &mut texture_atlases,
+        );
+        commands.entity(entity).insert((
+            CareerGladiator(roster_gladiator.id),
+            Details {
+                name: roster_gladiator.name.clone(),
+                motivation: "Glory".to_string(),
+            },
+            Gold {
+                value: roster_gladiator.gold,
+            },
+            Equipment::from_owned(&roster_gladiator.equipment, &item_catalog),
+        ));
+        if idx == 0 {
+            commands.entity(entity).insert((
+                Player,
+                Inventory {
+                    healing_items: roster_gladiator.healing_items.clone(),
+                },
+            ));
+        }
+
+        career.participants.push(roster_gladiator.id);
+    }
+
+    // the strangers are about as experienced as the roster gladiators they're up against
+    let average_level = career.save.roster[..n_gladiators]
+        .iter()
+        .map(|roster_gladiator| roster_gladiator.level)
+        .sum::<usize>()
+        / n_gladiators;
+    let gladiator_spawn = GladiatorSpawn {
+        team: Some(Team(CAREER_OPPONENT_TEAM)),
+        level: average_level,
+        ..default()
+    };
+    for idx in 0..n_gladiators {
+        let location = Vec2::new(
+            rng.gen_range(0.0..WINDOW_WIDTH / 2.0),
+            rng.gen_range(-WINDOW_HEIGHT / 2.0..WINDOW_HEIGHT / 2.0),
+        );
+        spawn_one_gladiator(
+            location,
+            idx,
+            &gladiator_spawn,
+            &mut commands,
+            &asset_server,
+            &mut texture_atlases,
+        );
+    }
+}
+
+/// When the player's gladiator has fallen, the next roster gladiator still in the arena
+/// takes over
+fn hand_over_player(
+    mut commands: Commands,
+    career: Res<Career>,
+    player_query: Query<(), With<Player>>,
+    query: Query<(Entity, &CareerGladiator, &Details)>,
+) {
+    if career.battle_over || !player_query.is_empty() {
+        return;
+    }
+    let successor = career.participants.iter().find_map(|id| {
+        query
+            .iter()
+            .find(|(_, career_gladiator, _)| career_gladiator.0 == *id)
+    });
+    let (entity, career_gladiator, details) = match successor {
+        Some(successor) => successor,
+        None => return, // nobody left to take over, or not spawned yet
+    };
+    let healing_items = career
+        .save
+        .roster
+        .iter()
+        .find(|roster_gladiator| roster_gladiator.id == career_gladiator.0)
+        .map(|roster_gladiator| roster_gladiator.healing_items.clone())
+        .unwrap_or_default();
+
+    println!("{} takes over!", details.name);
+    commands
+        .entity(entity)
+        .insert((Player, Inventory { healing_items }));
+}
+
+/// Once a team has won, writes the results of the battle back to the roster and saves,
+/// then opens the shop. Roster gladiators that are no longer in the arena have died and
+/// their gold is lost with them.
+fn end_career_battle(
+    mut ev_victory: EventReader<VictoryEvent>,
+    mut ev_exit: EventWriter<AppExit>,
+    mut career: ResMut<Career>,
+    mut career_state: ResMut<State<CareerState>>,
+    query: Query<(&CareerGladiator, &Level, &Gold, Option<&Inventory>)>,
+) {
+    for event in ev_victory.iter() {
+        if career.battle_over {
+            continue;
+        }
+        let career = &mut *career;
+        let won = event.team == Some(Team(CAREER_TEAM));
+
+        let participants = std::mem::take(&mut career.participants);
+        for id in participants {
+            let survivor = query
+                .iter()
+                .find(|(career_gladiator, ..)| career_gladiator.0 == id);
+            let roster_idx = career
+                .save
+                .roster
+                .iter()
+                .position(|roster_gladiator| roster_gladiator.id == id)
+                .expect("Battle participants should be on the roster.");
+
+            match survivor {
+                Some((_, level, gold, inventory)) => {
+                    let roster_gladiator = &mut career.save.roster[roster_idx];
+                    roster_gladiator.level = level.level;
+                    roster_gladiator.xp = level.xp;
+                    roster_gladiator.gold = gold.value + GOLD_FOR_SURVIVING;
+                    if let Some(inventory) = inventory {
+                        roster_gladiator.healing_items = inventory.healing_items.clone();
+                    }
+                    if won {
+                        roster_gladiator.wins += 1;
+                    } else {
+                        roster_gladiator.losses += 1;
+                    }
+                }
+                None => {
+                    let mut fallen = career.save.roster.remove(roster_idx);
+                    fallen.losses += 1;
+                    println!("{} has fallen.", fallen.name);
+                    career.save.fallen.push(fallen);
+                }
+            }
+        }
+
+        println!("{}", if won { "Victory!" } else { "Defeat!" });
+        println!("{}", career.save.roster_summary());
+        match career.save.save(&career.save_path) {
+            Ok(()) => println!("Career saved to {}", career.save_path),
+            Err(err) => println!("Unable to save career. {}", err),
+        }
+
+        career.battle_over = true;
+        if career.exit_when_done {
+            ev_exit.send(AppExit);
+        } else if career.save.roster.is_empty() {
+            career_state
+                .set(CareerState::GameOver)
+                .expect("Should be able to end the career after a battle.");
+        } else {
+            career_state
+                .set(CareerState::Shop)
+                .expect("Should be able to open the shop after a battle.");
+        }
+    }
+}
+
+/// Clears the survivors out of the arena so the next battle starts fresh
+fn clear_arena(
+    mut commands: Commands,
+    mut arena_grid: ResMut<ArenaGrid>,
+    query: Query<Entity, With<Gladiator>>,
+) {
+    for entity in &query {
+        commands.entity(entity).despawn();
+    }
+    arena_grid.clear();
+}
+
+/// Everybody on the roster has fallen, remembers them and offers a new career. Headless
+/// there is nobody to press space, so the app closes instead.
+fn game_over(career: Res<Career>, mut ev_exit: EventWriter<AppExit>) {
+    println!(
+        "Game over! The last of the roster fell after {} battles.",
+        career.save.battles
+    );
+    println!("The fallen:");
+    for fallen in &career.save.fallen {
+        println!(
+            "  {:<12} {:?} lvl {} | {}W {}L",
+            fallen.name, fallen.class, fallen.level, fallen.wins, fallen.losses
+        );
+    }
+    if career.exit_when_done {
+        println!("Delete {} to start a new career.", career.save_path);
+        ev_exit.send(AppExit);
+    } else {
+        println!("Press space to start a new career.");
+    }
+}
+
+/// Starts a new career with space, the fallen of the old one are forgotten
+fn new_career_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut career: ResMut<Career>,
+    mut career_state: ResMut<State<CareerState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Space) {
+        return;
+    }
+    career.save = CareerSave::new_career();
+    if let Err(err) = career.save.save(&career.save_path) {
+        println!("Unable to save career. {}", err);
+    }
+    career_state
+        .set(CareerState::Battle)
+        .expect("Should be able to start a new career.");
+}
+
+/// Saves what was bought in the shop before the next battle
+fn save_career(career: Res<Career>) {
+    if let Err(err) = career.save.save(&career.save_path) {
+        println!("Unable to save career. {}", err);
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CareerState {
+    Battle,
+    Shop,
+    /// Everybody on the roster has fallen
+    GameOver,
+}
+
+/// Marks a gladiator in the arena as the roster gladiator with this id
+#[derive(Component, Default, Reflect)]
+#[reflect(Component)]
+pub struct CareerGladiator(pub u32);
+
+#[derive(Resource)]
+pub struct Career {
+    pub save: CareerSave,
+    /// Ids of the roster gladiators fighting in the current battle
+    pub participants: Vec<u32>,
+    pub battle_over: bool,
+    pub exit_when_done: bool,
+    pub save_path: String,
+}
+
+/// Everything that is kept between runs of the game
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CareerSave {
+    pub roster: Vec<RosterGladiator>,
+    pub fallen: Vec<RosterGladiator>,
+    pub battles: usize,
+    pub next_id: u32,
+}
+
+impl CareerSave {
+    /// A fresh roster of level 1 gladiators named from CAREER_NAMES
+    pub fn new_career() -> Self {
+        let mut save = Self::default();
+        let classes = [Class::Fighter, Class::Archer, Class::Mage];
+        for (idx, name) in CAREER_NAMES.iter().enumerate() {
+            save.recruit(name, classes[idx % classes.len()]);
+        }
+        save
+    }
+
+    /// Adds a new level 1 gladiator to the roster
+    pub fn recruit(&mut self, name: &str, class: Class) {
+        self.roster.push(RosterGladiator {
+            id: self.next_id,
+            name: name.to_string(),
+            class,
+            level: 1,
+            xp: 0.,
+            equipment: Vec::new(),
+            gold: 0,
+            healing_items: Vec::new(),
+            wins: 0,
+            losses: 0,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read career save {}: {}", path, err))?;
+        ron::from_str(&contents)
+            .map_err(|err| format!("Could not parse career save {}: {}", path, err))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, contents).map_err(|err| err.to_string())
+    }
+
+    pub fn roster_summary(&self) -> String {
+        let mut summary = String::from("Roster:\n");
+        for roster_gladiator in &self.roster {
+            summary.push_str(&format!(
+                "  {:<12} {:?} lvl {} | {} gold | {}W {}L\n",
+                roster_gladiator.name,
+                roster_gladiator.class,
+                roster_gladiator.level,
+                roster_gladiator.gold,
+                roster_gladiator.wins,
+                roster_gladiator.losses
+            ));
+        }
+        summary
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RosterGladiator {
+    pub id: u32,
+    pub name: String,
+    pub class: Class,
+    pub level: usize,
+    pub xp: f32,
+    /// Names of the items the gladiator owns
+    #[serde(default)]
+    pub equipment: Vec<String>,
+    #[serde(default)]
+    pub gold: u32,
+    /// Healing items bought in the shop, carried into battle by the player
+    #[serde(default)]
+    pub healing_items: Vec<HealingItem>,
+    pub wins: usize,
+    pub losses: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_saved_career_loads_the_same() {
+        let mut save = CareerSave::new_career();
+        save.battles = 4;
+        let veteran = &mut save.roster[0];
+        veteran.level = 3;
+        veteran.xp = 7.5;
+        veteran.gold = 42;
+        veteran.equipment = vec!["Gladius".to_string()];
+        veteran.healing_items = vec![HealingItem::GreenMushroom];
+        veteran.wins = 3;
+        let fallen = save.roster.remove(1);
+        save.fallen.push(fallen);
+
+        let path = std::env::temp_dir().join(format!("career_{}.ron", std::process::id()));
+        let path = path.to_str().unwrap();
+        save.save(path).unwrap();
+        let loaded = CareerSave::load(path);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, Ok(save));
+    }
+
+    #[test]
+    fn a_new_career_has_a_full_roster_with_unique_ids() {
+        let save = CareerSave::new_career();
+
+        assert_eq!(save.roster.len(), CAREER_NAMES.len());
+        assert_eq!(save.next_id as usize, CAREER_NAMES.len());
+        let ids: Vec<u32> = save.roster.iter().map(|gladiator| gladiator.id).collect();
+        assert_eq!(ids, (0..CAREER_NAMES.len() as u32).collect::<Vec<_>>());
+        assert!(save.fallen.is_empty());
+    }
+
+    #[test]
+    fn a_missing_save_is_an_error() {
+        assert!(CareerSave::load("no/such/career.ron").is_err());
+    }
+}
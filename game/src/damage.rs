@@ -0,0 +1,201 @@
+//! The damage formula. An attack's damage is first scaled by the defender's resistance to
+//! its damage type, then the defender's defense is applied with the MitigationModel picked
+//! in assets/combat/combat.ron. Whatever the model, the damage taken is never negative, so
+//! no amount of defense heals the defender.
+
+use serde::Deserialize;
+
+use crate::gladiator::gladiator_components::*;
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// How much health the defender loses to an attack.
+/// * `attack` - the attack, with its (rolled) damage and damage type
+/// * `defense` - the defense of the defender
+/// * `resistances` - how well the defender resists each type of damage
+/// * `mitigation` - how defense reduces the damage
+pub fn damage_taken(
+    attack: &Attack,
+    defense: f32,
+    resistances: &Resistances,
+    mitigation: &MitigationModel,
+) -> f32 {
+    let resistance = resistances.against(attack.damage_type);
+    let damage = attack.damage * (1.0 - resistance).max(0.0);
+    mitigation.mitigate(damage, defense)
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// Ways defense can reduce incoming damage
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum MitigationModel {
+    /// Defense is subtracted from the damage, but every hit does at least `min_damage`
+    /// (or all of its damage, if it does less than that to begin with)
+    Flat { min_damage: f32 },
+    /// Every point of defense takes `per_point` of the damage off, up to `max_reduction`.
+    /// With a per_point of 0.1, 3 defense takes 30% off.
+    Percentage { per_point: f32, max_reduction: f32 },
+    /// Each point of defense is worth less than the one before it, damage is multiplied by
+    /// scale / (scale + defense). Defense equal to the scale halves the damage.
+    DiminishingReturns { scale: f32 },
+}
+
+impl Default for MitigationModel {
+    fn default() -> Self {
+        Self::Flat { min_damage: 0.25 }
+    }
+}
+
+impl MitigationModel {
+    /// Damage left over after defense has been applied, never negative.
+    /// * `damage` - incoming damage
+    /// * `defense` - defense of the defender
+    pub fn mitigate(&self, damage: f32, defense: f32) -> f32 {
+        if damage <= 0.0 {
+            return 0.0;
+        }
+        let defense = defense.max(0.0);
+
+        let mitigated = match *self {
+            Self::Flat { min_damage } => (damage - defense).max(min_damage.min(damage)),
+            Self::Percentage {
+                per_point,
+                max_reduction,
+            } => {
+                let reduction = (defense * per_point).clamp(0.0, max_reduction.clamp(0.0, 1.0));
+                damage * (1.0 - reduction)
+            }
+            Self::DiminishingReturns { scale } => {
+                if scale <= 0.0 {
+                    damage
+                } else {
+                    damage * scale / (scale + defense)
+                }
+            }
+        };
+
+        mitigated.max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < EPSILON,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn flat_subtracts_defense() {
+        let model = MitigationModel::Flat { min_damage: 0.5 };
+        assert_close(model.mitigate(5.0, 2.0), 3.0);
+    }
+
+    #[test]
+    fn flat_never_goes_below_min_damage() {
+        let model = MitigationModel::Flat { min_damage: 0.5 };
+        assert_close(model.mitigate(1.0, 0.9), 0.5);
+        assert_close(model.mitigate(1.0, 10.0), 0.5);
+    }
+
+    #[test]
+    fn flat_min_damage_does_not_exceed_the_hit() {
+        let model = MitigationModel::Flat { min_damage: 0.5 };
+        assert_close(model.mitigate(0.2, 3.0), 0.2);
+    }
+
+    #[test]
+    fn percentage_takes_off_per_point() {
+        let model = MitigationModel::Percentage {
+            per_point: 0.1,
+            max_reduction: 0.8,
+        };
+        assert_close(model.mitigate(10.0, 3.0), 7.0);
+    }
+
+    #[test]
+    fn percentage_is_capped_at_max_reduction() {
+        let model = MitigationModel::Percentage {
+            per_point: 0.1,
+            max_reduction: 0.8,
+        };
+        assert_close(model.mitigate(10.0, 50.0), 2.0);
+    }
+
+    #[test]
+    fn percentage_never_heals_even_with_a_bad_config() {
+        let model = MitigationModel::Percentage {
+            per_point: 1.0,
+            max_reduction: 5.0,
+        };
+        assert_close(model.mitigate(10.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn diminishing_returns_halves_damage_at_scale() {
+        let model = MitigationModel::DiminishingReturns { scale: 4.0 };
+        assert_close(model.mitigate(10.0, 4.0), 5.0);
+    }
+
+    #[test]
+    fn diminishing_returns_each_point_is_worth_less() {
+        let model = MitigationModel::DiminishingReturns { scale: 4.0 };
+        let first_point = model.mitigate(10.0, 0.0) - model.mitigate(10.0, 1.0);
+        let tenth_point = model.mitigate(10.0, 9.0) - model.mitigate(10.0, 10.0);
+        assert!(tenth_point < first_point);
+        assert!(model.mitigate(10.0, 1000.0) > 0.0);
+    }
+
+    #[test]
+    fn no_model_heals() {
+        let models = [
+            MitigationModel::Flat { min_damage: 0.0 },
+            MitigationModel::Percentage {
+                per_point: 0.1,
+                max_reduction: 1.0,
+            },
+            MitigationModel::DiminishingReturns { scale: 1.0 },
+        ];
+        for model in models {
+            for defense in [-5.0, 0.0, 1.0, 100.0] {
+                for damage in [-1.0, 0.0, 0.5, 10.0] {
+                    assert!(model.mitigate(damage, defense) >= 0.0, "{:?}", model);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn negative_defense_does_not_increase_damage() {
+        let model = MitigationModel::Flat { min_damage: 0.0 };
+        assert_close(model.mitigate(3.0, -2.0), 3.0);
+    }
+
+    #[test]
+    fn resistances_apply_before_defense() {
+        let attack = Attack {
+            damage: 10.0,
+            damage_type: DamageType::Fire,
+            ..Default::default()
+        };
+        let resistances = Resistances {
+            fire: 0.5,
+            ..Default::default()
+        };
+        let model = MitigationModel::Flat { min_damage: 0.0 };
+        assert_close(damage_taken(&attack, 2.0, &resistances, &model), 3.0);
+    }
+}
@@ -0,0 +1,635 @@
+//! Long battles stall when the last few gladiators keep missing each other. The arena
+//! director keeps them moving by unleashing hazards on a schedule from assets/director/:
+//!
+//! - SpikeTraps: spikes in some grid locations shoot up every SPIKE_TRAP_CYCLE seconds and
+//!   hurt whoever stands there
+//! - Lion: a lion is let into the arena and hunts the nearest gladiator until it is caged
+//!   again. Gladiators can't fight back.
+//! - SafeZone: the safe part of the arena shrinks toward the middle, gladiators outside it
+//!   take damage and head back in
+//! - CrowdThrow: the crowd throws items into the arena, picked up like the items of item
+//!   spawners (see arena)
+//!
+//! Spike traps and lions attack like gladiators do, with an AttackEvent, so they can miss
+//! and armor helps. Their AttackEvents have no attacker, nobody gets credit for gladiators
+//! the arena kills.
+//!
+//! Modes with more than one battle, e.g. the career, restart the director for every
+//! battle with restart_director and clear the hazards away afterwards with clear_hazards.
+//!
+//! Pick the schedule with:
+//!  cargo run -- --director colosseum   (default)
+//!  cargo run -- --director calm        (no hazards)
+
+use bevy::{prelude::*, time::FixedTimestep};
+use rand::{self, seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::*,
+    gladiator::{gladiator_components::*, gladiator_equipment::*, gladiator_events::*},
+    grid::*,
+    helper_functions::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+pub struct DirectorPlugin {
+    /// Name of the schedule file in assets/director/, without the extension
+    pub schedule: String,
+}
+
+impl DirectorPlugin {
+    /// Reads the schedule from `--director <name>` in the command line arguments,
+    /// DEFAULT_DIRECTOR_SCHEDULE without it
+    /// * `args` - command line arguments, usually std::env::args()
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        Self {
+            schedule: arg_value(args, "--director")
+                .unwrap_or_else(|| DEFAULT_DIRECTOR_SCHEDULE.to_string()),
+        }
+    }
+}
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        let path = format!("{}{}.ron", DIRECTOR_PATH, self.schedule);
+        let schedule: DirectorSchedule =
+            load_data_file(&path).expect("Director schedule should be valid.");
+
+        app.register_type::<SpikeTrap>()
+            .register_type::<Lion>()
+            .register_type::<SafeZoneShade>()
+            .insert_resource(ArenaDirector::new(schedule))
+            .init_resource::<SafeZone>()
+            .add_event::<HazardEvent>()
+            .add_startup_system(spawn_safe_zone_shades)
+            .add_system(direct_arena)
+            .add_system(start_hazards.after(direct_arena))
+            .add_system(spring_spike_traps)
+            .add_system(hunt_with_lions)
+            .add_system(shrink_safe_zone)
+            .add_system(draw_safe_zone.after(shrink_safe_zone))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(STATUS_EFFECT_STEP as f64))
+                    .with_system(hurt_outside_safe_zone),
+            );
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Sends a HazardEvent for every hazard of the schedule that is due
+fn direct_arena(
+    time: Res<Time>,
+    mut director: ResMut<ArenaDirector>,
+    mut ev_hazard: EventWriter<HazardEvent>,
+) {
+    if director.paused {
+        return;
+    }
+    for hazard in director.advance(time.delta_seconds()) {
+        println!("The arena director unleashes {:?}!", hazard);
+        ev_hazard.send(HazardEvent(hazard));
+    }
+}
+
+/// Puts the hazards of the HazardEvents into the arena
+fn start_hazards(
+    mut commands: Commands,
+    mut ev_hazard: EventReader<HazardEvent>,
+    arena_grid: Res<ArenaGrid>,
+    item_catalog: Option<Res<ItemCatalog>>,
+    mut safe_zone: ResMut<SafeZone>,
+) {
+    let mut rng = rand::thread_rng();
+    for HazardEvent(hazard) in ev_hazard.iter() {
+        match hazard {
+            Hazard::SpikeTraps {
+                locations,
+                random,
+                damage,
+                duration,
+            } => {
+                let random_locations =
+                    (0..*random).filter_map(|_| random_open_location(&arena_grid, &mut rng));
+                for location in locations.iter().copied().chain(random_locations) {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: SPIKE_TRAP_COLOR,
+                                custom_size: Some(ArenaGrid::cell_size()),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(
+                                ArenaGrid::center_of(location).extend(HAZARD_Z),
+                            ),
+                            ..default()
+                        },
+                        SpikeTrap {
+                            location,
+                            damage: *damage,
+                            cycle: Timer::from_seconds(SPIKE_TRAP_CYCLE, TimerMode::Repeating),
+                            lifetime: Timer::from_seconds(*duration, TimerMode::Once),
+                        },
+                    ));
+                }
+            }
+            Hazard::Lion {
+                damage,
+                speed,
+                duration,
+            } => {
+                let location = match random_open_location(&arena_grid, &mut rng) {
+                    Some(location) => location,
+                    None => continue,
+                };
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: LION_COLOR,
+                            custom_size: Some(ArenaGrid::cell_size() * LION_SIZE),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(
+                            ArenaGrid::center_of(location).extend(LION_Z),
+                        ),
+                        ..default()
+                    },
+                    Lion {
+                        damage: *damage,
+                        speed: *speed,
+                        attack_timer: Timer::from_seconds(LION_ATTACK_STEP, TimerMode::Repeating),
+                        lifetime: Timer::from_seconds(*duration, TimerMode::Once),
+                    },
+                ));
+            }
+            Hazard::SafeZone {
+                to,
+                over,
+                damage_per_second,
+            } => safe_zone.shrink(*to, *over, *damage_per_second),
+            Hazard::CrowdThrow { items, count } => {
+                let known_items: Vec<&String> = items
+                    .iter()
+                    .filter(|item| {
+                        item_catalog
+                            .as_ref()
+                            .map_or(false, |catalog| catalog.get(item).is_some())
+                    })
+                    .collect();
+                if known_items.len() < items.len() {
+                    println!("The crowd can only throw items from the item catalog.");
+                }
+                for _ in 0..*count {
+                    let item = match known_items.choose(&mut rng) {
+                        Some(item) => item,
+                        None => break,
+                    };
+                    if let Some(location) = random_open_location(&arena_grid, &mut rng) {
+                        spawn_item_pickup(
+                            &mut commands,
+                            item,
+                            ArenaGrid::center_of(location),
+                            None,
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Spike traps shoot up every SPIKE_TRAP_CYCLE and attack everybody in their grid
+/// location, until they are taken out again
+fn spring_spike_traps(
+    mut commands: Commands,
+    time: Res<Time>,
+    arena_grid: Res<ArenaGrid>,
+    mut ev_attack: EventWriter<AttackEvent>,
+    mut traps: Query<(Entity, &mut SpikeTrap)>,
+) {
+    for (entity, mut trap) in traps.iter_mut() {
+        if trap.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if !trap.cycle.tick(time.delta()).just_finished() {
+            continue;
+        }
+        for target in arena_grid.entities_at(&trap.location) {
+            ev_attack.send(AttackEvent {
+                target,
+                attacker: None,
+                attack: Attack {
+                    damage: trap.damage,
+                    damage_type: DamageType::Pierce,
+                    accuracy: SPIKE_TRAP_ACCURACY,
+                    crit_chance: 0.0,
+                },
+                on_hit: None,
+            });
+        }
+    }
+}
+
+/// Lions run at the nearest gladiator and maul it, until they are caged again
+fn hunt_with_lions(
+    mut commands: Commands,
+    time: Res<Time>,
+    arena_grid: Res<ArenaGrid>,
+    mut ev_attack: EventWriter<AttackEvent>,
+    mut lions: Query<(Entity, &mut Transform, &mut Lion), Without<Gladiator>>,
+    gladiators: Query<&Transform, With<Gladiator>>,
+) {
+    for (entity, mut transform, mut lion) in lions.iter_mut() {
+        if lion.lifetime.tick(time.delta()).finished() {
+            println!("The lion is back in its cage.");
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        let prey = arena_grid
+            .nearest::<1>(position, WINDOW_WIDTH, |gladiator| {
+                gladiators
+                    .get(gladiator)
+                    .ok()
+                    .map(|transform| transform.translation.truncate())
+            })
+            .first();
+        let (prey, prey_position) = match prey {
+            Some(prey) => prey,
+            None => continue,
+        };
+
+        if position.distance(prey_position) <= LION_REACH {
+            if lion.attack_timer.tick(time.delta()).just_finished() {
+                ev_attack.send(AttackEvent {
+                    target: prey,
+                    attacker: None,
+                    attack: Attack {
+                        damage: lion.damage,
+                        damage_type: DamageType::Slash,
+                        accuracy: LION_ACCURACY,
+                        crit_chance: LION_CRIT_CHANCE,
+                    },
+                    on_hit: None,
+                });
+            }
+        } else {
+            let delta =
+                (prey_position - position).normalize_or_zero() * lion.speed * time.delta_seconds();
+            transform.translation += terrain_step(&arena_grid, position, delta).extend(0.0);
+        }
+    }
+}
+
+/// Moves the edge of the safe zone toward where it is shrinking to
+fn shrink_safe_zone(time: Res<Time>, mut safe_zone: ResMut<SafeZone>) {
+    if safe_zone.size > safe_zone.target_size {
+        let size = safe_zone.size - safe_zone.shrink_speed * time.delta_seconds();
+        safe_zone.size = size.max(safe_zone.target_size);
+    }
+}
+
+/// Gladiators outside the safe zone take damage every STATUS_EFFECT_STEP
+fn hurt_outside_safe_zone(
+    safe_zone: Res<SafeZone>,
+    mut ev_death: EventWriter<DeathEvent>,
+    mut query: Query<(Entity, &Transform, &mut Health, &Level), With<Gladiator>>,
+) {
+    if safe_zone.damage_per_second <= 0.0 {
+        return;
+    }
+    for (entity, transform, mut health, level) in &mut query {
+        if safe_zone.contains(transform.translation.truncate()) {
+            continue;
+        }
+        // gladiators that were already dying had their DeathEvent sent by whatever did it
+        let was_alive = health.value >= 0.0;
+        health.value -= safe_zone.damage_per_second * STATUS_EFFECT_STEP;
+        if was_alive && health.value < 0.0 {
+            println!("{:?} was caught outside the safe zone!", entity);
+            ev_death.send(DeathEvent {
+                victor: None,
+                xp_earned: level.convert_to_xp(),
+                slain: entity,
+            });
+        }
+    }
+}
+
+/// Starts the schedule and the safe zone over for a new battle
+pub fn restart_director(
+    director: Option<ResMut<ArenaDirector>>,
+    safe_zone: Option<ResMut<SafeZone>>,
+) {
+    if let Some(mut director) = director {
+        director.restart();
+    }
+    if let Some(mut safe_zone) = safe_zone {
+        *safe_zone = SafeZone::default();
+    }
+}
+
+/// Takes the spike traps, lions and whatever the crowd threw out of the arena and holds
+/// the schedule until restart_director, e.g. once a battle is over
+pub fn clear_hazards(
+    mut commands: Commands,
+    director: Option<ResMut<ArenaDirector>>,
+    hazards: Query<Entity, Or<(With<SpikeTrap>, With<Lion>)>>,
+    pickups: Query<(Entity, &ItemPickup)>,
+) {
+    if let Some(mut director) = director {
+        director.paused = true;
+    }
+    for entity in &hazards {
+        commands.entity(entity).despawn();
+    }
+    // items of the map's spawners stay for the next battle
+    for (entity, _) in pickups
+        .iter()
+        .filter(|(_, pickup)| pickup.spawner.is_none())
+    {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// One shade for every side of the arena, they darken everything outside the safe zone
+fn spawn_safe_zone_shades(mut commands: Commands) {
+    for shade in [
+        SafeZoneShade::Top,
+        SafeZoneShade::Bottom,
+        SafeZoneShade::Left,
+        SafeZoneShade::Right,
+    ] {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: SAFE_ZONE_SHADE_COLOR,
+                    ..default()
+                },
+                ..default()
+            },
+            shade,
+        ));
+    }
+}
+
+/// Fits the shades around the safe zone
+fn draw_safe_zone(
+    safe_zone: Res<SafeZone>,
+    mut shades: Query<(&mut Transform, &mut Sprite, &SafeZoneShade)>,
+) {
+    if !safe_zone.is_changed() {
+        return;
+    }
+    for (mut transform, mut sprite, shade) in shades.iter_mut() {
+        let (center, size) = shade.rect(safe_zone.half_size());
+        transform.translation = center.extend(SAFE_ZONE_Z);
+        sprite.custom_size = Some(size);
+    }
+}
+
+/// A grid location in the arena that gladiators can walk into, None when none was found
+/// in SPAWN_ATTEMPTS tries
+fn random_open_location(arena_grid: &ArenaGrid, rng: &mut impl Rng) -> Option<GridLocation> {
+    let cell_size = ArenaGrid::cell_size();
+    let max_x = (WINDOW_WIDTH / 2.0 / cell_size.x) as i32;
+    let max_y = (WINDOW_HEIGHT / 2.0 / cell_size.y) as i32;
+    (0..SPAWN_ATTEMPTS)
+        .map(|_| GridLocation {
+            x: rng.gen_range(-max_x..=max_x),
+            y: rng.gen_range(-max_y..=max_y),
+        })
+        .find(|location| !arena_grid.is_blocked(location))
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// When the arena director unleashes which hazard, from a file in assets/director/
+#[derive(Default, Deserialize)]
+pub struct DirectorSchedule {
+    #[serde(default)]
+    pub hazards: Vec<ScheduledHazard>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ScheduledHazard {
+    /// Seconds into the battle
+    pub at: f32,
+    /// Seconds until it happens again, only once when None
+    #[serde(default)]
+    pub every: Option<f32>,
+    pub hazard: Hazard,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum Hazard {
+    SpikeTraps {
+        /// Grid locations that get a trap
+        #[serde(default)]
+        locations: Vec<GridLocation>,
+        /// How many traps go into random grid locations on top of those
+        #[serde(default)]
+        random: usize,
+        /// Damage of every time the spikes shoot up
+        damage: f32,
+        /// Seconds until the traps are taken out again
+        duration: f32,
+    },
+    Lion {
+        damage: f32,
+        /// Pixels per second
+        speed: f32,
+        /// Seconds until it is caged again
+        duration: f32,
+    },
+    SafeZone {
+        /// Fraction of the arena that is still safe once it is done shrinking
+        to: f32,
+        /// Seconds it takes to shrink
+        over: f32,
+        /// Damage per second to gladiators outside it
+        damage_per_second: f32,
+    },
+    CrowdThrow {
+        /// Names of the items in the ItemCatalog the crowd picks from
+        items: Vec<String>,
+        count: usize,
+    },
+}
+
+/// Sent by the arena director when a hazard is due
+pub struct HazardEvent(pub Hazard);
+
+/// Keeps track of the battle time and which hazard of the schedule is due next
+#[derive(Resource)]
+pub struct ArenaDirector {
+    pub schedule: Vec<ScheduledHazard>,
+    /// Seconds since the battle started
+    pub elapsed: f32,
+    /// No hazards come and the clock stands still while paused, e.g. between battles
+    pub paused: bool,
+    /// When each hazard of the schedule happens next, None once it is done
+    next: Vec<Option<f32>>,
+}
+
+impl ArenaDirector {
+    pub fn new(schedule: DirectorSchedule) -> Self {
+        Self {
+            next: schedule
+                .hazards
+                .iter()
+                .map(|scheduled| Some(scheduled.at))
+                .collect(),
+            schedule: schedule.hazards,
+            elapsed: 0.0,
+            paused: false,
+        }
+    }
+
+    /// Back to the start of the schedule, for a new battle
+    pub fn restart(&mut self) {
+        self.next = self
+            .schedule
+            .iter()
+            .map(|scheduled| Some(scheduled.at))
+            .collect();
+        self.elapsed = 0.0;
+        self.paused = false;
+    }
+
+    /// Moves the battle time forward and returns the hazards that came due, in the order
+    /// of the schedule
+    /// * `seconds` - how much time passed
+    pub fn advance(&mut self, seconds: f32) -> Vec<Hazard> {
+        self.elapsed += seconds;
+        let mut due = Vec::new();
+        for (scheduled, next) in self.schedule.iter().zip(self.next.iter_mut()) {
+            while let Some(at) = *next {
+                if at > self.elapsed {
+                    break;
+                }
+                due.push(scheduled.hazard.clone());
+                // a hazard can't repeat more often than every frame
+                *next = scheduled
+                    .every
+                    .filter(|every| *every > 0.0)
+                    .map(|every| at + every);
+            }
+        }
+        due
+    }
+}
+
+/// Part of the arena that is safe, centered on the middle of the arena and with the same
+/// proportions
+#[derive(Resource)]
+pub struct SafeZone {
+    /// Fraction of the arena that is safe, 1.0 for all of it
+    pub size: f32,
+    /// Fraction it is shrinking toward
+    pub target_size: f32,
+    /// Fraction per second
+    pub shrink_speed: f32,
+    pub damage_per_second: f32,
+}
+
+impl Default for SafeZone {
+    fn default() -> Self {
+        Self {
+            size: 1.0,
+            target_size: 1.0,
+            shrink_speed: 0.0,
+            damage_per_second: 0.0,
+        }
+    }
+}
+
+impl SafeZone {
+    /// Starts shrinking the safe zone, never growing it
+    /// * `to` - fraction of the arena that is still safe once it is done shrinking
+    /// * `over` - seconds it takes to shrink
+    /// * `damage_per_second` - damage to gladiators outside it
+    pub fn shrink(&mut self, to: f32, over: f32, damage_per_second: f32) {
+        self.target_size = to.clamp(0.0, self.size);
+        self.shrink_speed = if over > 0.0 {
+            (self.size - self.target_size) / over
+        } else {
+            f32::INFINITY
+        };
+        self.damage_per_second = damage_per_second;
+    }
+
+    /// Half the width and height of the safe zone in pixels
+    pub fn half_size(&self) -> Vec2 {
+        Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) / 2.0 * self.size
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        let half_size = self.half_size();
+        position.x.abs() <= half_size.x && position.y.abs() <= half_size.y
+    }
+}
+
+/// A trap in a grid location that stabs whoever stands there every SPIKE_TRAP_CYCLE
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct SpikeTrap {
+    pub location: GridLocation,
+    pub damage: f32,
+    pub cycle: Timer,
+    /// Time until the trap is taken out
+    pub lifetime: Timer,
+}
+
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Lion {
+    pub damage: f32,
+    /// Pixels per second
+    pub speed: f32,
+    pub attack_timer: Timer,
+    /// Time until the lion is caged again
+    pub lifetime: Timer,
+}
+
+/// Darkens one side of the arena outside the safe zone
+#[derive(Component, Clone, Copy, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub enum SafeZoneShade {
+    #[default]
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl SafeZoneShade {
+    /// Center and size of the shade around a safe zone. Gladiators can spawn as far as a
+    /// window away from the middle, so the shades reach that far too.
+    /// * `half_size` - half the width and height of the safe zone
+    fn rect(&self, half_size: Vec2) -> (Vec2, Vec2) {
+        let outer = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+        let vertical = (outer.y + half_size.y) / 2.0;
+        let horizontal = (outer.x + half_size.x) / 2.0;
+        let top_and_bottom = Vec2::new(2.0 * outer.x, outer.y - half_size.y);
+        let sides = Vec2::new(outer.x - half_size.x, 2.0 * half_size.y);
+        match self {
+            Self::Top => (Vec2::new(0.0, vertical), top_and_bottom),
+            Self::Bottom => (Vec2::new(0.0, -vertical), top_and_bottom),
+            Self::Left => (Vec2::new(-horizontal, 0.0), sides),
+            Self::Right => (Vec2::new(horizontal, 0.0), sides),
+        }
+    }
+}
@@ -1,6 +1,19 @@
-use bevy::prelude::*;
+use bevy::{
+    ecs::{
+        entity::{EntityMap, MapEntities, MapEntitiesError},
+        reflect::ReflectMapEntities,
+    },
+    prelude::*,
+    utils::HashSet,
+};
+use serde::{Deserialize, Serialize};
 
-use crate::grid::*;
+use crate::{
+    gladiator::{gladiator_equipment::*, gladiator_morale::*},
+    grid::*,
+    team::*,
+    *, // game_lib
+};
 
 /// Plan for this design.
 /// We are going to run movement systems first.
@@ -14,7 +27,8 @@ pub struct EngagementManagerPlugin;
 
 impl Plugin for EngagementManagerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(engagement_builder);
+        app.register_type::<Engagement>()
+            .add_system(engagement_builder);
     }
 }
 
@@ -29,46 +43,199 @@ impl Plugin for EngagementManagerPlugin {
 //     }
 // }
 
-// TODO: Good docstrings on how/why this is used in this way.
-fn engagement_builder(
+/// Teammates are never paired, so each gladiator is paired with the next unengaged
+/// gladiator in the same grid location that is not on its team. Gladiators with a ranged
+/// weapon that are still unengaged afterwards look for an opponent in the grid locations
+/// within the range of their weapon, their own grid location included: an enemy there
+/// that was left over from the pairing is shot point blank. Those engagements are
+/// one-way: only the shooter is engaged, its target can't reach back and is free to close
+/// in or walk off. Gladiators fleeing from a fight are never engaged.
+///
+/// Only the grid locations that changed since the last run are looked at (see
+/// ArenaGrid), ranged gladiators only when a grid location within their range changed.
+/// Every ENGAGEMENT_SWEEP_STEP all of them are looked at, which catches the few things
+/// that don't change the grid, like a gladiator that stops fleeing.
+pub fn engagement_builder(
     mut commands: Commands,
-    arena_grid: Res<ArenaGrid>,
-    gladiator_query: Query<&Engagement>,
+    time: Res<Time>,
+    mut since_sweep: Local<f32>,
+    mut arena_grid: ResMut<ArenaGrid>,
+    gladiator_query: Query<(
+        Option<&Engagement>,
+        Option<&Team>,
+        Option<&Weapon>,
+        Option<&Morale>,
+    )>,
+    ranged_query: Query<(Entity, &Weapon, Option<&Team>, Option<&Morale>), Without<Engagement>>,
 ) {
-    for group in arena_grid.grid_map.values() {
-        let mut colocated_and_unengaged = Vec::new();
-        for entity in group {
-            match gladiator_query.get(*entity) {
-                Ok(_engagement) => {} // already engaged, not going to engage additionally
-                Err(_) => colocated_and_unengaged.push(entity), // this gladiator is not engaged, so they are eligible
+    *since_sweep += time.delta_seconds();
+    if *since_sweep >= ENGAGEMENT_SWEEP_STEP {
+        *since_sweep = 0.0;
+        arena_grid.mark_all_dirty();
+    }
+
+    // gladiators engaged by this run, their Engagement is only inserted once commands apply
+    let mut newly_engaged = HashSet::default();
+    let mut colocated_and_unengaged = Vec::new();
+    let mut paired = Vec::new();
+
+    for location in arena_grid.dirty_locations() {
+        colocated_and_unengaged.clear();
+        for entity in arena_grid.entities_at(&location) {
+            match gladiator_query.get(entity) {
+                Ok((Some(_engagement), ..)) => {} // already engaged, not going to engage additionally
+                Ok((None, _, _, morale)) if Morale::is_fleeing(morale) => {} // running away, not looking for a fight
+                Ok((None, team, ..)) => colocated_and_unengaged.push((entity, team)), // this gladiator is not engaged, so they are eligible
+                Err(_) => {} // no longer in the ECS
             }
         }
 
-        let n_engagements = colocated_and_unengaged.len() / 2;
-        for idx in 0..n_engagements {
-            let &gladiator_a = colocated_and_unengaged
-                .get(2 * idx)
-                .expect("Already checked that this index should exist.");
-            let &gladiator_b = colocated_and_unengaged
-                .get(2 * idx + 1)
-                .expect("Already checked that this index should exist.");
-            println!(
-                "New engagement between {:?} and {:?}!",
-                gladiator_a, gladiator_b
-            );
-            // spawn the pair of Engagement(Targets) on the relevant entities
-            // this time not with commands.spawn but instead entity.insert()
-            commands.entity(*gladiator_a).insert(Engagement {
-                target: *gladiator_b,
-            });
-            commands.entity(*gladiator_b).insert(Engagement {
-                target: *gladiator_a,
+        paired.clear();
+        paired.resize(colocated_and_unengaged.len(), false);
+        for idx_a in 0..colocated_and_unengaged.len() {
+            if paired[idx_a] {
+                continue;
+            }
+            let (gladiator_a, team_a) = colocated_and_unengaged[idx_a];
+
+            let opponent = (idx_a + 1..colocated_and_unengaged.len()).find(|&idx_b| {
+                let (_, team_b) = colocated_and_unengaged[idx_b];
+                !paired[idx_b] && !Team::are_teammates(team_a, team_b)
             });
+            let idx_b = match opponent {
+                Some(idx_b) => idx_b,
+                None => continue, // only teammates left, nobody to fight here
+            };
+            let (gladiator_b, _) = colocated_and_unengaged[idx_b];
+            paired[idx_a] = true;
+            paired[idx_b] = true;
+
+            engage(&mut commands, gladiator_a, gladiator_b, &mut newly_engaged);
+        }
+    }
+
+    for (entity, weapon, team, morale) in &ranged_query {
+        let range = weapon.weapon_type.range();
+        if range == 0 || Morale::is_fleeing(morale) || newly_engaged.contains(&entity) {
+            continue;
+        }
+        let location = match arena_grid.location_of(entity) {
+            Some(location) => location,
+            None => continue, // not in the grid until the next PostUpdate
+        };
+        if !arena_grid.is_dirty_around(location, range) {
+            continue; // nothing changed within range since the last look
+        }
+
+        // entities_around includes the shooter's own grid location, and so the shooter
+        let opponent = arena_grid.entities_around(location, range).find(|other| {
+            *other != entity
+                && !newly_engaged.contains(other)
+                && matches!(
+                    gladiator_query.get(*other),
+                    Ok((None, other_team, _, other_morale))
+                        if !Team::are_teammates(team, other_team)
+                            && !Morale::is_fleeing(other_morale)
+                )
+        });
+
+        if let Some(opponent) = opponent {
+            engage_from_range(&mut commands, entity, opponent, &mut newly_engaged);
         }
     }
+
+    arena_grid.clear_dirty();
+}
+
+/// Spawns the pair of Engagement(Targets) on the two gladiators
+fn engage(
+    commands: &mut Commands,
+    gladiator_a: Entity,
+    gladiator_b: Entity,
+    newly_engaged: &mut HashSet<Entity>,
+) {
+    println!(
+        "New engagement between {:?} and {:?}!",
+        gladiator_a, gladiator_b
+    );
+    // this time not with commands.spawn but instead entity.insert(). Both or neither, one
+    // of them may have been despawned by the time commands apply, e.g. killed this frame.
+    commands.add(move |world: &mut World| {
+        if world.get_entity(gladiator_a).is_none() || world.get_entity(gladiator_b).is_none() {
+            return;
+        }
+        world.entity_mut(gladiator_a).insert(Engagement {
+            target: gladiator_b,
+        });
+        world.entity_mut(gladiator_b).insert(Engagement {
+            target: gladiator_a,
+        });
+    });
+    newly_engaged.insert(gladiator_a);
+    newly_engaged.insert(gladiator_b);
+}
+
+/// Engages a ranged gladiator with a target that doesn't fight back
+fn engage_from_range(
+    commands: &mut Commands,
+    shooter: Entity,
+    target: Entity,
+    newly_engaged: &mut HashSet<Entity>,
+) {
+    println!("{:?} takes aim at {:?}!", shooter, target);
+    commands.add(move |world: &mut World| {
+        if world.get_entity(target).is_none() {
+            return;
+        }
+        if let Some(mut shooter) = world.get_entity_mut(shooter) {
+            shooter.insert(Engagement { target });
+        }
+    });
+    newly_engaged.insert(shooter);
+}
+
+/// Ends the Engagement of a gladiator, and that of its target if the target is fighting it
+/// back. The target of a ranged engagement may be fighting somebody else.
+pub fn disengage(commands: &mut Commands, gladiator: Entity) {
+    commands.add(move |world: &mut World| {
+        let engagement = world
+            .get_entity_mut(gladiator)
+            .and_then(|mut gladiator| gladiator.remove::<Engagement>());
+        let target = match engagement {
+            Some(engagement) => engagement.target,
+            None => return,
+        };
+        if let Some(mut target) = world.get_entity_mut(target) {
+            let fighting_back = target
+                .get::<Engagement>()
+                .map_or(false, |engagement| engagement.target == gladiator);
+            if fighting_back {
+                target.remove::<Engagement>();
+            }
+        }
+    });
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component, MapEntities)]
 pub struct Engagement {
     pub target: Entity,
 }
+
+// Reflection needs to be able to build a placeholder Engagement before applying the
+// real values on top of it, same as bevy's own Parent component. An Engagement should
+// only ever be inserted with a real target by the engagement_builder.
+impl FromWorld for Engagement {
+    fn from_world(_world: &mut World) -> Self {
+        Engagement {
+            target: Entity::from_raw(u32::MAX),
+        }
+    }
+}
+
+impl MapEntities for Engagement {
+    fn map_entities(&mut self, entity_map: &EntityMap) -> Result<(), MapEntitiesError> {
+        self.target = entity_map.get(self.target)?;
+        Ok(())
+    }
+}
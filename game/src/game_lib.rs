@@ -1,11 +1,26 @@
 use bevy::prelude::*;
 
 pub mod animation;
+pub mod arena;
+pub mod career;
+pub mod damage;
+pub mod director;
 pub mod engagements;
 pub mod gladiator;
 pub mod grid;
+pub mod headless;
 pub mod helper_functions;
+#[cfg(feature = "inspector")]
+pub mod inspector;
 pub mod player;
+pub mod scenario;
+#[cfg(feature = "scripting")]
+pub mod scripting;
+pub mod shop;
+pub mod survival;
+pub mod team;
+pub mod tiled_map;
+pub mod tournament;
 
 // TODO several of these constsants could be pushed into a lazy static to deal with their coupling.
 // probably several of them could be calculated based on the number of gladiators relative to the
@@ -14,6 +29,48 @@ pub mod player;
 /// Grid constants
 pub const GRID_HORIZONTAL_DIVISIONS: f32 = 42.; // vertical divisions calculated by this * (window) height/width
 pub const GRID_EVALUATION_STEP: f32 = 1.0 / 30.0;
+pub const GRID_VERIFICATION_STEP: f32 = 1.0; // how often the grid is checked against the Transforms (in seconds)
+
+/// Arena constants
+pub const MAPS_PATH: &str = "maps/";
+pub const DEFAULT_MAP: &str = "colosseum";
+pub const SAND_SPEED_MODIFIER: f32 = 0.5;
+pub const TERRAIN_Z: f32 = -100.0; // over the background, under the gladiators
+pub const SAND_COLOR: Color = Color::rgba(0.85, 0.7, 0.4, 0.6);
+pub const WATER_COLOR: Color = Color::rgba(0.2, 0.4, 0.8, 0.8);
+pub const PILLAR_COLOR: Color = Color::rgb(0.55, 0.5, 0.45);
+pub const WALL_COLOR: Color = Color::rgb(0.4, 0.35, 0.3);
+pub const SPAWN_ATTEMPTS: usize = 10; // tries to find a spot for a gladiator that isn't blocked
+pub const SPAWN_POINT_JITTER: f32 = 0.4; // how far from a spawn point gladiators spawn (in grid locations)
+pub const BACKGROUND_Z: f32 = -900.0;
+pub const TILE_LAYER_Z_STEP: f32 = 1.0; // how much closer every Tiled layer is than the one under it
+pub const ITEM_Z: f32 = -50.0; // over the terrain, under the gladiators
+pub const ITEM_COLOR: Color = Color::rgb(1.0, 0.85, 0.2);
+pub const ITEM_SIZE: f32 = 0.5; // of a grid location
+pub const ITEM_RESPAWN_INTERVAL: f32 = 20.0; // seconds until a picked up item is replaced
+
+/// Director constants
+pub const DIRECTOR_PATH: &str = "director/";
+pub const DEFAULT_DIRECTOR_SCHEDULE: &str = "colosseum";
+pub const HAZARD_Z: f32 = -75.0; // over the terrain, under items and gladiators
+pub const SPIKE_TRAP_CYCLE: f32 = 2.0; // seconds between the spikes shooting up
+pub const SPIKE_TRAP_ACCURACY: f32 = 0.8;
+pub const SPIKE_TRAP_COLOR: Color = Color::rgba(0.6, 0.6, 0.65, 0.8);
+pub const LION_REACH: f32 = 20.0; // how close a lion has to be to maul a gladiator (in pixels)
+pub const LION_ATTACK_STEP: f32 = 1.0; // seconds between a lion's attacks
+pub const LION_ACCURACY: f32 = 0.9;
+pub const LION_CRIT_CHANCE: f32 = 0.2;
+pub const LION_SIZE: f32 = 1.2; // of a grid location
+pub const LION_Z: f32 = 2.0; // over the gladiators
+pub const LION_COLOR: Color = Color::rgb(0.8, 0.55, 0.2);
+pub const SAFE_ZONE_Z: f32 = 10.0; // over everything in the arena
+pub const SAFE_ZONE_SHADE_COLOR: Color = Color::rgba(0.5, 0.0, 0.0, 0.35);
+
+/// Engagement constants
+pub const ENGAGEMENT_SWEEP_STEP: f32 = 0.5; // how often every grid location is checked for new engagements (in seconds)
+
+/// Asset constants
+pub const ASSETS_PATH: &str = "assets";
 
 /// Game window constants
 pub const WINDOW_WIDTH: f32 = 1280.;
@@ -22,6 +79,86 @@ pub const BACKGROUND_WIDTH: f32 = 6020.;
 pub const BACKGROUND_HEIGHT: f32 = 3920.;
 pub const CLEAR: Color = Color::rgb(0.1, 0.1, 0.1);
 
+/// Team constants
+pub const CLASS_TEAM_SIZE: usize = 10; // gladiators per class in class-vs-class battles
+pub const TEAM_COLORS: [Color; 6] = [
+    Color::rgb(1.0, 0.5, 0.5),
+    Color::rgb(0.5, 0.6, 1.0),
+    Color::rgb(0.5, 1.0, 0.5),
+    Color::rgb(1.0, 1.0, 0.4),
+    Color::rgb(1.0, 0.5, 1.0),
+    Color::rgb(0.4, 1.0, 1.0),
+];
+
+/// Survival constants
+pub const SURVIVAL_FIRST_WAVE_SIZE: usize = 3;
+pub const SURVIVAL_WAVE_GROWTH: usize = 2; // how many more gladiators each wave brings
+pub const SURVIVAL_LEVEL_UP_WAVES: usize = 2; // waves level up once every this many waves
+pub const SURVIVAL_REST_DURATION: f32 = 5.0; // seconds between waves
+pub const SURVIVAL_REST_HEALING: f32 = 10.0; // health per second the player recovers between waves
+pub const SURVIVAL_ENEMY_TEAM: u32 = 1;
+pub const SURVIVAL_PLAYER_HEALTH: f32 = 150.0; // lasts a few waves if the player fights back, a gladiator has 8 to 15
+
+/// Career constants
+pub const CAREER_SAVE_PATH: &str = "saves/career.ron";
+pub const CAREER_BATTLE_SIZE: usize = 5; // how many roster gladiators fight in a battle
+pub const CAREER_TEAM: u32 = 0;
+pub const CAREER_OPPONENT_TEAM: u32 = 1;
+pub const CAREER_NAMES: [&str; 8] = [
+    "Spartacus",
+    "Crixus",
+    "Gannicus",
+    "Oenomaus",
+    "Varro",
+    "Agron",
+    "Naevia",
+    "Mira",
+];
+
+/// Economy constants
+pub const GOLD_PER_KILL: u32 = 10;
+pub const GOLD_FOR_SURVIVING: u32 = 15; // awarded to career gladiators alive at the end of a battle
+pub const SHOP_CATALOG_PATH: &str = "shop/catalog.ron";
+
+/// Equipment constants
+pub const ITEMS_PATH: &str = "items/items.ron";
+pub const CLASSES_PATH: &str = "classes/classes.ron";
+
+/// Combat constants
+pub const COMBAT_CONFIG_PATH: &str = "combat/combat.ron";
+
+/// Tournament constants
+pub const TOURNAMENTS_PATH: &str = "tournaments/";
+pub const DEFAULT_TOURNAMENT_ROSTER: &str = "classic"; // played when the roster asked for can't be loaded
+pub const DUEL_DISTANCE: f32 = 20.0; // how far apart duelists start, within one grid location
+pub const DUEL_INTERMISSION: f32 = 2.0; // seconds between duels
+
+/// Morale constants
+pub const MORALE_STEP: f32 = 0.5; // how often morale is re-evaluated (in seconds)
+pub const MORALE_RADIUS: i32 = 2; // grid locations around a gladiator that count as nearby
+pub const MORALE_BASE: f32 = 0.5;
+pub const MORALE_HEALTH_WEIGHT: f32 = 0.6; // full health adds half of this, near death takes half off
+pub const MORALE_CROWD_WEIGHT: f32 = 0.3; // only allies nearby adds this, only enemies takes it off
+pub const MORALE_PER_KILL: f32 = 0.15;
+pub const MORALE_KILL_DECAY: f32 = 0.01; // kill boost lost per second
+pub const MORALE_FLEE_THRESHOLD: f32 = 0.2; // morale breaks below this
+pub const MORALE_RALLY_THRESHOLD: f32 = 0.35; // fleeing gladiators stop running above this
+pub const MORALE_EAGER_THRESHOLD: f32 = 0.75; // gladiators charge the nearest enemy above this
+
+/// AI constants
+pub const AI_STEP: f32 = 0.25; // how often AI gladiators reconsider what to do (in seconds)
+pub const AI_SIGHT: i32 = 3; // grid locations around a gladiator that it can see
+
+/// Scripting constants
+pub const SCRIPTS_PATH: &str = "scripts/";
+pub const SCRIPT_MAX_OPERATIONS: u64 = 10_000; // budget of every call to a brain's think function
+pub const SCRIPT_MAX_CALL_LEVELS: usize = 16;
+pub const SCRIPT_MAX_STRING_SIZE: usize = 1024;
+pub const SCRIPT_MAX_COLLECTION_SIZE: usize = 1024; // arrays and maps
+
+/// Scenario constants
+pub const SCENARIOS_PATH: &str = "scenarios/";
+
 /// Animation constants
 pub const ANIMATION_STEP: f32 = 0.15;
 
@@ -29,11 +166,17 @@ pub const ANIMATION_STEP: f32 = 0.15;
 // starting coordinates of player
 pub const PLAYER_START_X: f32 = -200.0;
 pub const PLAYER_START_Y: f32 = -100.0;
+pub const PLAYER_HEALTH: f32 = 999.0;
 
 /// Gladiator constants
 pub const ATTACK_STEP: f32 = 0.3; // how fast do gladiators attack (in seconds)
 pub const MOVEMENT_STEP: f32 = 1.0 / 60.0; // warning, this is related to GLADIATOR_SPEED
-pub const N_GLADIATORS: usize = 300; // for now this can't be larger than the length of GLADIATOR_SPRITES
+pub const RANDOM_WALK_KEEP_WEIGHT: u32 = 100; // odds of keeping the same direction against 1 for each other direction
+pub const PARALLEL_BATCH_SIZE: usize = 256; // gladiators per task in systems that run in parallel
+pub const STATUS_EFFECT_STEP: f32 = 0.25; // how often status effects tick (in seconds)
+pub const HEALTH_REGEN_RATE: f32 = 0.02; // fraction of max health regained per second out of combat
+pub const LEVEL_STAT_GAIN: f32 = 0.1; // fraction of the level 1 health, damage and defense every level above 1 adds
+pub const N_GLADIATORS: usize = 300; // free-for-all default, `--gladiators <n>` overrides it
 pub const GLADIATOR_BASE_SPEED: f32 = 2.; // warning, this is related to MOVEMENT_STEP and GLADIATOR_SIZE
 pub const GLADIATOR_SIZE: f32 = 1.5; // this scales the size of the sprite() - lower once there are many
 pub const GLADIATOR_SPRITES_PATH: &str = "Puny-Characters/";
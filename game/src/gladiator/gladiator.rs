@@ -1,23 +1,20 @@
-use bevy::{prelude::*, time::FixedTimestep};
+use bevy::{asset::HandleId, prelude::*, time::FixedTimestep};
 use rand::{self, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    arena::*,
     gladiator::{
-        gladiator_bundles::*, gladiator_combat::*, gladiator_events::*, gladiator_movement::*,
+        gladiator_ai::*, gladiator_bundles::*, gladiator_combat::*, gladiator_components::*,
+        gladiator_equipment::*, gladiator_events::*, gladiator_morale::*, gladiator_movement::*,
+        gladiator_rolls::*, gladiator_status::*,
     },
+    grid::*,
+    helper_functions::*,
+    team::*,
     *, // game_lib
 };
 
-///////////////////////////////////////////////////////
-/// Bugs
-///////////////////////////////////////////////////////
-/// 276v0 is dead!
-/// 301v0 is dead!
-/// 184v0 attacking 8v0 for 1 damage!
-/// 8v0 attacking 184v0 for 1 damage!
-/// 276v0 attacking 52v0 for 3 damage!
-/// thread 'main' panicked at 'Victor of engagement should exist in ECS.: NoSuchEntity(276v0)', game\src\gladiator\gladiator_combat.rs:83:14
-
 ///////////////////////////////////////////////////////
 /// Plugin
 ///////////////////////////////////////////////////////
@@ -26,12 +23,70 @@ pub struct GladiatorPlugin;
 
 impl Plugin for GladiatorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_gladiators)
+        let item_catalog: ItemCatalogFile =
+            load_data_file(ITEMS_PATH).expect("Item definitions should be valid.");
+        let class_catalog: ClassCatalog =
+            load_data_file(CLASSES_PATH).expect("Class definitions should be valid.");
+        let combat_config: CombatConfig =
+            load_data_file(COMBAT_CONFIG_PATH).expect("Combat config should be valid.");
+
+        app.register_type::<Gladiator>()
+            .register_type::<GladiatorClass>()
+            .register_type::<Class>()
+            .register_type::<Health>()
+            .register_type::<Attack>()
+            .register_type::<AttackTimer>()
+            .register_type::<Defense>()
+            .register_type::<DamageType>()
+            .register_type::<Resistances>()
+            .register_type::<Level>()
+            .register_type::<Movement>()
+            .register_type::<Details>()
+            .register_type::<Gold>()
+            .register_type::<GladiatorDirection>()
+            .register_type::<Equipment>()
+            .register_type::<Option<String>>()
+            .register_type::<BaseStats>()
+            .register_type::<Weapon>()
+            .register_type::<StatusEffects>()
+            .register_type::<Morale>()
+            .register_type::<MoraleState>()
+            .register_type::<StatusEffect>()
+            .register_type::<StatusEffectKind>()
+            .register_type::<WeaponType>()
+            .register_type::<Behaviour>()
+            .register_type::<Intent>()
+            .register_type::<AiAction>()
+            .insert_resource(ItemCatalog::new(item_catalog))
+            .insert_resource(class_catalog)
+            .insert_resource(combat_config)
+            .init_resource::<CombatRng>()
+            .init_resource::<ArenaPopulation>()
+            .add_startup_system(spawn_gladiators)
             .add_system_set(
                 SystemSet::new()
                     .with_run_criteria(FixedTimestep::step(MOVEMENT_STEP as f64))
                     .with_system(gladiator_movement),
             )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(STATUS_EFFECT_STEP as f64))
+                    .with_system(tick_status_effects)
+                    .with_system(regenerate_health),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(MORALE_STEP as f64))
+                    .with_system(update_morale),
+            )
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(AI_STEP as f64))
+                    .with_system(decide_actions),
+            )
+            .add_system(assign_behaviours)
+            .add_system(morale_from_kills)
+            .add_system(apply_equipment.before(gladiator_attacks))
             .add_system(gladiator_attacks)
             .add_system(gladiator_receive_attack)
             .add_system(gladiator_death_handler) // how does this guy go last?
@@ -48,14 +103,65 @@ fn spawn_gladiators(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    arena_population: Option<Res<ArenaPopulation>>,
+    team_mode: Option<Res<TeamMode>>,
+    arena_grid: Res<ArenaGrid>,
+    arena_map: Option<Res<ArenaMap>>,
 ) {
-    for i in 0..N_GLADIATORS {
+    let arena_population =
+        arena_population.map_or(ArenaPopulation::default(), |population| population.clone());
+    if !arena_population.random_gladiators {
+        return;
+    }
+
+    let team_mode = team_mode.map_or(TeamMode::default(), |team_mode| *team_mode);
+    let n_gladiators = match (team_mode, arena_population.n_gladiators) {
+        (TeamMode::FreeForAll, Some(n_gladiators)) => n_gladiators,
+        _ => team_mode.n_gladiators(),
+    };
+    let spawn_points = arena_map.map_or(Vec::new(), |arena_map| arena_map.spawn_points.clone());
+    for i in 0..n_gladiators {
+        let team = team_mode.team(i);
         let mut rng = rand::thread_rng();
-        let x = rng.gen_range(-WINDOW_WIDTH..WINDOW_WIDTH);
-        let y = rng.gen_range(-WINDOW_HEIGHT..WINDOW_HEIGHT);
+
+        // take turns at the spawn points of the gladiator's team, or anywhere without any
+        let team_spawn_points: Vec<&SpawnPoint> = spawn_points
+            .iter()
+            .filter(|spawn_point| spawn_point.team.is_none() || spawn_point.team == team)
+            .collect();
+        let mut random_location = || {
+            if team_spawn_points.is_empty() {
+                Vec2::new(
+                    rng.gen_range(-WINDOW_WIDTH..WINDOW_WIDTH),
+                    rng.gen_range(-WINDOW_HEIGHT..WINDOW_HEIGHT),
+                )
+            } else {
+                let jitter = ArenaGrid::cell_size() * SPAWN_POINT_JITTER;
+                team_spawn_points[i % team_spawn_points.len()].position
+                    + Vec2::new(
+                        rng.gen_range(-jitter.x..jitter.x),
+                        rng.gen_range(-jitter.y..jitter.y),
+                    )
+            }
+        };
+        // keep out of pillars, walls and water, a gladiator can still walk out if it runs
+        // out of tries
+        let mut location = random_location();
+        for _ in 1..SPAWN_ATTEMPTS {
+            if !arena_grid.is_blocked(&ArenaGrid::get_grid_location(location.x, location.y)) {
+                break;
+            }
+            location = random_location();
+        }
+        let gladiator_spawn = GladiatorSpawn {
+            class: team_mode.gladiator_class(i),
+            team,
+            ..default()
+        };
         spawn_one_gladiator(
-            Vec2::new(x, y),
+            location,
             i as usize,
+            &gladiator_spawn,
             &mut commands,
             &asset_server,
             &mut texture_atlases,
@@ -63,34 +169,78 @@ fn spawn_gladiators(
     }
 }
 
-/// Spawns a gladiator not controlled by the player
-fn spawn_one_gladiator(
+/// Spawns a gladiator not controlled by the player and returns its Entity
+pub fn spawn_one_gladiator(
     location: Vec2,
     gladiator_idx: usize,
+    gladiator_spawn: &GladiatorSpawn,
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
-) {
-    let sprite_file =
-        GLADIATOR_SPRITES[(gladiator_idx % GLADIATOR_SPRITES.len()) as usize].to_string();
-
-    // just a quick hack, doesn't evenly distribute the classes because there are more
-    // warrior and soldier sprite sheets
-    let gladiator_class = if sprite_file.contains("Archer") {
-        Class::Archer
-    } else if sprite_file.contains("Mage") {
-        Class::Mage
-    } else if sprite_file.contains("Soldier") {
-        Class::Fighter
-    } else if sprite_file.contains("Warrior") {
-        Class::Fighter
-    } else {
-        Class::Fighter
+) -> Entity {
+    let (sprite_file, gladiator_class) = match gladiator_spawn.class {
+        Some(class) => (class.sprite_file(gladiator_idx).to_string(), class),
+        None => {
+            let sprite_file =
+                GLADIATOR_SPRITES[(gladiator_idx % GLADIATOR_SPRITES.len()) as usize].to_string();
+
+            // just a quick hack, doesn't evenly distribute the classes because there are more
+            // warrior and soldier sprite sheets
+            let gladiator_class = Class::from_sprite_file(&sprite_file);
+            (sprite_file, gladiator_class)
+        }
     };
 
     // grab a different spritesheet based on gladiator_idx
+    let texture_atlas_handle =
+        load_gladiator_texture_atlas(&sprite_file, asset_server, texture_atlases);
+
+    // set size and starting location of the gladiator
+    let mut transform = Transform::from_scale(Vec3::splat(GLADIATOR_SIZE));
+    transform.translation = location.extend(1.0);
+
+    let team = gladiator_spawn.team;
+    let mut sprite = TextureAtlasSprite::default();
+    if let Some(team) = team {
+        sprite.color = team.color();
+    }
+
+    let mut gladiator = commands.spawn(SpriteSheetBundle {
+        sprite,
+        texture_atlas: texture_atlas_handle,
+        transform,
+        ..default()
+    });
+    gladiator.insert(
+        GladiatorBundle::new(gladiator_class)
+            .with_level(gladiator_spawn.level)
+            .with_xp(gladiator_spawn.xp),
+    );
+
+    if let Some(team) = team {
+        gladiator.insert(team);
+    }
+
+    gladiator.id()
+}
+
+/// Builds the texture atlas for one of the sprite sheets in GLADIATOR_SPRITES_PATH.
+/// Every gladiator with the same sprite sheet shares one atlas, so the renderer can draw
+/// them in a single batch instead of one per gladiator.
+/// * `sprite_file` - file name of the sprite sheet, e.g. "Archer-Green.png"
+pub fn load_gladiator_texture_atlas(
+    sprite_file: &str,
+    asset_server: &Res<AssetServer>,
+    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+) -> Handle<TextureAtlas> {
     let path = format!("{}{}", GLADIATOR_SPRITES_PATH, sprite_file);
 
+    // the atlas handle is derived from the path, the same sheet always gets the same atlas
+    let atlas_id = HandleId::from(format!("{}#atlas", path).as_str());
+    if texture_atlases.contains(&Handle::weak(atlas_id)) {
+        return texture_atlases.get_handle(atlas_id);
+    }
+
     let texture_handle = asset_server.load(&path);
     // The values used in the next function are specific to the Puny Characters sprite sheets
     // TODO lazy static this?
@@ -102,27 +252,150 @@ fn spawn_one_gladiator(
         Some(Vec2::new(8.0, 8.0)),
         None,
     );
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-
-    // set size and starting location of the gladiator
-    let mut transform = Transform::from_scale(Vec3::splat(GLADIATOR_SIZE));
-    transform.translation = location.extend(1.0);
-
-    commands
-        .spawn(SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle,
-            transform,
-            ..default()
-        })
-        .insert(GladiatorBundle::new(gladiator_class));
+    texture_atlases.set(atlas_id, texture_atlas)
 }
 
 ///////////////////////////////////////////////////////
 /// Structs and Enums
 ///////////////////////////////////////////////////////
 
+/// Game modes that bring in their own gladiators (scenarios, survival waves, tournament
+/// duels, ...) insert this to turn off the random population of the arena at startup.
+#[derive(Resource, Clone)]
+pub struct ArenaPopulation {
+    /// Spawn gladiators at random locations, see spawn_gladiators
+    pub random_gladiators: bool,
+    /// Spawn the player at the usual starting location, see spawn_player
+    pub player: bool,
+    /// Number of gladiators in a free-for-all instead of N_GLADIATORS, team modes
+    /// decide their own numbers
+    pub n_gladiators: Option<usize>,
+}
+
+impl ArenaPopulation {
+    /// Reads the number of gladiators from `--gladiators <n>` in the command line arguments.
+    /// Falls back to N_GLADIATORS when it isn't a number.
+    /// * `args` - command line arguments, usually std::env::args()
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let n_gladiators = arg_value(args, "--gladiators").and_then(|n| match n.parse::<usize>() {
+            Ok(n) => Some(n),
+            Err(_) => {
+                println!(
+                    "'{}' is not a number of gladiators, spawning {}.",
+                    n, N_GLADIATORS
+                );
+                None
+            }
+        });
+        Self {
+            n_gladiators,
+            ..default()
+        }
+    }
+}
+
+impl Default for ArenaPopulation {
+    fn default() -> Self {
+        Self {
+            random_gladiators: true,
+            player: true,
+            n_gladiators: None,
+        }
+    }
+}
+
+/// The choices that go into spawn_one_gladiator. When no class is given, the sprite
+/// sheet is picked from GLADIATOR_SPRITES by gladiator_idx and the class follows from it.
+pub struct GladiatorSpawn {
+    pub class: Option<Class>,
+    pub team: Option<Team>,
+    pub level: usize,
+    pub xp: f32,
+}
+
+impl Default for GladiatorSpawn {
+    fn default() -> Self {
+        Self {
+            class: None,
+            team: None,
+            level: 1,
+            xp: 0.,
+        }
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect, Serialize, Deserialize,
+)]
+#[reflect(Serialize, Deserialize)]
 pub enum Class {
     Archer,
     Mage,
+    #[default]
     Fighter,
 }
+
+/// Defaults for each class that are read from assets/classes/classes.ron
+#[derive(Resource, Deserialize)]
+pub struct ClassCatalog {
+    pub archer: ClassDefinition,
+    pub mage: ClassDefinition,
+    pub fighter: ClassDefinition,
+}
+
+impl ClassCatalog {
+    pub fn get(&self, class: Class) -> &ClassDefinition {
+        match class {
+            Class::Archer => &self.archer,
+            Class::Mage => &self.mage,
+            Class::Fighter => &self.fighter,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ClassDefinition {
+    /// Type of damage dealt unless the equipped weapon says otherwise
+    pub damage_type: DamageType,
+    #[serde(default)]
+    pub resistances: Resistances,
+    pub accuracy: f32,
+    #[serde(default)]
+    pub evasion: f32,
+    #[serde(default)]
+    pub crit_chance: f32,
+    /// How AI gladiators of this class fight, see gladiator_ai
+    #[serde(default)]
+    pub behaviour: Behaviour,
+}
+
+impl Class {
+    /// Determines the class that goes with a sprite sheet based on its file name
+    pub fn from_sprite_file(sprite_file: &str) -> Self {
+        if sprite_file.contains("Archer") {
+            Class::Archer
+        } else if sprite_file.contains("Mage") {
+            Class::Mage
+        } else if sprite_file.contains("Soldier") {
+            Class::Fighter
+        } else if sprite_file.contains("Warrior") {
+            Class::Fighter
+        } else {
+            Class::Fighter
+        }
+    }
+
+    /// Picks one of the GLADIATOR_SPRITES that belong to this class. The index cycles
+    /// through the matching sprite sheets so that a group of the same class isn't
+    /// all wearing the same colors.
+    /// * `idx` - any index, e.g. the order in which the gladiator was spawned
+    pub fn sprite_file(&self, idx: usize) -> &'static str {
+        let sprite_files: Vec<&'static str> = GLADIATOR_SPRITES
+            .iter()
+            .copied()
+            .filter(|sprite_file| Class::from_sprite_file(sprite_file) == *self)
+            .collect();
+
+        sprite_files[idx % sprite_files.len()]
+    }
+}
@@ -0,0 +1,533 @@
+//! AI gladiators decide what to do with a utility scorer. Every AI_STEP each gladiator
+//! looks at its Surroundings, scores the actions it could take (wander, charge the nearest
+//! enemy, hunt a wounded one, retreat, regroup with allies or hold its ground) and acts on
+//! the best one until the next step. How much each consideration counts depends on the
+//! gladiator's Behaviour, so an aggressive gladiator and a coward in the same spot make
+//! different choices.
+//!
+//! Every class has a default Behaviour in assets/classes/classes.ron. Gladiators that
+//! already have a Behaviour when they are spawned (e.g. from a scenario) keep it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    director::*,
+    gladiator::{
+        gladiator::*, gladiator_components::*, gladiator_morale::*, gladiator_movement::*,
+    },
+    grid::*,
+    helper_functions::*,
+    player::player_components::*,
+    team::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Sets up new AI gladiators, those without a Behaviour get the default one of their class
+pub fn assign_behaviours(
+    mut commands: Commands,
+    class_catalog: Res<ClassCatalog>,
+    query: Query<
+        (Entity, &GladiatorClass, Option<&Behaviour>),
+        (With<Gladiator>, Without<Intent>, Without<Player>),
+    >,
+) {
+    for (entity, class, behaviour) in &query {
+        let behaviour = behaviour
+            .copied()
+            .unwrap_or(class_catalog.get(class.class).behaviour);
+        try_insert(&mut commands, entity, (behaviour, Intent::default()));
+    }
+}
+
+/// Picks the highest scoring action for every AI gladiator, in parallel batches
+pub fn decide_actions(
+    arena_grid: Res<ArenaGrid>,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &Health,
+            Option<&Team>,
+            Option<&Morale>,
+            &Behaviour,
+            &mut Intent,
+        ),
+        Without<Player>,
+    >,
+    neighbour_query: Query<(Option<&Team>, &Transform, &Health), With<Gladiator>>,
+    safe_zone: Option<Res<SafeZone>>,
+) {
+    query.par_for_each_mut(
+        PARALLEL_BATCH_SIZE,
+        |(entity, transform, health, team, morale, behaviour, mut intent)| {
+            let position = transform.translation.truncate();
+
+            // staying outside a safe zone that hurts is never worth it
+            if let Some(safe_zone) = &safe_zone {
+                if safe_zone.damage_per_second > 0.0 && !safe_zone.contains(position) {
+                    intent.action = AiAction::MoveToward(Vec2::ZERO);
+                    return;
+                }
+            }
+
+            // a gladiator whose morale broke only wants to get away
+            if let Some(Morale {
+                state: MoraleState::Fleeing { from },
+                ..
+            }) = morale
+            {
+                intent.action = AiAction::MoveAway(*from);
+                return;
+            }
+
+            let surroundings = Surroundings::perceive(
+                entity,
+                position,
+                team,
+                AI_SIGHT,
+                &arena_grid,
+                &neighbour_query,
+            );
+            let eager = matches!(
+                morale,
+                Some(Morale {
+                    state: MoraleState::Eager { .. },
+                    ..
+                })
+            );
+            intent.action =
+                behaviour
+                    .weights()
+                    .best_action(health.fraction(), eager, &surroundings);
+        },
+    );
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// How an AI gladiator weighs its options
+#[derive(
+    Component, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Component)]
+pub enum Behaviour {
+    /// Goes straight for the nearest enemy
+    #[default]
+    Aggressive,
+    /// Sticks with its allies and lets the enemy come to it
+    Defensive,
+    /// Hunts down wounded enemies
+    Opportunist,
+    /// Avoids fights, especially when hurt, and breaks more easily
+    Coward,
+}
+
+impl Behaviour {
+    pub fn weights(&self) -> UtilityWeights {
+        match self {
+            Self::Aggressive => UtilityWeights {
+                wander: 0.2,
+                aggression: 1.0,
+                opportunism: 0.3,
+                caution: 0.1,
+                cohesion: 0.1,
+            },
+            Self::Defensive => UtilityWeights {
+                wander: 0.2,
+                aggression: 0.3,
+                opportunism: 0.1,
+                caution: 0.6,
+                cohesion: 0.8,
+            },
+            Self::Opportunist => UtilityWeights {
+                wander: 0.3,
+                aggression: 0.2,
+                opportunism: 1.2,
+                caution: 0.4,
+                cohesion: 0.2,
+            },
+            Self::Coward => UtilityWeights {
+                wander: 0.4,
+                aggression: 0.05,
+                opportunism: 0.3,
+                caution: 1.0,
+                cohesion: 0.6,
+            },
+        }
+    }
+
+    /// Added to the gladiator's morale, see gladiator_morale
+    pub fn morale_modifier(&self) -> f32 {
+        match self {
+            Self::Aggressive => 0.1,
+            Self::Defensive => 0.05,
+            Self::Opportunist => 0.0,
+            Self::Coward => -0.15,
+        }
+    }
+}
+
+/// How much each consideration counts towards the score of an action
+pub struct UtilityWeights {
+    /// Baseline score of wandering around
+    pub wander: f32,
+    /// Charging the nearest enemy
+    pub aggression: f32,
+    /// Going after wounded enemies
+    pub opportunism: f32,
+    /// Retreating when hurt or outnumbered, holding position when healthy
+    pub caution: f32,
+    /// Staying close to allies
+    pub cohesion: f32,
+}
+
+impl UtilityWeights {
+    /// Scores every action and returns the best one
+    /// * `health` - fraction of max health the gladiator has left
+    /// * `eager` - whether the gladiator's morale is high enough to look for a fight
+    /// * `surroundings` - what the gladiator can see
+    pub fn best_action(&self, health: f32, eager: bool, surroundings: &Surroundings) -> AiAction {
+        let mut candidates = vec![(self.wander, AiAction::Wander)];
+
+        let outnumbered = (surroundings.n_enemies as f32 - surroundings.n_allies as f32)
+            / (surroundings.n_enemies + surroundings.n_allies).max(1) as f32;
+
+        if let Some(nearest_enemy) = surroundings.nearest_enemy {
+            let eagerness = if eager { 1.5 } else { 1.0 };
+            candidates.push((
+                self.aggression * eagerness * (0.5 + 0.5 * health),
+                AiAction::MoveToward(nearest_enemy),
+            ));
+        }
+        if let Some((weakest_enemy, enemy_health)) = surroundings.weakest_enemy {
+            candidates.push((
+                self.opportunism * (1.0 - enemy_health),
+                AiAction::MoveToward(weakest_enemy),
+            ));
+        }
+        if let Some(enemy_center) = surroundings.enemy_center {
+            candidates.push((
+                self.caution * ((1.0 - health) + outnumbered.max(0.0)),
+                AiAction::MoveAway(enemy_center),
+            ));
+            candidates.push((self.caution * health * 0.8, AiAction::Hold));
+        }
+        if let Some(ally_center) = surroundings.ally_center {
+            candidates.push((
+                self.cohesion * (0.3 + outnumbered.max(0.0)),
+                AiAction::MoveToward(ally_center),
+            ));
+        }
+
+        candidates
+            .into_iter()
+            .max_by(|(score_a, _), (score_b, _)| score_a.total_cmp(score_b))
+            .map_or(AiAction::Wander, |(_, action)| action)
+    }
+}
+
+/// What an AI gladiator decided to do until its next decision
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Intent {
+    pub action: AiAction,
+    /// Don't attack the gladiator it is engaged with, e.g. a scripted brain biding its time
+    pub hold_attacks: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum AiAction {
+    /// The random walk
+    #[default]
+    Wander,
+    MoveToward(Vec2),
+    MoveAway(Vec2),
+    Move(GladiatorDirection),
+    /// Stay put and wait for the enemy
+    Hold,
+}
+
+impl AiAction {
+    /// Direction a gladiator at the given position moves in for this action, None when
+    /// it wanders or holds
+    /// * `position` - where the gladiator is
+    pub fn direction(&self, position: Vec2) -> Option<GladiatorDirection> {
+        match *self {
+            Self::MoveToward(target) => GladiatorDirection::toward(target - position),
+            Self::MoveAway(threat) => GladiatorDirection::toward(position - threat),
+            Self::Move(direction) => Some(direction),
+            Self::Wander | Self::Hold => None,
+        }
+    }
+}
+
+/// What a gladiator can see of the gladiators around it
+#[derive(Default)]
+pub struct Surroundings {
+    pub n_allies: usize,
+    pub n_enemies: usize,
+    pub nearest_enemy: Option<Vec2>,
+    /// Position and fraction of health left of the most wounded enemy
+    pub weakest_enemy: Option<(Vec2, f32)>,
+    pub enemy_center: Option<Vec2>,
+    pub ally_center: Option<Vec2>,
+}
+
+impl Surroundings {
+    /// Looks at the gladiators in the grid locations around a gladiator
+    /// * `entity` - the gladiator that is looking
+    /// * `position` - where the gladiator is
+    /// * `team` - the gladiator's team, gladiators without a team have no allies
+    /// * `radius` - how many grid locations away the gladiator can see
+    /// * `arena_grid` - where everybody is
+    /// * `neighbour_query` - team, position and health of the other gladiators
+    pub fn perceive(
+        entity: Entity,
+        position: Vec2,
+        team: Option<&Team>,
+        radius: i32,
+        arena_grid: &ArenaGrid,
+        neighbour_query: &Query<(Option<&Team>, &Transform, &Health), With<Gladiator>>,
+    ) -> Self {
+        let mut surroundings = Self::default();
+        let mut enemy_sum = Vec2::ZERO;
+        let mut ally_sum = Vec2::ZERO;
+
+        let grid_location = ArenaGrid::get_grid_location(position.x, position.y);
+        let neighbours = arena_grid
+            .entities_around(grid_location, radius)
+            .filter(|neighbour| *neighbour != entity);
+
+        for neighbour in neighbours {
+            let (neighbour_team, neighbour_transform, neighbour_health) =
+                match neighbour_query.get(neighbour) {
+                    Ok(neighbour) => neighbour,
+                    Err(_) => continue, // no longer in the ECS
+                };
+            let neighbour_position = neighbour_transform.translation.truncate();
+
+            if Team::are_teammates(team, neighbour_team) {
+                surroundings.n_allies += 1;
+                ally_sum += neighbour_position;
+                continue;
+            }
+
+            surroundings.n_enemies += 1;
+            enemy_sum += neighbour_position;
+
+            let is_nearer = surroundings.nearest_enemy.map_or(true, |nearest| {
+                neighbour_position.distance_squared(position) < nearest.distance_squared(position)
+            });
+            if is_nearer {
+                surroundings.nearest_enemy = Some(neighbour_position);
+            }
+
+            let enemy_health = neighbour_health.fraction();
+            let is_weaker = surroundings
+                .weakest_enemy
+                .map_or(true, |(_, weakest_health)| enemy_health < weakest_health);
+            if is_weaker {
+                surroundings.weakest_enemy = Some((neighbour_position, enemy_health));
+            }
+        }
+
+        if surroundings.n_enemies > 0 {
+            surroundings.enemy_center = Some(enemy_sum / surroundings.n_enemies as f32);
+        }
+        if surroundings.n_allies > 0 {
+            surroundings.ally_center = Some(ally_sum / surroundings.n_allies as f32);
+        }
+        surroundings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::{ComputeTaskPool, TaskPool};
+
+    use super::*;
+
+    const NEAREST_ENEMY: Vec2 = Vec2::new(100.0, 0.0);
+    const WEAKEST_ENEMY: Vec2 = Vec2::new(-100.0, 50.0);
+    const ALLY_CENTER: Vec2 = Vec2::new(0.0, 100.0);
+
+    /// Two enemies, the one further away badly wounded, and an ally
+    fn skirmish() -> Surroundings {
+        Surroundings {
+            n_allies: 1,
+            n_enemies: 2,
+            nearest_enemy: Some(NEAREST_ENEMY),
+            weakest_enemy: Some((WEAKEST_ENEMY, 0.2)),
+            enemy_center: Some((NEAREST_ENEMY + WEAKEST_ENEMY) / 2.0),
+            ally_center: Some(ALLY_CENTER),
+        }
+    }
+
+    fn best_action(behaviour: Behaviour, health: f32) -> AiAction {
+        behaviour.weights().best_action(health, false, &skirmish())
+    }
+
+    #[test]
+    fn each_behaviour_weighs_the_same_fight_its_own_way() {
+        assert_eq!(
+            best_action(Behaviour::Aggressive, 1.0),
+            AiAction::MoveToward(NEAREST_ENEMY)
+        );
+        assert_eq!(
+            best_action(Behaviour::Defensive, 1.0),
+            AiAction::MoveToward(ALLY_CENTER)
+        );
+        assert_eq!(
+            best_action(Behaviour::Opportunist, 1.0),
+            AiAction::MoveToward(WEAKEST_ENEMY)
+        );
+        assert_eq!(best_action(Behaviour::Coward, 1.0), AiAction::Hold);
+    }
+
+    #[test]
+    fn hurt_gladiators_retreat_unless_they_are_aggressive() {
+        let away = AiAction::MoveAway(skirmish().enemy_center.unwrap());
+        assert_eq!(best_action(Behaviour::Defensive, 0.1), away);
+        assert_eq!(best_action(Behaviour::Coward, 0.1), away);
+        assert_eq!(
+            best_action(Behaviour::Aggressive, 0.1),
+            AiAction::MoveToward(NEAREST_ENEMY)
+        );
+    }
+
+    #[test]
+    fn gladiators_wander_when_nobody_is_around() {
+        for behaviour in [
+            Behaviour::Aggressive,
+            Behaviour::Defensive,
+            Behaviour::Opportunist,
+            Behaviour::Coward,
+        ] {
+            let action = behaviour
+                .weights()
+                .best_action(1.0, true, &Surroundings::default());
+            assert_eq!(action, AiAction::Wander);
+        }
+    }
+
+    #[test]
+    fn actions_point_the_right_way() {
+        let position = Vec2::new(10.0, 10.0);
+        assert_eq!(
+            AiAction::MoveToward(Vec2::new(50.0, 10.0)).direction(position),
+            Some(GladiatorDirection::Right)
+        );
+        assert_eq!(
+            AiAction::MoveAway(Vec2::new(50.0, 50.0)).direction(position),
+            Some(GladiatorDirection::DownLeft)
+        );
+        assert_eq!(AiAction::MoveToward(position).direction(position), None);
+        assert_eq!(
+            AiAction::Move(GladiatorDirection::Up).direction(position),
+            Some(GladiatorDirection::Up)
+        );
+        assert_eq!(AiAction::Wander.direction(position), None);
+        assert_eq!(AiAction::Hold.direction(position), None);
+    }
+
+    /// Spawns an AI gladiator with Morale and puts it in the grid
+    fn spawn(world: &mut World, position: Vec2, health: f32, team: u32) -> Entity {
+        let gladiator = world
+            .spawn((
+                Gladiator,
+                Transform::from_translation(position.extend(0.0)),
+                Health {
+                    value: health,
+                    max: 100.0,
+                },
+                Team(team),
+                Morale::default(),
+                Behaviour::Aggressive,
+                Intent::default(),
+            ))
+            .id();
+        world.resource_mut::<ArenaGrid>().place(
+            gladiator,
+            ArenaGrid::get_grid_location(position.x, position.y),
+        );
+        gladiator
+    }
+
+    #[test]
+    fn gladiators_flee_once_their_morale_breaks() {
+        ComputeTaskPool::init(TaskPool::default);
+        let mut world = World::new();
+        world.init_resource::<ArenaGrid>();
+        let enemy_position = Vec2::new(40.0, 0.0);
+        let hurt = spawn(&mut world, Vec2::ZERO, 5.0, 0);
+        let healthy = spawn(&mut world, Vec2::new(0.0, 40.0), 100.0, 0);
+        spawn(&mut world, enemy_position, 100.0, 1);
+        spawn(&mut world, enemy_position, 100.0, 1);
+        spawn(&mut world, enemy_position, 100.0, 1);
+
+        SystemStage::single_threaded()
+            .with_system(update_morale)
+            .with_system(decide_actions.after(update_morale))
+            .run(&mut world);
+
+        let morale = world.get::<Morale>(hurt).unwrap();
+        assert!(morale.value < MORALE_FLEE_THRESHOLD);
+        let MoraleState::Fleeing { from } = morale.state else {
+            panic!(
+                "expected the hurt gladiator to flee, got {:?}",
+                morale.state
+            );
+        };
+        assert_eq!(
+            world.get::<Intent>(hurt).unwrap().action,
+            AiAction::MoveAway(from)
+        );
+        assert!(matches!(
+            world.get::<Intent>(healthy).unwrap().action,
+            AiAction::MoveToward(_)
+        ));
+    }
+
+    #[test]
+    fn authored_behaviours_are_kept() {
+        let class = |behaviour| ClassDefinition {
+            damage_type: DamageType::Slash,
+            resistances: Resistances::default(),
+            accuracy: 0.8,
+            evasion: 0.0,
+            crit_chance: 0.0,
+            behaviour,
+        };
+        let mut world = World::new();
+        world.insert_resource(ClassCatalog {
+            archer: class(Behaviour::Opportunist),
+            mage: class(Behaviour::Defensive),
+            fighter: class(Behaviour::Aggressive),
+        });
+        let fighter = || GladiatorClass {
+            class: Class::Fighter,
+        };
+        let authored = world.spawn((Gladiator, fighter(), Behaviour::Coward)).id();
+        let defaulted = world.spawn((Gladiator, fighter())).id();
+
+        SystemStage::single_threaded()
+            .with_system(assign_behaviours)
+            .run(&mut world);
+
+        assert_eq!(world.get::<Behaviour>(authored), Some(&Behaviour::Coward));
+        assert_eq!(
+            world.get::<Behaviour>(defaulted),
+            Some(&Behaviour::Aggressive)
+        );
+        assert!(world.get::<Intent>(authored).is_some());
+        assert!(world.get::<Intent>(defaulted).is_some());
+    }
+}
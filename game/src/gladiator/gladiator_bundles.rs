@@ -4,7 +4,10 @@ use crate::{
     animation::*,
     gladiator::gladiator::*,
     gladiator::gladiator_components::*,
+    gladiator::gladiator_equipment::*,
+    gladiator::gladiator_morale::*,
     gladiator::gladiator_movement::*,
+    gladiator::gladiator_status::*,
     *, // game_lib
 };
 
@@ -21,6 +24,12 @@ pub struct GladiatorBundle {
     attack: Attack,
     defense: Defense,
     class: GladiatorClass,
+    gold: Gold,
+    base_stats: BaseStats,
+    weapon: Weapon,
+    resistances: Resistances,
+    status_effects: StatusEffects,
+    morale: Morale,
 }
 
 impl GladiatorBundle {
@@ -30,7 +39,7 @@ impl GladiatorBundle {
         //  multiply by a base value here.
         // This could consist of a few helper functions to clean it up and tie in the balancing
         //  hyper parameter for all of the math to generate these values.
-        let (health, attack_speed, damage, defense, speed, class_xp_modifier) =
+        let (health, attack_speed, damage, defense, speed, class_xp_modifier, weapon_type) =
             match gladiator_class {
                 Class::Archer => (
                     10.0,
@@ -39,6 +48,7 @@ impl GladiatorBundle {
                     0.2,
                     GLADIATOR_BASE_SPEED * 1.2,
                     1.0,
+                    WeaponType::Bow,
                 ),
                 Class::Mage => (
                     8.0,
@@ -47,6 +57,7 @@ impl GladiatorBundle {
                     0.1,
                     GLADIATOR_BASE_SPEED * 0.8,
                     1.1,
+                    WeaponType::Staff,
                 ),
                 Class::Fighter => (
                     15.0,
@@ -55,12 +66,13 @@ impl GladiatorBundle {
                     0.5,
                     GLADIATOR_BASE_SPEED * 1.0,
                     0.9,
+                    WeaponType::Sword,
                 ),
             };
 
         Self {
             gladiator: Gladiator,
-            movement: Movement { speed },
+            movement: Movement::new(speed),
             animation: Animation {
                 animation_type: AnimationType::Idle,
                 animation_direction: GladiatorDirection::Down,
@@ -71,17 +83,67 @@ impl GladiatorBundle {
                 TimerMode::Repeating,
             )),
             attack_timer: AttackTimer(Timer::from_seconds(attack_speed, TimerMode::Repeating)),
-            health: Health { value: health },
+            health: Health::new(health),
             level: Level {
                 level: 1,
                 xp: 0.,
                 class_xp_modifier,
             },
-            attack: Attack { damage },
-            defense: Defense { value: defense },
+            // damage type, resistances, accuracy, evasion and crit chance come from the
+            // ClassCatalog in apply_equipment
+            attack: Attack {
+                damage,
+                ..default()
+            },
+            defense: Defense {
+                value: defense,
+                ..default()
+            },
             class: GladiatorClass {
                 class: gladiator_class,
             },
+            gold: Gold { value: 0 },
+            // Attack, Defense, Movement and AttackTimer are recomputed from these by
+            // apply_equipment once the gladiator is spawned
+            base_stats: BaseStats {
+                damage,
+                defense,
+                speed,
+                attack_interval: attack_speed,
+                weapon_type,
+            },
+            weapon: Weapon {
+                weapon_type,
+                on_hit: None,
+            },
+            resistances: Resistances::default(),
+            status_effects: StatusEffects::default(),
+            morale: Morale::default(),
         }
     }
+
+    /// Starts the gladiator at the given level instead of level 1, with the stats of that
+    /// level
+    pub fn with_level(mut self, level: usize) -> Self {
+        grow_stats(
+            self.level.level,
+            level,
+            &mut self.health,
+            &mut self.base_stats,
+        );
+        self.level.level = level;
+        self
+    }
+
+    /// Starts the gladiator with XP already earned towards the next level
+    pub fn with_xp(mut self, xp: f32) -> Self {
+        self.level.xp = xp;
+        self
+    }
+
+    /// Levels the gladiator up faster or slower than the rest of its class
+    pub fn with_class_xp_modifier(mut self, class_xp_modifier: f32) -> Self {
+        self.level.class_xp_modifier = class_xp_modifier;
+        self
+    }
 }
@@ -1,31 +1,48 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 
 use crate::{
     animation::*,
     engagements::*,
-    gladiator::{gladiator::*, gladiator_components::*, gladiator_events::*},
+    gladiator::{
+        gladiator_ai::*, gladiator_components::*, gladiator_equipment::*, gladiator_events::*,
+        gladiator_rolls::*, gladiator_status::*,
+    },
+    grid::*,
     helper_functions::*,
+    *, // game_lib
 };
 
+/// Engaged gladiators attack their target every time their AttackTimer finishes, as long as
+/// the target is within the range of their weapon
 pub fn gladiator_attacks(
+    mut commands: Commands,
     time: Res<Time>,
+    arena_grid: Res<ArenaGrid>,
     mut ev_attack: EventWriter<AttackEvent>,
     mut query: Query<(
         &Engagement,
         &Attack,
         &mut AttackTimer,
         &mut Animation,
-        &GladiatorClass,
+        &Weapon,
+        Option<&Intent>,
         Entity,
     )>,
 ) {
-    for (engagement, attack, mut attack_timer, mut animation, class, entity) in &mut query {
+    for (engagement, attack, mut attack_timer, mut animation, weapon, intent, entity) in &mut query
+    {
+        if intent.map_or(false, |intent| intent.hold_attacks) {
+            continue;
+        }
+        // the target of a ranged engagement is free to walk away, look for somebody else then
+        let distance = arena_grid.distance_between(entity, engagement.target);
+        if distance.map_or(false, |distance| distance > weapon.weapon_type.range()) {
+            commands.entity(entity).remove::<Engagement>();
+            continue;
+        }
+
         // determine correct attack animation
-        let combat_animation_type = match &class.class {
-            Class::Archer => AnimationType::Bow,
-            Class::Mage => AnimationType::Staff,
-            Class::Fighter => AnimationType::Sword,
-        };
+        let combat_animation_type = weapon.weapon_type.animation_type();
 
         // initialize animation type if switching from another animation.
         if animation.animation_type != combat_animation_type {
@@ -37,31 +54,84 @@ pub fn gladiator_attacks(
         if attack_timer.just_finished() {
             ev_attack.send(AttackEvent {
                 target: engagement.target,
-                attacker: entity,
+                attacker: Some(entity),
                 attack: *attack,
+                on_hit: weapon.on_hit.map(|effect| effect.from_source(entity)),
             });
         }
     }
 }
 
 pub fn gladiator_receive_attack(
+    mut commands: Commands,
     mut ev_attack: EventReader<AttackEvent>,
     mut ev_death: EventWriter<DeathEvent>,
-    mut query: Query<(&mut Health, &Defense, &Level)>,
+    combat_config: Res<CombatConfig>,
+    mut combat_rng: ResMut<CombatRng>,
+    mut query: Query<(
+        &mut Health,
+        &Defense,
+        &Resistances,
+        &mut StatusEffects,
+        &Level,
+    )>,
 ) {
     for attack in ev_attack.iter() {
-        let (mut health, defense, level) = query
-            .get_mut(attack.target)
-            .expect("The target of an attack should have Health and Defense.");
+        let (mut health, defense, resistances, mut status_effects, level) =
+            match query.get_mut(attack.target) {
+                Ok(target) => target,
+                Err(_) => {
+                    // the target was despawned since, the attacker should look elsewhere, if
+                    // it is still around itself
+                    if let Some(mut attacker) = attack
+                        .attacker
+                        .and_then(|attacker| commands.get_entity(attacker))
+                    {
+                        attacker.remove::<Engagement>();
+                    }
+                    continue;
+                }
+            };
+        if health.value < 0.0 {
+            continue; // already dying, it only gets to die once
+        }
+
+        let attacker = attack.attacker.map_or_else(
+            || "The arena".to_string(),
+            |attacker| format!("{:?}", attacker),
+        );
+        let damage = match roll_attack(&attack.attack, defense, &combat_config, &mut combat_rng) {
+            AttackRoll::Miss => {
+                println!("{} missed {:?}!", attacker, attack.target);
+                continue;
+            }
+            AttackRoll::Hit(damage) => damage,
+            AttackRoll::Critical(damage) => {
+                println!("Critical hit!");
+                damage
+            }
+        };
 
         println!(
-            "{:?} attacking {:?} for {} damage!",
-            attack.attacker, attack.target, attack.attack.damage
+            "{} attacking {:?} for {} {:?} damage!",
+            attacker, attack.target, damage, attack.attack.damage_type
         );
-        reduce_health_from_attack(&mut health.value, &defense.value, &attack.attack.damage);
+        reduce_health_from_attack(
+            &mut health.value,
+            &defense.value,
+            &Attack {
+                damage,
+                ..attack.attack
+            },
+            resistances,
+            &combat_config.mitigation,
+        );
+        if let Some(effect) = attack.on_hit {
+            status_effects.apply(effect);
+        }
 
         // The reader for DeathEvents will despawn the gladiator that died and award XP to the
-        // gladiator that made the kill.
+        // gladiator that made the kill, if a gladiator made it.
         if health.value < 0.0 {
             ev_death.send(DeathEvent {
                 victor: attack.attacker,
@@ -75,16 +145,104 @@ pub fn gladiator_receive_attack(
 pub fn gladiator_death_handler(
     mut commands: Commands,
     mut ev_death: EventReader<DeathEvent>,
-    mut query: Query<&mut Level, With<Gladiator>>,
+    mut query: Query<(&mut Level, &mut Gold, &mut Health, &mut BaseStats), With<Gladiator>>,
 ) {
-    for event in ev_death.iter() {
-        let mut victor_level = query
-            .get_mut(event.victor)
-            .expect("Victor of engagement should exist in ECS.");
-        victor_level.gain_xp(event.xp_earned);
-        commands.entity(event.victor).remove::<Engagement>();
+    // e.g. a bleed and an attack finishing off the same gladiator in one frame, it only
+    // dies once
+    let mut slain = HashSet::default();
+    let deaths: Vec<&DeathEvent> = ev_death
+        .iter()
+        .filter(|event| slain.insert(event.slain))
+        .collect();
+
+    for event in deaths {
+        // the victor may have died already, e.g. when a bleed they caused does the killing
+        if let Some(victor) = event.victor {
+            if let Ok((mut victor_level, mut victor_gold, mut health, mut base_stats)) =
+                query.get_mut(victor)
+            {
+                let level_before = victor_level.level;
+                victor_level.gain_xp(event.xp_earned);
+                victor_gold.value += GOLD_PER_KILL;
+                if victor_level.level > level_before {
+                    grow_stats(
+                        level_before,
+                        victor_level.level,
+                        &mut health,
+                        &mut base_stats,
+                    );
+                }
+            }
+        }
+        // whoever was fighting the slain gladiator is free to find a new opponent
+        disengage(&mut commands, event.slain);
         println!("{:?} is dead!", event.slain);
 
-        commands.entity(event.slain).despawn();
+        // deaths from last frame can name a gladiator that was cleared away since, e.g. by
+        // the career between battles
+        if let Some(mut slain) = commands.get_entity(event.slain) {
+            slain.despawn();
+        }
+    }
+}
+
+/// Gladiators that are not fighting anyone slowly regain health, up to their max
+pub fn regenerate_health(mut query: Query<&mut Health, (With<Gladiator>, Without<Engagement>)>) {
+    for mut health in &mut query {
+        let amount = health.max * HEALTH_REGEN_RATE * STATUS_EFFECT_STEP;
+        health.heal(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A world with a gladiator that is fighting and one that is not, at the given health
+    fn world_with(value: f32) -> (World, Entity, Entity) {
+        let mut world = World::new();
+        let health = || Health { value, max: 100.0 };
+        let idle = world.spawn((Gladiator, health())).id();
+        let fighting = world
+            .spawn((Gladiator, health(), Engagement { target: idle }))
+            .id();
+        (world, idle, fighting)
+    }
+
+    fn regenerate(world: &mut World, n_steps: usize) {
+        let mut stage = SystemStage::single_threaded().with_system(regenerate_health);
+        for _ in 0..n_steps {
+            stage.run(world);
+        }
+    }
+
+    #[test]
+    fn gladiators_out_of_combat_regenerate_every_step() {
+        let (mut world, idle, fighting) = world_with(50.0);
+
+        regenerate(&mut world, 4);
+
+        let per_step = 100.0 * HEALTH_REGEN_RATE * STATUS_EFFECT_STEP;
+        let regenerated = world.get::<Health>(idle).unwrap().value - 50.0;
+        assert!((regenerated - 4.0 * per_step).abs() < 1e-4);
+        assert_eq!(world.get::<Health>(fighting).unwrap().value, 50.0);
+    }
+
+    #[test]
+    fn regeneration_stops_at_the_max() {
+        let (mut world, idle, _) = world_with(99.9);
+
+        regenerate(&mut world, 10);
+
+        assert_eq!(world.get::<Health>(idle).unwrap().value, 100.0);
+    }
+
+    #[test]
+    fn the_dead_do_not_regenerate() {
+        let (mut world, idle, _) = world_with(-5.0);
+
+        regenerate(&mut world, 10);
+
+        assert_eq!(world.get::<Health>(idle).unwrap().value, -5.0);
     }
 }
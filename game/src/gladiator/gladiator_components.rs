@@ -1,43 +1,104 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::gladiator::gladiator::Class;
+use crate::{gladiator::gladiator::Class, LEVEL_STAT_GAIN};
 
-#[derive(Component, Deref, DerefMut)]
+#[derive(Component, Deref, DerefMut, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct AnimationTimer(pub Timer);
 
-#[derive(Component, Debug, Clone, Copy)]
+#[derive(Component, Debug, Clone, Copy, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Attack {
     pub damage: f32,
+    pub damage_type: DamageType,
+    /// Chance to hit before the defender's evasion is taken off
+    pub accuracy: f32,
+    /// Chance that a hit is critical
+    pub crit_chance: f32,
 }
 
-#[derive(Component, Deref, DerefMut)]
+#[derive(Component, Deref, DerefMut, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct AttackTimer(pub Timer);
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Defense {
     pub value: f32,
+    /// Taken off the attacker's chance to hit
+    pub evasion: f32,
 }
 
-#[derive(Component)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum DamageType {
+    #[default]
+    Slash,
+    Pierce,
+    Blunt,
+    Fire,
+    Arcane,
+}
+
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Details {
     pub name: String,
     pub motivation: String,
 }
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Gladiator;
 
-#[derive(Component)]
+/// Coins earned from kills and surviving battles, spent in the shop between battles
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Gold {
+    pub value: u32,
+}
+
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct GladiatorClass {
     pub class: Class,
 }
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Health {
     pub value: f32,
+    pub max: f32,
+}
+
+impl Health {
+    /// Full health
+    pub fn new(max: f32) -> Self {
+        Self { value: max, max }
+    }
+
+    /// Restores health without going over the max. Gladiators below zero are dying and
+    /// can't be healed back up.
+    pub fn heal(&mut self, amount: f32) {
+        if self.value < 0.0 {
+            return;
+        }
+        self.value = (self.value + amount).min(self.max);
+    }
+
+    /// Fraction of the max that is left, e.g. for health bars
+    pub fn fraction(&self) -> f32 {
+        if self.max > 0.0 {
+            (self.value / self.max).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
 }
 
-#[derive(Component)]
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Level {
     pub level: usize,
     pub xp: f32,
@@ -45,6 +106,12 @@ pub struct Level {
 }
 
 impl Level {
+    /// How much stronger a gladiator of the given level is than at level 1, their health,
+    /// damage and defense are multiplied by it
+    pub fn stat_multiplier(level: usize) -> f32 {
+        1.0 + level.saturating_sub(1) as f32 * LEVEL_STAT_GAIN
+    }
+
     pub fn convert_to_xp(&self) -> f32 {
         // TODO placeholder math
         let level_xp_base: f32 = 2.;
@@ -64,7 +131,108 @@ impl Level {
     }
 }
 
-#[derive(Component)]
+#[derive(Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Movement {
     pub speed: f32,
+    /// Set by status effects, e.g. 0.5 while slowed and 0.0 while stunned
+    pub speed_modifier: f32,
+}
+
+impl Movement {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            speed_modifier: 1.0,
+        }
+    }
+
+    /// Speed with the status effects taken into account
+    pub fn current_speed(&self) -> f32 {
+        self.speed * self.speed_modifier
+    }
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+/// Fraction of the damage of each type that is shrugged off. 0.25 takes a quarter off,
+/// negative values are weaknesses that increase the damage taken.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+#[serde(default)]
+pub struct Resistances {
+    pub slash: f32,
+    pub pierce: f32,
+    pub blunt: f32,
+    pub fire: f32,
+    pub arcane: f32,
+}
+
+impl Resistances {
+    pub fn against(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Slash => self.slash,
+            DamageType::Pierce => self.pierce,
+            DamageType::Blunt => self.blunt,
+            DamageType::Fire => self.fire,
+            DamageType::Arcane => self.arcane,
+        }
+    }
+}
+
+impl std::ops::AddAssign for Resistances {
+    fn add_assign(&mut self, other: Self) {
+        self.slash += other.slash;
+        self.pierce += other.pierce;
+        self.blunt += other.blunt;
+        self.fire += other.fire;
+        self.arcane += other.arcane;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn healing_stops_at_the_max() {
+        let mut health = Health {
+            value: 40.0,
+            max: 50.0,
+        };
+        health.heal(5.0);
+        assert_eq!(health.value, 45.0);
+        health.heal(20.0);
+        assert_eq!(health.value, 50.0);
+    }
+
+    #[test]
+    fn dying_gladiators_are_not_healed() {
+        let mut health = Health {
+            value: -1.0,
+            max: 50.0,
+        };
+        health.heal(20.0);
+        assert_eq!(health.value, -1.0);
+
+        let mut health = Health {
+            value: 0.0,
+            max: 50.0,
+        };
+        health.heal(20.0);
+        assert_eq!(health.value, 20.0);
+    }
+
+    #[test]
+    fn the_health_fraction_is_clamped() {
+        let health = |value, max| Health { value, max };
+        assert_eq!(health(25.0, 100.0).fraction(), 0.25);
+        assert_eq!(health(-10.0, 100.0).fraction(), 0.0);
+        assert_eq!(health(150.0, 100.0).fraction(), 1.0);
+        assert_eq!(health(10.0, 0.0).fraction(), 0.0);
+    }
 }
@@ -0,0 +1,551 @@
+//! Gladiators can equip a weapon, a piece of armor and a trinket. The items are defined
+//! in assets/items/items.ron, each with the slot it goes in and the stat modifiers it
+//! gives. Weapons also have a WeaponType, which decides the attack animation and how many
+//! grid locations away the gladiator can engage an opponent from.
+//!
+//! Attack, Defense (including accuracy, evasion and crit chance), Resistances, Movement
+//! and the AttackTimer are derived stats: they are
+//! recomputed from the class BaseStats, the class defaults in the ClassCatalog and the
+//! equipped items whenever the Equipment changes.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::*,
+    gladiator::{gladiator::*, gladiator_components::*, gladiator_status::*},
+};
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Recomputes the derived stats of gladiators whose Equipment changed. Also runs for
+/// newly spawned gladiators, so Equipment authored in a scenario or added at spawn is
+/// applied no matter which of the two was inserted first.
+pub fn apply_equipment(
+    item_catalog: Res<ItemCatalog>,
+    class_catalog: Res<ClassCatalog>,
+    mut query: Query<
+        (
+            &BaseStats,
+            &GladiatorClass,
+            Option<&Equipment>,
+            &mut Attack,
+            &mut Defense,
+            &mut Resistances,
+            &mut Movement,
+            &mut AttackTimer,
+            &mut Weapon,
+        ),
+        Or<(Changed<Equipment>, Changed<BaseStats>)>,
+    >,
+) {
+    for (
+        base_stats,
+        class,
+        equipment,
+        mut attack,
+        mut defense,
+        mut resistances,
+        mut movement,
+        mut attack_timer,
+        mut weapon,
+    ) in &mut query
+    {
+        let class_definition = class_catalog.get(class.class);
+        let mut damage = base_stats.damage;
+        let mut defense_value = base_stats.defense;
+        let mut speed = base_stats.speed;
+        let mut attack_interval = base_stats.attack_interval;
+        let mut weapon_type = base_stats.weapon_type;
+        let mut on_hit = None;
+        let mut damage_type = class_definition.damage_type;
+        let mut resistances_value = class_definition.resistances;
+        let mut accuracy = class_definition.accuracy;
+        let mut evasion = class_definition.evasion;
+        let mut crit_chance = class_definition.crit_chance;
+
+        let items = equipment
+            .into_iter()
+            .flat_map(|equipment| equipment.item_names())
+            .filter_map(|name| item_catalog.get(name));
+        for item in items {
+            damage += item.modifiers.damage;
+            defense_value += item.modifiers.defense;
+            speed *= item.modifiers.speed;
+            attack_interval *= item.modifiers.attack_interval;
+            resistances_value += item.modifiers.resistances;
+            accuracy += item.modifiers.accuracy;
+            evasion += item.modifiers.evasion;
+            crit_chance += item.modifiers.crit_chance;
+            if let Some(item_weapon_type) = item.weapon_type {
+                weapon_type = item_weapon_type;
+            }
+            if let Some(item_damage_type) = item.damage_type {
+                damage_type = item_damage_type;
+            }
+            if item.on_hit.is_some() {
+                on_hit = item.on_hit;
+            }
+        }
+
+        attack.damage = damage;
+        attack.damage_type = damage_type;
+        attack.accuracy = accuracy;
+        attack.crit_chance = crit_chance;
+        defense.value = defense_value;
+        defense.evasion = evasion;
+        *resistances = resistances_value;
+        movement.speed = speed;
+        attack_timer.set_duration(Duration::from_secs_f32(attack_interval));
+        weapon.weapon_type = weapon_type;
+        weapon.on_hit = on_hit;
+    }
+}
+
+/// Grows health, damage and defense from what they were at one level to what they are at
+/// another, see Level::stat_multiplier. Health that was lost stays lost.
+/// * `from` - the level the stats are for now
+/// * `to` - the level the stats should be for
+pub fn grow_stats(from: usize, to: usize, health: &mut Health, base_stats: &mut BaseStats) {
+    let growth = Level::stat_multiplier(to) / Level::stat_multiplier(from);
+    let lost = health.max - health.value;
+    health.max *= growth;
+    health.value = health.max - lost;
+    base_stats.damage *= growth;
+    base_stats.defense *= growth;
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// Names of the items a gladiator has equipped, as defined in the ItemCatalog
+#[derive(Component, Clone, Debug, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Equipment {
+    pub weapon: Option<String>,
+    pub armor: Option<String>,
+    pub trinket: Option<String>,
+}
+
+impl Equipment {
+    /// Equips owned items into their slots. When several items go in the same slot, the
+    /// last one wins, so the most recently bought item is the one in use.
+    /// * `owned` - names of the items the gladiator owns
+    /// * `item_catalog` - definitions of all items
+    pub fn from_owned(owned: &[String], item_catalog: &ItemCatalog) -> Self {
+        let mut equipment = Self::default();
+        for name in owned {
+            match item_catalog.get(name) {
+                Some(item) => *equipment.slot_mut(item.slot) = Some(name.clone()),
+                None => println!("Unknown item {}, leaving it in storage.", name),
+            }
+        }
+        equipment
+    }
+
+    pub fn slot_mut(&mut self, slot: EquipmentSlot) -> &mut Option<String> {
+        match slot {
+            EquipmentSlot::Weapon => &mut self.weapon,
+            EquipmentSlot::Armor => &mut self.armor,
+            EquipmentSlot::Trinket => &mut self.trinket,
+        }
+    }
+
+    /// Names of everything that is equipped
+    pub fn item_names(&self) -> impl Iterator<Item = &String> {
+        [&self.weapon, &self.armor, &self.trinket]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// The unequipped stats of a gladiator, decided by their class
+#[derive(Component, Clone, Copy, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct BaseStats {
+    pub damage: f32,
+    pub defense: f32,
+    pub speed: f32,
+    /// Seconds between attacks
+    pub attack_interval: f32,
+    pub weapon_type: WeaponType,
+}
+
+/// The kind of weapon a gladiator is fighting with, derived from their Equipment
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Weapon {
+    pub weapon_type: WeaponType,
+    /// Status effect applied to whoever the weapon hits
+    pub on_hit: Option<StatusEffect>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum WeaponType {
+    #[default]
+    Sword,
+    Spear,
+    Bow,
+    Staff,
+}
+
+impl WeaponType {
+    /// Animation played while attacking with this weapon
+    pub fn animation_type(&self) -> AnimationType {
+        match self {
+            Self::Sword => AnimationType::Sword,
+            Self::Spear => AnimationType::Throw,
+            Self::Bow => AnimationType::Bow,
+            Self::Staff => AnimationType::Staff,
+        }
+    }
+
+    /// How many grid locations away an opponent can be engaged from. Melee weapons only
+    /// reach opponents in the same grid location.
+    pub fn range(&self) -> i32 {
+        match self {
+            Self::Sword => 0,
+            Self::Spear => 1,
+            Self::Staff => 2,
+            Self::Bow => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armor,
+    Trinket,
+}
+
+/// Changes an item makes to the stats of whoever equips it
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatModifiers {
+    /// Added to the attack damage
+    pub damage: f32,
+    /// Added to the defense
+    pub defense: f32,
+    /// Multiplies the movement speed
+    pub speed: f32,
+    /// Multiplies the time between attacks, below 1.0 attacks faster
+    pub attack_interval: f32,
+    /// Added to the resistances
+    pub resistances: Resistances,
+    /// Added to the chance to hit
+    pub accuracy: f32,
+    /// Added to the chance to dodge
+    pub evasion: f32,
+    /// Added to the chance of a critical hit
+    pub crit_chance: f32,
+}
+
+impl Default for StatModifiers {
+    fn default() -> Self {
+        Self {
+            damage: 0.0,
+            defense: 0.0,
+            speed: 1.0,
+            attack_interval: 1.0,
+            resistances: Resistances::default(),
+            accuracy: 0.0,
+            evasion: 0.0,
+            crit_chance: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemDefinition {
+    pub name: String,
+    pub slot: EquipmentSlot,
+    /// Only weapons have a weapon type
+    #[serde(default)]
+    pub weapon_type: Option<WeaponType>,
+    /// Weapons can change the type of damage dealt, e.g. a flaming sword
+    #[serde(default)]
+    pub damage_type: Option<DamageType>,
+    /// Status effect applied to whoever the item's wearer hits
+    #[serde(default)]
+    pub on_hit: Option<StatusEffect>,
+    #[serde(default)]
+    pub modifiers: StatModifiers,
+}
+
+/// Contents of assets/items/items.ron
+#[derive(Deserialize)]
+pub struct ItemCatalogFile {
+    pub items: Vec<ItemDefinition>,
+}
+
+/// Every item a gladiator can equip, by name
+#[derive(Resource, Default)]
+pub struct ItemCatalog {
+    pub items: HashMap<String, ItemDefinition>,
+}
+
+impl ItemCatalog {
+    pub fn new(file: ItemCatalogFile) -> Self {
+        Self {
+            items: file
+                .items
+                .into_iter()
+                .map(|item| (item.name.clone(), item))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ItemDefinition> {
+        self.items.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gladiator::gladiator_ai::Behaviour;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < EPSILON,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    fn item(name: &str, slot: EquipmentSlot, modifiers: StatModifiers) -> ItemDefinition {
+        ItemDefinition {
+            name: name.to_string(),
+            slot,
+            weapon_type: None,
+            damage_type: None,
+            on_hit: None,
+            modifiers,
+        }
+    }
+
+    fn item_catalog() -> ItemCatalog {
+        let spear = ItemDefinition {
+            weapon_type: Some(WeaponType::Spear),
+            damage_type: Some(DamageType::Pierce),
+            ..item(
+                "Spear",
+                EquipmentSlot::Weapon,
+                StatModifiers {
+                    damage: 1.0,
+                    attack_interval: 0.5,
+                    accuracy: 0.1,
+                    ..default()
+                },
+            )
+        };
+        let armor = item(
+            "Armor",
+            EquipmentSlot::Armor,
+            StatModifiers {
+                defense: 0.5,
+                speed: 0.8,
+                resistances: Resistances {
+                    slash: 0.2,
+                    ..default()
+                },
+                evasion: -0.05,
+                ..default()
+            },
+        );
+        let charm = item(
+            "Charm",
+            EquipmentSlot::Trinket,
+            StatModifiers {
+                damage: 0.5,
+                defense: 0.25,
+                speed: 1.5,
+                crit_chance: 0.1,
+                ..default()
+            },
+        );
+        ItemCatalog::new(ItemCatalogFile {
+            items: vec![spear, armor, charm],
+        })
+    }
+
+    fn class_catalog() -> ClassCatalog {
+        let class = || ClassDefinition {
+            damage_type: DamageType::Slash,
+            resistances: Resistances::default(),
+            accuracy: 0.8,
+            evasion: 0.1,
+            crit_chance: 0.05,
+            behaviour: Behaviour::default(),
+        };
+        ClassCatalog {
+            archer: class(),
+            mage: class(),
+            fighter: class(),
+        }
+    }
+
+    /// A world with the catalogs and one fighter wearing the given equipment
+    fn world_with(equipment: Equipment) -> (World, Entity) {
+        let mut world = World::new();
+        world.insert_resource(item_catalog());
+        world.insert_resource(class_catalog());
+        let gladiator = world
+            .spawn((
+                BaseStats {
+                    damage: 2.0,
+                    defense: 1.0,
+                    speed: 100.0,
+                    attack_interval: 2.0,
+                    weapon_type: WeaponType::Sword,
+                },
+                GladiatorClass {
+                    class: Class::Fighter,
+                },
+                equipment,
+                Attack::default(),
+                Defense::default(),
+                Resistances::default(),
+                Movement::new(0.0),
+                AttackTimer::default(),
+                Weapon::default(),
+            ))
+            .id();
+        (world, gladiator)
+    }
+
+    fn fully_equipped() -> Equipment {
+        Equipment {
+            weapon: Some("Spear".to_string()),
+            armor: Some("Armor".to_string()),
+            trinket: Some("Charm".to_string()),
+        }
+    }
+
+    #[test]
+    fn weapon_armor_and_trinket_modifiers_stack() {
+        let (mut world, gladiator) = world_with(fully_equipped());
+        SystemStage::single_threaded()
+            .with_system(apply_equipment)
+            .run(&mut world);
+
+        let attack = world.get::<Attack>(gladiator).unwrap();
+        assert_close(attack.damage, 3.5);
+        assert_eq!(attack.damage_type, DamageType::Pierce);
+        assert_close(attack.accuracy, 0.9);
+        assert_close(attack.crit_chance, 0.15);
+        let defense = world.get::<Defense>(gladiator).unwrap();
+        assert_close(defense.value, 1.75);
+        assert_close(defense.evasion, 0.05);
+        assert_close(world.get::<Resistances>(gladiator).unwrap().slash, 0.2);
+        assert_close(world.get::<Movement>(gladiator).unwrap().speed, 120.0);
+        let attack_timer = world.get::<AttackTimer>(gladiator).unwrap();
+        assert_close(attack_timer.duration().as_secs_f32(), 1.0);
+        assert_eq!(
+            world.get::<Weapon>(gladiator).unwrap().weapon_type,
+            WeaponType::Spear
+        );
+    }
+
+    #[test]
+    fn stats_are_recomputed_when_the_equipment_changes() {
+        let (mut world, gladiator) = world_with(fully_equipped());
+        // the same system every time, so it remembers what it has already seen
+        let mut stage = SystemStage::single_threaded().with_system(apply_equipment);
+        stage.run(&mut world);
+
+        world.get_mut::<Equipment>(gladiator).unwrap().weapon = None;
+        stage.run(&mut world);
+
+        let attack = world.get::<Attack>(gladiator).unwrap();
+        assert_close(attack.damage, 2.5);
+        assert_eq!(attack.damage_type, DamageType::Slash);
+        assert_close(attack.accuracy, 0.8);
+        let attack_timer = world.get::<AttackTimer>(gladiator).unwrap();
+        assert_close(attack_timer.duration().as_secs_f32(), 2.0);
+        assert_eq!(
+            world.get::<Weapon>(gladiator).unwrap().weapon_type,
+            WeaponType::Sword
+        );
+
+        *world.get_mut::<Equipment>(gladiator).unwrap() = Equipment::default();
+        stage.run(&mut world);
+
+        assert_close(world.get::<Attack>(gladiator).unwrap().damage, 2.0);
+        assert_close(world.get::<Defense>(gladiator).unwrap().value, 1.0);
+        assert_close(world.get::<Movement>(gladiator).unwrap().speed, 100.0);
+    }
+
+    #[test]
+    fn unchanged_equipment_is_not_reapplied() {
+        let (mut world, gladiator) = world_with(fully_equipped());
+        let mut stage = SystemStage::single_threaded().with_system(apply_equipment);
+        stage.run(&mut world);
+
+        world.get_mut::<Attack>(gladiator).unwrap().damage = 0.0;
+        stage.run(&mut world);
+
+        assert_close(world.get::<Attack>(gladiator).unwrap().damage, 0.0);
+    }
+
+    #[test]
+    fn owned_items_go_in_their_slots_and_the_last_one_wins() {
+        let owned = ["Charm", "Armor", "Spear", "Sling", "Armor"].map(String::from);
+        let equipment = Equipment::from_owned(&owned, &item_catalog());
+
+        assert_eq!(equipment.weapon.as_deref(), Some("Spear"));
+        assert_eq!(equipment.armor.as_deref(), Some("Armor"));
+        assert_eq!(equipment.trinket.as_deref(), Some("Charm"));
+        assert_eq!(equipment.item_names().count(), 3);
+
+        let mut second_weapon = item_catalog();
+        second_weapon.items.insert(
+            "Sling".to_string(),
+            item("Sling", EquipmentSlot::Weapon, StatModifiers::default()),
+        );
+        let equipment = Equipment::from_owned(&owned, &second_weapon);
+        assert_eq!(equipment.weapon.as_deref(), Some("Sling"));
+    }
+
+    #[test]
+    fn ranged_weapons_reach_further() {
+        assert_eq!(WeaponType::Sword.range(), 0);
+        assert_eq!(WeaponType::Spear.range(), 1);
+        assert_eq!(WeaponType::Staff.range(), 2);
+        assert_eq!(WeaponType::Bow.range(), 3);
+    }
+
+    #[test]
+    fn growing_stats_keeps_lost_health_lost() {
+        let mut health = Health {
+            value: 80.0,
+            max: 100.0,
+        };
+        let mut base_stats = BaseStats {
+            damage: 2.0,
+            defense: 1.0,
+            ..default()
+        };
+        grow_stats(1, 3, &mut health, &mut base_stats);
+
+        let growth = Level::stat_multiplier(3);
+        assert_close(health.max, 100.0 * growth);
+        assert_close(health.value, 100.0 * growth - 20.0);
+        assert_close(base_stats.damage, 2.0 * growth);
+        assert_close(base_stats.defense, 1.0 * growth);
+
+        grow_stats(3, 1, &mut health, &mut base_stats);
+        assert_close(health.max, 100.0);
+        assert_close(base_stats.damage, 2.0);
+    }
+}
@@ -1,17 +1,21 @@
 use bevy::prelude::*;
 
-use crate::gladiator::gladiator_components::*;
+use crate::gladiator::{gladiator_components::*, gladiator_status::*};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AttackEvent {
     pub target: Entity,
-    pub attacker: Entity,
+    /// None when the arena itself attacks, e.g. a spike trap or a lion
+    pub attacker: Option<Entity>,
     pub attack: Attack,
+    /// Status effect of the attacker's weapon, applied if the attack hits
+    pub on_hit: Option<StatusEffect>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DeathEvent {
-    pub victor: Entity,
+    /// None when nobody gets credit for the kill, e.g. poison from a mushroom
+    pub victor: Option<Entity>,
     pub xp_earned: f32,
     pub slain: Entity,
 }
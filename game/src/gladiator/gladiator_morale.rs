@@ -0,0 +1,274 @@
+//! Morale decides whether an AI gladiator keeps fighting. It goes up with health, with
+//! allies nearby and with recent kills, and down when wounded or outnumbered. Gladiators
+//! whose morale breaks leave their engagement and run away from the enemies around them
+//! until they rally, and gladiators with high morale are eager to charge the nearest enemy
+//! (see gladiator_ai for how that plays out). Some Behaviours are braver than others. The
+//! player's gladiator has no morale to speak of.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engagements::*,
+    gladiator::{gladiator_ai::*, gladiator_components::*, gladiator_events::*},
+    grid::*,
+    player::player_components::*,
+    team::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Recomputes the morale of AI gladiators from their health, the allies and enemies in
+/// the grid locations around them and their recent kills, and decides whether they flee,
+/// hold or charge.
+pub fn update_morale(
+    mut commands: Commands,
+    arena_grid: Res<ArenaGrid>,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &Health,
+            Option<&Team>,
+            Option<&Engagement>,
+            Option<&Behaviour>,
+            &mut Morale,
+        ),
+        Without<Player>,
+    >,
+    neighbour_query: Query<(Option<&Team>, &Transform, &Health), With<Gladiator>>,
+) {
+    for (entity, transform, health, team, engagement, behaviour, mut morale) in &mut query {
+        let position = transform.translation.truncate();
+        let surroundings = Surroundings::perceive(
+            entity,
+            position,
+            team,
+            MORALE_RADIUS,
+            &arena_grid,
+            &neighbour_query,
+        );
+        let n_allies = surroundings.n_allies;
+        let n_enemies = surroundings.n_enemies;
+
+        morale.kill_boost = (morale.kill_boost - MORALE_KILL_DECAY * MORALE_STEP).max(0.0);
+        let crowd = (n_allies as f32 - n_enemies as f32) / ((n_allies + n_enemies) as f32).max(1.0);
+        morale.value = (MORALE_BASE
+            + (health.fraction() - 0.5) * MORALE_HEALTH_WEIGHT
+            + crowd * MORALE_CROWD_WEIGHT
+            + morale.kill_boost
+            + behaviour.map_or(0.0, Behaviour::morale_modifier))
+        .clamp(0.0, 1.0);
+
+        let fleeing = matches!(morale.state, MoraleState::Fleeing { .. });
+        morale.state = if morale.value < MORALE_FLEE_THRESHOLD
+            || (fleeing && morale.value < MORALE_RALLY_THRESHOLD)
+        {
+            // run from whoever is around, or from where the fight was if they can't be seen
+            let from = surroundings.enemy_center.unwrap_or(match morale.state {
+                MoraleState::Fleeing { from } => from,
+                _ => position,
+            });
+            if engagement.is_some() {
+                println!("{:?} breaks and runs!", entity);
+                disengage(&mut commands, entity);
+            }
+            MoraleState::Fleeing { from }
+        } else if morale.value > MORALE_EAGER_THRESHOLD {
+            match surroundings.nearest_enemy {
+                Some(toward) => MoraleState::Eager { toward },
+                None => MoraleState::Steady,
+            }
+        } else {
+            MoraleState::Steady
+        };
+    }
+}
+
+/// Kills lift the morale of the gladiator that made them
+pub fn morale_from_kills(mut ev_death: EventReader<DeathEvent>, mut query: Query<&mut Morale>) {
+    for event in ev_death.iter() {
+        if let Some(Ok(mut morale)) = event.victor.map(|victor| query.get_mut(victor)) {
+            morale.kill_boost += MORALE_PER_KILL;
+        }
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+#[derive(Component, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Morale {
+    /// From 0.0 (broken) to 1.0 (fearless)
+    pub value: f32,
+    /// Extra morale from recent kills, wears off over time
+    pub kill_boost: f32,
+    pub state: MoraleState,
+}
+
+impl Morale {
+    /// Whether a gladiator is running away and can't be engaged. Gladiators without
+    /// Morale never flee.
+    pub fn is_fleeing(morale: Option<&Morale>) -> bool {
+        matches!(
+            morale,
+            Some(Morale {
+                state: MoraleState::Fleeing { .. },
+                ..
+            })
+        )
+    }
+}
+
+impl Default for Morale {
+    fn default() -> Self {
+        Self {
+            value: MORALE_BASE,
+            kill_boost: 0.0,
+            state: MoraleState::Steady,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+pub enum MoraleState {
+    /// Wanders the arena and fights whoever comes along
+    #[default]
+    Steady,
+    /// Runs away from the given location
+    Fleeing { from: Vec2 },
+    /// Heads for the enemy at the given location
+    Eager { toward: Vec2 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spawns a gladiator with Morale and puts it in the grid
+    fn spawn(world: &mut World, position: Vec2, health: f32, team: u32) -> Entity {
+        let gladiator = world
+            .spawn((
+                Gladiator,
+                Transform::from_translation(position.extend(0.0)),
+                Health {
+                    value: health,
+                    max: 100.0,
+                },
+                Team(team),
+                Morale::default(),
+            ))
+            .id();
+        world.resource_mut::<ArenaGrid>().place(
+            gladiator,
+            ArenaGrid::get_grid_location(position.x, position.y),
+        );
+        gladiator
+    }
+
+    fn update(world: &mut World) {
+        SystemStage::single_threaded()
+            .with_system(update_morale)
+            .run(world);
+    }
+
+    fn state(world: &World, gladiator: Entity) -> MoraleState {
+        world.get::<Morale>(gladiator).unwrap().state
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.init_resource::<ArenaGrid>();
+        world
+    }
+
+    #[test]
+    fn wounded_and_outnumbered_gladiators_break_and_run() {
+        let mut world = world();
+        let wounded = spawn(&mut world, Vec2::ZERO, 10.0, 0);
+        let enemy_a = spawn(&mut world, Vec2::new(20.0, 0.0), 100.0, 1);
+        let enemy_b = spawn(&mut world, Vec2::new(0.0, 20.0), 100.0, 1);
+        world
+            .entity_mut(wounded)
+            .insert(Engagement { target: enemy_a });
+        world
+            .entity_mut(enemy_a)
+            .insert(Engagement { target: wounded });
+
+        update(&mut world);
+
+        assert_eq!(
+            state(&world, wounded),
+            MoraleState::Fleeing {
+                from: Vec2::new(10.0, 10.0)
+            }
+        );
+        assert!(Morale::is_fleeing(world.get::<Morale>(wounded)));
+        assert!(world.get::<Engagement>(wounded).is_none());
+        assert!(world.get::<Engagement>(enemy_a).is_none());
+        // healthy and with an ally at their side, the enemies smell blood
+        assert_eq!(
+            state(&world, enemy_b),
+            MoraleState::Eager { toward: Vec2::ZERO }
+        );
+    }
+
+    #[test]
+    fn fleeing_gladiators_only_rally_above_the_rally_threshold() {
+        let mut world = world();
+        // alone, with morale between the flee and rally thresholds
+        let steady = spawn(&mut world, Vec2::ZERO, 17.0, 0);
+        let fleeing = spawn(&mut world, Vec2::new(600.0, 0.0), 17.0, 0);
+        world.get_mut::<Morale>(fleeing).unwrap().state = MoraleState::Fleeing { from: Vec2::ZERO };
+
+        update(&mut world);
+
+        let morale = world.get::<Morale>(steady).unwrap().value;
+        assert!(morale > MORALE_FLEE_THRESHOLD && morale < MORALE_RALLY_THRESHOLD);
+        assert_eq!(state(&world, steady), MoraleState::Steady);
+        assert!(Morale::is_fleeing(world.get::<Morale>(fleeing)));
+
+        world.get_mut::<Health>(fleeing).unwrap().value = 60.0;
+        update(&mut world);
+
+        assert_eq!(state(&world, fleeing), MoraleState::Steady);
+    }
+
+    #[test]
+    fn kills_lift_morale_until_the_boost_wears_off() {
+        let mut world = world();
+        world.init_resource::<Events<DeathEvent>>();
+        let victor = spawn(&mut world, Vec2::ZERO, 50.0, 0);
+        let slain = spawn(&mut world, Vec2::new(20.0, 0.0), 0.0, 1);
+        world.send_event(DeathEvent {
+            victor: Some(victor),
+            xp_earned: 1.0,
+            slain,
+        });
+
+        SystemStage::single_threaded()
+            .with_system(morale_from_kills)
+            .run(&mut world);
+        assert_eq!(
+            world.get::<Morale>(victor).unwrap().kill_boost,
+            MORALE_PER_KILL
+        );
+
+        world.despawn(slain);
+        update(&mut world);
+        let boosted = world.get::<Morale>(victor).unwrap().value;
+        assert!(boosted > MORALE_BASE);
+
+        for _ in 0..(MORALE_PER_KILL / (MORALE_KILL_DECAY * MORALE_STEP)).ceil() as usize {
+            update(&mut world);
+        }
+        assert_eq!(world.get::<Morale>(victor).unwrap().kill_boost, 0.0);
+        assert_eq!(world.get::<Morale>(victor).unwrap().value, MORALE_BASE);
+    }
+}
@@ -1,120 +1,124 @@
 use bevy::prelude::*;
-use rand::{self, distributions::WeightedIndex, prelude::*, rngs::ThreadRng};
+use rand::{self, prelude::*};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     animation::*,
+    arena::*,
     engagements::*,
-    gladiator::gladiator_components::*,
+    gladiator::{gladiator_ai::*, gladiator_components::*},
     grid::*,
     player::player_components::*,
     *, // game_lib
 };
 
-/// Moves gladiators not controlled by the player
+/// Moves gladiators not controlled by the player. Every gladiator moves on its own, so
+/// they are moved in parallel batches.
 /// TODO we can have a disjoint query here. One that is With<Engagement>
 /// and the other that is Without<Engagement> and handle the movement
 /// differently.
 /// It would be helpful to break this out into two functions to do that.
 /// For now just going to filter query to remove engaged Gladiators
 pub fn gladiator_movement(
+    arena_grid: Res<ArenaGrid>,
     mut query: Query<
-        (&mut Transform, &Movement, &mut Animation, Entity),
+        (&mut Transform, &Movement, &mut Animation, Option<&Intent>),
         (With<Gladiator>, Without<Player>, Without<Engagement>),
     >,
-    mut ev_grid_change: EventWriter<GridChangeEvent>,
 ) {
-    for (mut transform, movement, mut animation, entity) in &mut query {
-        // initialize animation type if switching from another animation.
-        if !matches!(&animation.animation_type, &AnimationType::Walk) {
-            animation.animation_type = AnimationType::Walk;
-            animation.frame_index = 0;
-        }
-
-        let mut direction_probability = GladiatorDirectionProbability::new();
+    query.par_for_each_mut(
+        PARALLEL_BATCH_SIZE,
+        |(mut transform, movement, mut animation, intent)| {
+            let action = intent.map_or(AiAction::Wander, |intent| intent.action);
+
+            // holding gladiators wait where they are for the enemy to come to them
+            if action == AiAction::Hold {
+                if !matches!(&animation.animation_type, &AnimationType::Idle) {
+                    animation.animation_type = AnimationType::Idle;
+                    animation.frame_index = 0;
+                }
+                return;
+            }
 
-        // determine previous direction to weight the probability.
-        let prev_direction_weight = 100;
-        match animation.animation_direction {
-            GladiatorDirection::Down => direction_probability.down = prev_direction_weight,
-            GladiatorDirection::DownRight => {
-                direction_probability.down_right = prev_direction_weight
+            // initialize animation type if switching from another animation.
+            if !matches!(&animation.animation_type, &AnimationType::Walk) {
+                animation.animation_type = AnimationType::Walk;
+                animation.frame_index = 0;
             }
-            GladiatorDirection::Right => direction_probability.right = prev_direction_weight,
-            GladiatorDirection::UpRight => direction_probability.up_right = prev_direction_weight,
-            GladiatorDirection::Up => direction_probability.up = prev_direction_weight,
-            GladiatorDirection::UpLeft => direction_probability.up_left = prev_direction_weight,
-            GladiatorDirection::Left => direction_probability.left = prev_direction_weight,
-            GladiatorDirection::DownLeft => direction_probability.down_left = prev_direction_weight,
-        }
 
-        let new_direction = direction_probability.get_direction();
-        let (mut x_movement, mut y_movement) = new_direction.to_movement();
-        animation.animation_direction = new_direction;
-
-        // maintain either left or right, otherwise default to left
-        // This movement is just a placeholder until they get path planning.
-        // let mut x_movement: i16 = -1;
-        // (animation.animation_direction, x_movement) = match animation.animation_direction {
-        //     GladiatorDirection::Down => (GladiatorDirection::Left, -1),
-        //     GladiatorDirection::DownRight => (GladiatorDirection::Left, -1),
-        //     GladiatorDirection::Right => (GladiatorDirection::Right, 1),
-        //     GladiatorDirection::UpRight => (GladiatorDirection::Left, -1),
-        //     GladiatorDirection::Up => (GladiatorDirection::Left, -1),
-        //     GladiatorDirection::UpLeft => (GladiatorDirection::Left, -1),
-        //     GladiatorDirection::Left => (GladiatorDirection::Left, -1),
-        //     GladiatorDirection::DownLeft => (GladiatorDirection::Left, -1),
-        // };
-
-        // if too far left, go right
-        if (transform.translation[0] + x_movement) < (-WINDOW_WIDTH / 2.) {
-            animation.animation_direction = GladiatorDirection::Right;
-            x_movement = 1.0;
-        }
+            // gladiators that decided where they want to go head there, see gladiator_ai
+            let new_direction = action
+                .direction(transform.translation.truncate())
+                .unwrap_or_else(|| {
+                    GladiatorDirection::random_walk(
+                        animation.animation_direction,
+                        &mut rand::thread_rng(),
+                    )
+                });
+            let (mut x_movement, mut y_movement) = new_direction.to_movement();
+            animation.animation_direction = new_direction;
+
+            // maintain either left or right, otherwise default to left
+            // This movement is just a placeholder until they get path planning.
+            // let mut x_movement: i16 = -1;
+            // (animation.animation_direction, x_movement) = match animation.animation_direction {
+            //     GladiatorDirection::Down => (GladiatorDirection::Left, -1),
+            //     GladiatorDirection::DownRight => (GladiatorDirection::Left, -1),
+            //     GladiatorDirection::Right => (GladiatorDirection::Right, 1),
+            //     GladiatorDirection::UpRight => (GladiatorDirection::Left, -1),
+            //     GladiatorDirection::Up => (GladiatorDirection::Left, -1),
+            //     GladiatorDirection::UpLeft => (GladiatorDirection::Left, -1),
+            //     GladiatorDirection::Left => (GladiatorDirection::Left, -1),
+            //     GladiatorDirection::DownLeft => (GladiatorDirection::Left, -1),
+            // };
+
+            // if too far left, go right
+            if (transform.translation[0] + x_movement) < (-WINDOW_WIDTH / 2.) {
+                animation.animation_direction = GladiatorDirection::Right;
+                x_movement = 1.0;
+            }
 
-        // if too far right, go left
-        if (transform.translation[0] + x_movement) > (WINDOW_WIDTH / 2.) {
-            animation.animation_direction = GladiatorDirection::Left;
-            x_movement = -1.0;
-        }
+            // if too far right, go left
+            if (transform.translation[0] + x_movement) > (WINDOW_WIDTH / 2.) {
+                animation.animation_direction = GladiatorDirection::Left;
+                x_movement = -1.0;
+            }
 
-        // if too far down, go up
-        if (transform.translation[1] + y_movement) < (-WINDOW_HEIGHT / 2.) {
-            animation.animation_direction = GladiatorDirection::Up;
-            y_movement = 1.0;
-        }
+            // if too far down, go up
+            if (transform.translation[1] + y_movement) < (-WINDOW_HEIGHT / 2.) {
+                animation.animation_direction = GladiatorDirection::Up;
+                y_movement = 1.0;
+            }
 
-        // if too far up, go down
-        if (transform.translation[1] + y_movement) > (WINDOW_HEIGHT / 2.) {
-            animation.animation_direction = GladiatorDirection::Left;
-            y_movement = -1.0;
-        }
+            // if too far up, go down
+            if (transform.translation[1] + y_movement) > (WINDOW_HEIGHT / 2.) {
+                animation.animation_direction = GladiatorDirection::Left;
+                y_movement = -1.0;
+            }
 
-        // determine previous grid location
-        let prev_grid_location =
-            ArenaGrid::get_grid_location(transform.translation[0], transform.translation[1]);
-
-        // apply the movement
-        let translation_delta =
-            Vec3::new(x_movement.into(), y_movement.into(), 0.0) * movement.speed;
-        transform.translation += translation_delta;
-
-        // determine current grid location
-        let current_grid_location =
-            ArenaGrid::get_grid_location(transform.translation[0], transform.translation[1]);
-
-        // emit event if entering a new grid location
-        if current_grid_location != prev_grid_location {
-            ev_grid_change.send(GridChangeEvent {
-                entity,
-                prev_loc: prev_grid_location,
-                curr_loc: current_grid_location,
-            });
-        }
-    }
+            // apply the movement around the terrain, the ArenaGrid picks it up from the
+            // changed Transform
+            let delta = Vec2::new(x_movement, y_movement) * movement.current_speed();
+            let step = terrain_step(&arena_grid, transform.translation.truncate(), delta);
+            match GladiatorDirection::toward(step) {
+                Some(direction) => animation.animation_direction = direction,
+                // stuck, try the other way next time
+                None if delta != Vec2::ZERO => {
+                    animation.animation_direction = animation.animation_direction.opposite()
+                }
+                None => {}
+            }
+            transform.translation += step.extend(0.0);
+        },
+    );
 }
 
-#[derive(Clone, Copy)]
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, FromReflect, Serialize, Deserialize,
+)]
+#[reflect(Serialize, Deserialize)]
 pub enum GladiatorDirection {
+    #[default]
     Down = 0,
     DownRight = 1,
     Right = 2,
@@ -126,6 +130,34 @@ pub enum GladiatorDirection {
 }
 
 impl GladiatorDirection {
+    const ALL: [GladiatorDirection; 8] = [
+        GladiatorDirection::Down,
+        GladiatorDirection::DownRight,
+        GladiatorDirection::Right,
+        GladiatorDirection::UpRight,
+        GladiatorDirection::Up,
+        GladiatorDirection::UpLeft,
+        GladiatorDirection::Left,
+        GladiatorDirection::DownLeft,
+    ];
+
+    /// One step of the random walk: mostly keeps going the same way, sometimes turns.
+    /// The previous direction has a weight of RANDOM_WALK_KEEP_WEIGHT and every other
+    /// direction a weight of 1.
+    /// * `previous` - the direction the gladiator was going in
+    /// * `rng` - where the randomness comes from
+    pub fn random_walk(previous: Self, rng: &mut impl Rng) -> Self {
+        let roll = rng.gen_range(0..RANDOM_WALK_KEEP_WEIGHT + 7);
+        if roll < RANDOM_WALK_KEEP_WEIGHT {
+            return previous;
+        }
+        Self::ALL
+            .into_iter()
+            .filter(|direction| *direction != previous)
+            .nth((roll - RANDOM_WALK_KEEP_WEIGHT) as usize)
+            .unwrap_or(previous)
+    }
+
     pub fn from_movement(x_movement: i32, y_movement: i32) -> Result<Self, String> {
         match (x_movement, y_movement) {
             (1, 1) => Ok(GladiatorDirection::UpRight),
@@ -143,6 +175,29 @@ impl GladiatorDirection {
         }
     }
 
+    /// Snaps a heading to the nearest of the 8 directions, None for a zero heading
+    /// * `heading` - the way to go, doesn't need to be normalized
+    pub fn toward(heading: Vec2) -> Option<Self> {
+        if heading == Vec2::ZERO {
+            return None;
+        }
+
+        // a component counts if it is at least half of the larger one
+        let threshold = heading.x.abs().max(heading.y.abs()) / 2.0;
+        let snap = |value: f32| {
+            if value.abs() >= threshold {
+                value.signum() as i32
+            } else {
+                0
+            }
+        };
+        Self::from_movement(snap(heading.x), snap(heading.y)).ok()
+    }
+
+    pub fn opposite(&self) -> Self {
+        Self::ALL[(*self as usize + 4) % Self::ALL.len()]
+    }
+
     pub fn to_movement(&self) -> (f32, f32) {
         match self {
             GladiatorDirection::Down => (0., -1.),
@@ -156,68 +211,3 @@ impl GladiatorDirection {
         }
     }
 }
-
-struct GladiatorDirectionProbability {
-    pub down: u32,
-    pub down_right: u32,
-    pub right: u32,
-    pub up_right: u32,
-    pub up: u32,
-    pub up_left: u32,
-    pub left: u32,
-    pub down_left: u32,
-    categorical_distribution: WeightedIndex<u32>,
-    directions: Vec<GladiatorDirection>,
-    rng: ThreadRng,
-}
-
-impl GladiatorDirectionProbability {
-    pub fn new() -> Self {
-        let directions = vec![
-            GladiatorDirection::Down,
-            GladiatorDirection::DownRight,
-            GladiatorDirection::Right,
-            GladiatorDirection::UpRight,
-            GladiatorDirection::Up,
-            GladiatorDirection::UpLeft,
-            GladiatorDirection::Left,
-            GladiatorDirection::DownLeft,
-        ];
-
-        // bad default
-        let categorical_distribution = WeightedIndex::new(&[1, 1, 1, 1, 1, 1, 1, 1]).unwrap();
-        Self {
-            down: 1,
-            down_right: 1,
-            right: 1,
-            up_right: 1,
-            up: 1,
-            up_left: 1,
-            left: 1,
-            down_left: 1,
-            categorical_distribution,
-            directions,
-            rng: rand::thread_rng(),
-        }
-    }
-
-    pub fn update_categorical_distribution(&mut self) {
-        self.categorical_distribution = WeightedIndex::new(&[
-            self.down,
-            self.down_right,
-            self.right,
-            self.up_right,
-            self.up,
-            self.up_left,
-            self.left,
-            self.down_left,
-        ])
-        .unwrap();
-    }
-
-    pub fn get_direction(&mut self) -> GladiatorDirection {
-        self.update_categorical_distribution();
-        // let mut rng = rand::thread_rng();
-        self.directions[self.categorical_distribution.sample(&mut self.rng)]
-    }
-}
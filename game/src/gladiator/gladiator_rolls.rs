@@ -0,0 +1,268 @@
+//! Attacks are not a straight comparison of stats. Every attack rolls to hit against the
+//! accuracy of the attacker and the evasion of the defender, then rolls for a critical
+//! hit, and the damage itself is drawn from the distribution set in
+//! assets/combat/combat.ron around the attacker's damage.
+//!
+//! All combat rolls come from the CombatRng, so the same seed always rolls the same
+//! sequence of hits, crits and damage. That doesn't replay a whole battle: spawns,
+//! movement and the frame timing are not seeded, so who attacks whom and when still
+//! differs. Pass the seed a run printed at startup to roll the same way again:
+//!  cargo run -- --seed 1234
+
+use bevy::prelude::*;
+use probability::{prelude::*, source};
+use serde::Deserialize;
+
+use crate::{damage::*, gladiator::gladiator_components::*, helper_functions::arg_value};
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Rolls an attack against a defender.
+/// * `attack` - the attack stats of the attacker
+/// * `defense` - the defense stats of the defender
+/// * `combat_config` - how the rolls are made
+/// * `combat_rng` - source of the rolls
+pub fn roll_attack(
+    attack: &Attack,
+    defense: &Defense,
+    combat_config: &CombatConfig,
+    combat_rng: &mut CombatRng,
+) -> AttackRoll {
+    let hit_chance = (attack.accuracy - defense.evasion).clamp(combat_config.min_hit_chance, 1.0);
+    if combat_rng.chance() >= hit_chance {
+        return AttackRoll::Miss;
+    }
+
+    let damage = combat_config
+        .damage_roll
+        .roll(attack.damage, combat_rng)
+        .max(0.0);
+    if combat_rng.chance() < attack.crit_chance {
+        AttackRoll::Critical(damage * combat_config.crit_multiplier)
+    } else {
+        AttackRoll::Hit(damage)
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttackRoll {
+    Miss,
+    Hit(f32),
+    Critical(f32),
+}
+
+/// Seeded source of randomness for everything that is rolled in combat
+#[derive(Resource)]
+pub struct CombatRng {
+    pub seed: u64,
+    source: source::Default,
+}
+
+impl CombatRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            source: source::default(seed),
+        }
+    }
+
+    /// Reads the seed from `--seed <n>` in the command line arguments, or picks a random
+    /// seed when there is none. The seed is printed so the run can be rolled again.
+    /// * `args` - command line arguments, usually std::env::args()
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let combat_rng = match arg_value(args, "--seed").map(|seed| seed.parse()) {
+            Some(Ok(seed)) => Self::new(seed),
+            Some(Err(_)) => {
+                println!("--seed needs to be a whole number, picking a random seed.");
+                Self::default()
+            }
+            None => Self::default(),
+        };
+        println!("Combat seed: {}", combat_rng.seed);
+        combat_rng
+    }
+
+    /// A number in [0, 1) to compare against a chance
+    pub fn chance(&mut self) -> f32 {
+        Uniform::new(0.0, 1.0).sample(&mut self.source) as f32
+    }
+
+    pub fn sample<D: Sample<Value = f64>>(&mut self, distribution: &D) -> f32 {
+        distribution.sample(&mut self.source) as f32
+    }
+}
+
+impl Default for CombatRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+/// Contents of assets/combat/combat.ron
+#[derive(Resource, Deserialize)]
+pub struct CombatConfig {
+    pub damage_roll: DamageRoll,
+    /// Damage of a critical hit compared to a normal one
+    pub crit_multiplier: f32,
+    /// Even the clumsiest gladiator hits this often
+    pub min_hit_chance: f32,
+    /// How defense reduces the damage of a hit
+    #[serde(default)]
+    pub mitigation: MitigationModel,
+}
+
+/// Distribution the damage of an attack is drawn from. Spreads are fractions of the
+/// attacker's damage, so a spread of 0.2 on 10 damage rolls between 8 and 12.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum DamageRoll {
+    /// Always exactly the attacker's damage
+    Fixed,
+    /// Every value within the spread is equally likely
+    Uniform { spread: f32 },
+    /// Values near the attacker's damage are the most likely, tapering off to the spread
+    Triangular { spread: f32 },
+    /// Bell curve around the attacker's damage
+    Gaussian { std_dev: f32 },
+}
+
+impl DamageRoll {
+    pub fn roll(&self, damage: f32, combat_rng: &mut CombatRng) -> f32 {
+        let damage = damage as f64;
+        match *self {
+            Self::Uniform { spread } if spread > 0.0 && damage > 0.0 => {
+                let spread = damage * spread as f64;
+                combat_rng.sample(&Uniform::new(damage - spread, damage + spread))
+            }
+            Self::Triangular { spread } if spread > 0.0 && damage > 0.0 => {
+                let spread = damage * spread as f64;
+                combat_rng.sample(&Triangular::new(damage - spread, damage + spread, damage))
+            }
+            Self::Gaussian { std_dev } if std_dev > 0.0 && damage > 0.0 => {
+                combat_rng.sample(&Gaussian::new(damage, damage * std_dev as f64))
+            }
+            // no spread to roll within
+            _ => damage as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(damage_roll: DamageRoll) -> CombatConfig {
+        CombatConfig {
+            damage_roll,
+            crit_multiplier: 2.0,
+            min_hit_chance: 0.05,
+            mitigation: MitigationModel::default(),
+        }
+    }
+
+    fn attack(accuracy: f32, crit_chance: f32) -> Attack {
+        Attack {
+            damage: 10.0,
+            damage_type: DamageType::Slash,
+            accuracy,
+            crit_chance,
+        }
+    }
+
+    fn defense(evasion: f32) -> Defense {
+        Defense {
+            value: 0.0,
+            evasion,
+        }
+    }
+
+    fn rolls(seed: u64, n: usize) -> Vec<AttackRoll> {
+        let combat_config = config(DamageRoll::Gaussian { std_dev: 0.2 });
+        let mut combat_rng = CombatRng::new(seed);
+        (0..n)
+            .map(|_| {
+                roll_attack(
+                    &attack(0.7, 0.2),
+                    &defense(0.1),
+                    &combat_config,
+                    &mut combat_rng,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_rolls_the_same_attacks() {
+        assert_eq!(rolls(1234, 100), rolls(1234, 100));
+        assert_ne!(rolls(1234, 100), rolls(4321, 100));
+    }
+
+    #[test]
+    fn certain_hits_without_crits_do_the_fixed_damage() {
+        let mut combat_rng = CombatRng::new(1);
+        for _ in 0..100 {
+            let roll = roll_attack(
+                &attack(1.0, 0.0),
+                &defense(0.0),
+                &config(DamageRoll::Fixed),
+                &mut combat_rng,
+            );
+            assert_eq!(roll, AttackRoll::Hit(10.0));
+        }
+    }
+
+    #[test]
+    fn certain_crits_multiply_the_damage() {
+        let mut combat_rng = CombatRng::new(1);
+        for _ in 0..100 {
+            let roll = roll_attack(
+                &attack(1.0, 1.0),
+                &defense(0.0),
+                &config(DamageRoll::Fixed),
+                &mut combat_rng,
+            );
+            assert_eq!(roll, AttackRoll::Critical(20.0));
+        }
+    }
+
+    #[test]
+    fn evasion_can_not_push_the_hit_chance_below_the_minimum() {
+        let mut combat_rng = CombatRng::new(1);
+        let n_hits = (0..10_000)
+            .filter(|_| {
+                roll_attack(
+                    &attack(0.5, 0.0),
+                    &defense(1.0),
+                    &config(DamageRoll::Fixed),
+                    &mut combat_rng,
+                ) != AttackRoll::Miss
+            })
+            .count();
+        // 5% of 10,000, give or take
+        assert!((300..700).contains(&n_hits), "{} hits", n_hits);
+    }
+
+    #[test]
+    fn damage_rolls_stay_within_their_spread() {
+        let mut combat_rng = CombatRng::new(1);
+        for damage_roll in [
+            DamageRoll::Uniform { spread: 0.2 },
+            DamageRoll::Triangular { spread: 0.2 },
+        ] {
+            for _ in 0..1_000 {
+                let damage = damage_roll.roll(10.0, &mut combat_rng);
+                assert!(
+                    (8.0..=12.0).contains(&damage),
+                    "{:?} rolled {}",
+                    damage_roll,
+                    damage
+                );
+            }
+        }
+    }
+}
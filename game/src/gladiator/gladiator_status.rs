@@ -0,0 +1,381 @@
+//! Status effects are timed conditions on a gladiator: bleeding and poison deal damage
+//! over time, slows change how fast they move, stuns stop them from moving and attacking,
+//! and regeneration heals them. They tick every STATUS_EFFECT_STEP, separately from
+//! attacks and movement.
+//!
+//! Weapons apply their on-hit effect (see assets/items/items.ron) to whoever they hit,
+//! and some items have an effect when used, e.g. a GreenMushroom poisons whoever eats it.
+//! Anything else that wants to apply an effect, like skills, pushes it with
+//! StatusEffects::apply.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gladiator::{gladiator_components::*, gladiator_events::*},
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Applies one step of every status effect and drops the ones that ran out. Gladiators
+/// that bleed or are poisoned to death are credited to whoever applied the effect.
+/// Movement and the AttackTimer are only touched when a slow or stun starts or ends, and
+/// only a timer paused for a stun is unpaused again, anything else that paused it keeps
+/// it paused.
+pub fn tick_status_effects(
+    mut ev_death: EventWriter<DeathEvent>,
+    mut query: Query<(
+        Entity,
+        &mut StatusEffects,
+        &mut Health,
+        &mut Movement,
+        &mut AttackTimer,
+        &Level,
+    )>,
+) {
+    for (entity, mut status_effects, mut health, mut movement, mut attack_timer, level) in
+        &mut query
+    {
+        // the last source of damage over time gets the kill
+        let mut damage_source = None;
+        let was_alive = health.value >= 0.0;
+        let mut speed_modifier = 1.0;
+        let mut stunned = false;
+
+        for effect in status_effects.effects.iter_mut() {
+            match effect.kind {
+                StatusEffectKind::Bleed { damage_per_second }
+                | StatusEffectKind::Poison { damage_per_second } => {
+                    health.value -= damage_per_second * STATUS_EFFECT_STEP;
+                    damage_source = Some(effect.source);
+                }
+                StatusEffectKind::Slow { speed_multiplier } => speed_modifier *= speed_multiplier,
+                StatusEffectKind::Stun => stunned = true,
+                StatusEffectKind::Regeneration { health_per_second } => {
+                    health.heal(health_per_second * STATUS_EFFECT_STEP);
+                }
+            }
+            effect.duration -= STATUS_EFFECT_STEP;
+        }
+        status_effects
+            .effects
+            .retain(|effect| effect.duration > 0.0);
+
+        let speed_modifier = if stunned { 0.0 } else { speed_modifier };
+        if movement.speed_modifier != speed_modifier {
+            movement.speed_modifier = speed_modifier;
+        }
+        if status_effects.stunned != stunned {
+            status_effects.stunned = stunned;
+            if stunned {
+                attack_timer.pause();
+            } else {
+                attack_timer.unpause();
+            }
+        }
+
+        // gladiators that were already dying had their DeathEvent sent by whatever did it
+        if let Some(source) = damage_source {
+            if was_alive && health.value < 0.0 {
+                println!("{:?} succumbed to their wounds!", entity);
+                ev_death.send(DeathEvent {
+                    victor: source,
+                    xp_earned: level.convert_to_xp(),
+                    slain: entity,
+                });
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// The status effects a gladiator is currently suffering from (or enjoying)
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+    /// Whether the AttackTimer is paused for a stun, so it is unpaused once the stun is over
+    #[serde(skip)]
+    pub stunned: bool,
+}
+
+impl StatusEffects {
+    /// Adds an effect. Applying an effect of a kind the gladiator already has refreshes
+    /// its duration instead of stacking.
+    pub fn apply(&mut self, effect: StatusEffect) {
+        let existing = self.effects.iter_mut().find(|existing| {
+            std::mem::discriminant(&existing.kind) == std::mem::discriminant(&effect.kind)
+        });
+        match existing {
+            Some(existing) => *existing = effect,
+            None => self.effects.push(effect),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// Seconds left until the effect wears off
+    pub duration: f32,
+    /// Whoever applied the effect, they get the credit if it kills
+    #[reflect(ignore)]
+    #[serde(skip)]
+    pub source: Option<Entity>,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusEffectKind, duration: f32) -> Self {
+        Self {
+            kind,
+            duration,
+            source: None,
+        }
+    }
+
+    /// The same effect, applied by the given gladiator
+    pub fn from_source(mut self, source: Entity) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Reflect, FromReflect, Serialize, Deserialize)]
+pub enum StatusEffectKind {
+    Bleed {
+        damage_per_second: f32,
+    },
+    Poison {
+        damage_per_second: f32,
+    },
+    /// Multiplies movement speed, below 1.0 slows the gladiator down
+    Slow {
+        speed_multiplier: f32,
+    },
+    /// Can't move or attack
+    #[default]
+    Stun,
+    Regeneration {
+        health_per_second: f32,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < EPSILON,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    /// A world with one gladiator that has the given health and effects
+    fn world_with(health: f32, effects: Vec<StatusEffect>) -> (World, Entity) {
+        let mut world = World::new();
+        world.init_resource::<Events<DeathEvent>>();
+        let gladiator = world
+            .spawn((
+                StatusEffects {
+                    effects,
+                    ..default()
+                },
+                Health {
+                    value: health,
+                    max: 100.0,
+                },
+                Movement::new(100.0),
+                AttackTimer(Timer::from_seconds(1.0, TimerMode::Repeating)),
+                Level::default(),
+            ))
+            .id();
+        (world, gladiator)
+    }
+
+    fn tick(world: &mut World, n_ticks: usize) {
+        let mut stage = SystemStage::single_threaded().with_system(tick_status_effects);
+        for _ in 0..n_ticks {
+            stage.run(world);
+        }
+    }
+
+    fn deaths(world: &World) -> Vec<(Option<Entity>, Entity)> {
+        let events = world.resource::<Events<DeathEvent>>();
+        events
+            .get_reader()
+            .iter(events)
+            .map(|death| (death.victor, death.slain))
+            .collect()
+    }
+
+    #[test]
+    fn applying_the_same_kind_refreshes_instead_of_stacking() {
+        let mut status_effects = StatusEffects::default();
+        status_effects.apply(StatusEffect::new(
+            StatusEffectKind::Bleed {
+                damage_per_second: 5.0,
+            },
+            2.0,
+        ));
+        status_effects.apply(StatusEffect::new(
+            StatusEffectKind::Bleed {
+                damage_per_second: 3.0,
+            },
+            4.0,
+        ));
+        status_effects.apply(StatusEffect::new(StatusEffectKind::Stun, 1.0));
+
+        assert_eq!(status_effects.effects.len(), 2);
+        let bleed = status_effects.effects[0];
+        assert!(matches!(
+            bleed.kind,
+            StatusEffectKind::Bleed { damage_per_second } if damage_per_second == 3.0
+        ));
+        assert_close(bleed.duration, 4.0);
+    }
+
+    #[test]
+    fn damage_over_time_ticks_until_it_wears_off() {
+        let bleed = StatusEffect::new(
+            StatusEffectKind::Bleed {
+                damage_per_second: 10.0,
+            },
+            2.0 * STATUS_EFFECT_STEP,
+        );
+        let (mut world, gladiator) = world_with(100.0, vec![bleed]);
+
+        tick(&mut world, 1);
+        assert_close(
+            world.get::<Health>(gladiator).unwrap().value,
+            100.0 - 10.0 * STATUS_EFFECT_STEP,
+        );
+
+        tick(&mut world, 3);
+        assert_close(
+            world.get::<Health>(gladiator).unwrap().value,
+            100.0 - 20.0 * STATUS_EFFECT_STEP,
+        );
+        assert!(world
+            .get::<StatusEffects>(gladiator)
+            .unwrap()
+            .effects
+            .is_empty());
+    }
+
+    #[test]
+    fn regeneration_does_not_heal_past_the_max() {
+        let regeneration = StatusEffect::new(
+            StatusEffectKind::Regeneration {
+                health_per_second: 1000.0,
+            },
+            1.0,
+        );
+        let (mut world, gladiator) = world_with(50.0, vec![regeneration]);
+
+        tick(&mut world, 1);
+
+        assert_close(world.get::<Health>(gladiator).unwrap().value, 100.0);
+    }
+
+    #[test]
+    fn slows_and_stuns_hold_the_gladiator_back_until_they_wear_off() {
+        let slow = StatusEffect::new(
+            StatusEffectKind::Slow {
+                speed_multiplier: 0.5,
+            },
+            2.0 * STATUS_EFFECT_STEP,
+        );
+        let stun = StatusEffect::new(StatusEffectKind::Stun, STATUS_EFFECT_STEP);
+        let (mut world, gladiator) = world_with(100.0, vec![slow, stun]);
+
+        tick(&mut world, 1);
+        assert_close(
+            world.get::<Movement>(gladiator).unwrap().speed_modifier,
+            0.0,
+        );
+        assert!(world.get::<AttackTimer>(gladiator).unwrap().paused());
+
+        // the stun wore off, the slow is still there
+        tick(&mut world, 1);
+        assert_close(
+            world.get::<Movement>(gladiator).unwrap().speed_modifier,
+            0.5,
+        );
+        assert!(!world.get::<AttackTimer>(gladiator).unwrap().paused());
+
+        tick(&mut world, 1);
+        assert_close(
+            world.get::<Movement>(gladiator).unwrap().speed_modifier,
+            1.0,
+        );
+    }
+
+    #[test]
+    fn movement_and_the_attack_timer_are_left_alone_without_slows_or_stuns() {
+        let bleed = StatusEffect::new(
+            StatusEffectKind::Bleed {
+                damage_per_second: 1.0,
+            },
+            1.0,
+        );
+        let (mut world, gladiator) = world_with(100.0, vec![bleed]);
+        world.clear_trackers();
+
+        tick(&mut world, 2);
+
+        let mut changed =
+            world.query_filtered::<Entity, Or<(Changed<Movement>, Changed<AttackTimer>)>>();
+        assert_eq!(changed.iter(&world).count(), 0);
+        assert!(world.get::<Health>(gladiator).unwrap().value < 100.0);
+    }
+
+    #[test]
+    fn only_timers_paused_for_a_stun_are_unpaused() {
+        let (mut world, gladiator) = world_with(100.0, Vec::new());
+        world.get_mut::<AttackTimer>(gladiator).unwrap().pause();
+
+        tick(&mut world, 2);
+        assert!(world.get::<AttackTimer>(gladiator).unwrap().paused());
+
+        world.get_mut::<AttackTimer>(gladiator).unwrap().unpause();
+        world
+            .get_mut::<StatusEffects>(gladiator)
+            .unwrap()
+            .apply(StatusEffect::new(
+                StatusEffectKind::Stun,
+                STATUS_EFFECT_STEP,
+            ));
+        tick(&mut world, 1);
+        assert!(world.get::<AttackTimer>(gladiator).unwrap().paused());
+        tick(&mut world, 1);
+        assert!(!world.get::<AttackTimer>(gladiator).unwrap().paused());
+    }
+
+    #[test]
+    fn bleeding_to_death_credits_whoever_applied_it_once() {
+        let source = Entity::from_raw(1000);
+        let bleed = StatusEffect::new(
+            StatusEffectKind::Bleed {
+                damage_per_second: 10.0,
+            },
+            10.0,
+        )
+        .from_source(source);
+        let (mut world, gladiator) = world_with(1.0, vec![bleed]);
+
+        tick(&mut world, 3);
+
+        assert_eq!(deaths(&world), vec![(Some(source), gladiator)]);
+    }
+}
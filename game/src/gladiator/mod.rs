@@ -1,6 +1,11 @@
 pub mod gladiator;
+pub mod gladiator_ai;
 pub mod gladiator_bundles;
 pub mod gladiator_combat;
 pub mod gladiator_components;
+pub mod gladiator_equipment;
 pub mod gladiator_events;
+pub mod gladiator_morale;
 pub mod gladiator_movement;
+pub mod gladiator_rolls;
+pub mod gladiator_status;
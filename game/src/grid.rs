@@ -1,64 +1,108 @@
-use std::collections::HashMap;
-
-use bevy::prelude::*;
+use bevy::{prelude::*, time::FixedTimestep, utils::HashMap};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    gladiator::{gladiator_combat::*, gladiator_events::DeathEvent},
+    arena::*,
+    engagements::*,
+    gladiator::gladiator_components::*,
     *, // game_lib
 };
 
-#[derive(Debug)]
-pub struct GridChangeEvent {
-    pub entity: Entity,
-    pub prev_loc: GridLocation,
-    pub curr_loc: GridLocation,
-}
-
+/// Keeps the ArenaGrid in sync with where the gladiators are. Gladiators are added when
+/// they are spawned, moved when their Transform changes and removed when they are
+/// despawned (or stop being a Gladiator), so systems that move or despawn gladiators
+/// don't need to tell the grid about it. This happens in PostUpdate, after every
+/// gameplay system had its say, so during Update the grid is one frame behind.
+///
+/// Every GRID_VERIFICATION_STEP the grid is checked against the Transforms and repaired
+/// if anything slipped through. Build with `--features grid-assertions` to check it
+/// every frame and panic on the first inconsistency instead.
 pub struct GridPlugin;
 
 impl Plugin for GridPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<GridChangeEvent>()
-            .add_system(evaluate_grid)
-            .add_system(
-                prune_grid
-                    .after(gladiator_receive_attack)
-                    .before(gladiator_death_handler),
-            )
-            .init_resource::<ArenaGrid>();
+        app.register_type::<GridLocation>()
+            .register_type::<ArenaGrid>()
+            .init_resource::<ArenaGrid>()
+            .add_system_to_stage(CoreStage::PostUpdate, sync_grid)
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(GRID_VERIFICATION_STEP as f64))
+                    .with_system(verify_grid.after(sync_grid)),
+            );
+
+        #[cfg(feature = "grid-assertions")]
+        app.add_system_to_stage(CoreStage::PostUpdate, assert_grid.after(sync_grid));
     }
 }
 
-fn evaluate_grid(
-    mut ev_grid_change: EventReader<GridChangeEvent>,
+/// Adds, moves and removes gladiators in the grid, and flags the grid locations of
+/// gladiators that got free of their engagement for the engagement_builder
+fn sync_grid(
     mut arena_grid: ResMut<ArenaGrid>,
+    removed: RemovedComponents<Gladiator>,
+    disengaged: RemovedComponents<Engagement>,
+    query: Query<(Entity, &Transform), (With<Gladiator>, Changed<Transform>)>,
 ) {
-    for event in ev_grid_change.iter() {
-        arena_grid.update_entity_location(event.entity, &event.prev_loc, &event.curr_loc);
+    for entity in removed.iter() {
+        arena_grid.remove(entity);
+    }
+    for entity in disengaged.iter() {
+        arena_grid.mark_dirty_at(entity);
+    }
+    for (entity, transform) in &query {
+        let location =
+            ArenaGrid::get_grid_location(transform.translation.x, transform.translation.y);
+        arena_grid.place(entity, location);
     }
 }
 
-/// Listens to DeathEvents and removes the gladiator that died from the arena grid.
-fn prune_grid(
+/// Repairs the grid if it disagrees with the Transforms of the gladiators
+fn verify_grid(
     mut arena_grid: ResMut<ArenaGrid>,
-    mut ev_death: EventReader<DeathEvent>,
-    query: Query<&Transform>,
+    query: Query<(Entity, &Transform), With<Gladiator>>,
 ) {
-    for event in ev_death.iter() {
-        let transform = query
-            .get(event.slain)
-            .expect("Slain gladiator should exist in ECS."); // this may be race condition with despawn? could handle by having grid go first or last always?
-        let grid_location =
-            ArenaGrid::get_grid_location(transform.translation[0], transform.translation[1]);
-        let gladiators = arena_grid
-            .grid_map
-            .get_mut(&grid_location)
-            .expect("Grid location of entity being removed should be present in grid_map.");
-        gladiators.retain(|x| x != &event.slain);
+    let locations = gladiator_locations(&query);
+    let problems = arena_grid.verify(locations.iter().copied());
+    if !problems.is_empty() {
+        warn!(
+            "ArenaGrid was out of sync, rebuilding it: {}",
+            problems.join("; ")
+        );
+        arena_grid.rebuild(locations.into_iter());
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq)]
+/// Panics as soon as the grid disagrees with the Transforms of the gladiators
+#[cfg(feature = "grid-assertions")]
+fn assert_grid(arena_grid: Res<ArenaGrid>, query: Query<(Entity, &Transform), With<Gladiator>>) {
+    let problems = arena_grid.verify(gladiator_locations(&query).into_iter());
+    assert!(
+        problems.is_empty(),
+        "ArenaGrid is out of sync: {}",
+        problems.join("; ")
+    );
+}
+
+/// Where every gladiator should be in the grid
+fn gladiator_locations(
+    query: &Query<(Entity, &Transform), With<Gladiator>>,
+) -> Vec<(Entity, GridLocation)> {
+    query
+        .iter()
+        .map(|(entity, transform)| {
+            let location =
+                ArenaGrid::get_grid_location(transform.translation.x, transform.translation.y);
+            (entity, location)
+        })
+        .collect()
+}
+
+#[derive(
+    Copy, Clone, Debug, Default, Eq, Hash, PartialEq, Reflect, FromReflect, Serialize, Deserialize,
+)]
+#[reflect(Hash, PartialEq, Serialize, Deserialize)]
 pub struct GridLocation {
     pub x: i32,
     pub y: i32,
@@ -66,44 +110,71 @@ pub struct GridLocation {
 
 /// ArenaGrid stores a Vec of Entitys that are located at each
 /// GridLocation, which is a section of the whole arena.
-/// The HashMap could either be reconstructed at a given rate or
-/// it could be optimized to only be updated by moving Gladiators,
-/// but that seems like a really complicated option. Initial impl
-/// will be to reconstruct by looping over all Gladiators at about
-/// half the interval as the MOVEMENT_STEPs are.
-/// This is likely a performance bottleneck, but can be optimized
-/// at a later time.
+/// It is kept up to date from the gladiators' Transforms by the GridPlugin.
+///
+/// The grid locations are stored densely, row by row, in one Vec that covers twice the
+/// window in each direction (where gladiators can be spawned). Gladiators that wander
+/// outside of that are kept in the nearest grid location on its edge. Every entity
+/// remembers its slot, so moving it to another grid location is a swap_remove and a push
+/// instead of a search.
 ///
-/// Possible option: Gladiators can ask for their current grid
-/// location and store their previous grid location. On change,
-/// an Event can be emitted that the ArenaGrid is listening for,
-/// which hopefully can have the Entity and location data in it.
-/// It would need previous coordinates as well as new, so that
-/// it could be removed from the previous GridLocation key.
-/// Gladiators may getting their current GridLocation anyways
-/// for the purposes of figuring out what other Gladiators
-/// are nearby so they can know where to move. Bit of a twofer
-/// so this may be a pretty sensible option and allows for
-/// carving out a chunkier grid as a performance lever.
+/// The grid also keeps track of which grid locations changed (gladiators came or went,
+/// or one of them got free of an engagement) so the engagement_builder only has to look
+/// at those.
 ///
-/// I think minimizing the mutable reference to this guy
-/// will be a performance win. I think that can be accomplished
-/// by the events so that the Grid can mutate itself.
+/// Every grid location also has a Terrain (open floor unless a map says otherwise, see
+/// the ArenaPlugin). Unlike the entities it is not touched by clear or rebuild.
 ///
 /// Gladiators will need to know their GridLocation and two
 /// other things:
 /// 1. Entity of Gladiator(unengaged) in the same GridLocation. (can engage)
 /// 2. Entities of Gladiators(unengaged) in any of the adjacent
 ///  GridLocations. (will move towards)
-#[derive(Resource, Default, Debug)]
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
 pub struct ArenaGrid {
-    pub grid_map: HashMap<GridLocation, Vec<Entity>>,
+    /// Entities in each grid location, see cell_index
+    cells: Vec<Vec<Entity>>,
+    /// Where each entity is in the cells, so it can be found again once it is despawned
+    #[reflect(ignore)]
+    slots: HashMap<Entity, GridSlot>,
+    /// Indices of the cells that changed since the last clear_dirty
+    #[reflect(ignore)]
+    dirty: Vec<usize>,
+    #[reflect(ignore)]
+    is_dirty: Vec<bool>,
+    /// Terrain of each grid location, see the ArenaPlugin
+    #[reflect(ignore)]
+    terrain: Vec<Terrain>,
     // https://docs.rs/bevy/latest/bevy/prelude/struct.Query.html#method.get
     // Store the Entity in this hashmap. Then any query that would contain
     // this Entity, can just use query.get(Entity) instead of looping through
     // the entire query.
 }
 
+impl Default for ArenaGrid {
+    fn default() -> Self {
+        let (min, max) = Self::bounds();
+        let n_cells = ((max.x - min.x + 1) * (max.y - min.y + 1)) as usize;
+        Self {
+            cells: vec![Vec::new(); n_cells],
+            slots: HashMap::default(),
+            dirty: Vec::new(),
+            is_dirty: vec![false; n_cells],
+            terrain: vec![Terrain::Open; n_cells],
+        }
+    }
+}
+
+/// Where an entity is stored in the ArenaGrid
+#[derive(Clone, Copy, Debug)]
+struct GridSlot {
+    /// Index of the cell
+    cell: usize,
+    /// Index of the entity in the cell's Vec
+    index: usize,
+}
+
 impl ArenaGrid {
     /// Takes x,y coordinates and returns the GridLocation.
     /// Normalized for window size with given number of divisions.
@@ -112,15 +183,12 @@ impl ArenaGrid {
     /// * `x`: pixel coordinate in x direction
     /// * `y`: pixel coordinate in y direction
     pub fn get_grid_location(x: f32, y: f32) -> GridLocation {
-        // TODO - turn these into lazy statics so that we don't have to compute this so many times.
-        let grid_cell_width = WINDOW_WIDTH / GRID_HORIZONTAL_DIVISIONS;
-        let n_vertical_divisions = GRID_HORIZONTAL_DIVISIONS * (WINDOW_HEIGHT / WINDOW_WIDTH);
-        let grid_cell_height = WINDOW_HEIGHT / n_vertical_divisions;
+        let cell_size = Self::cell_size();
 
-        let x_grid_num = ((x.abs() / grid_cell_width) + 0.5).floor();
+        let x_grid_num = ((x.abs() / cell_size.x) + 0.5).floor();
         let horizontal_grid_location = (x.signum() * x_grid_num) as i32;
 
-        let y_grid_num = ((y.abs() / grid_cell_height) + 0.5).floor();
+        let y_grid_num = ((y.abs() / cell_size.y) + 0.5).floor();
         let vertical_grid_location = (y.signum() * y_grid_num) as i32;
 
         GridLocation {
@@ -129,81 +197,763 @@ impl ArenaGrid {
         }
     }
 
-    /// Returns a Vec of Entitys that are currently located in the
-    /// given grid location.
+    /// Pixel coordinates of the middle of a grid location, the inverse of get_grid_location
+    pub fn center_of(loc: GridLocation) -> Vec2 {
+        Vec2::new(loc.x as f32, loc.y as f32) * Self::cell_size()
+    }
+
+    /// Width and height of a grid location in pixels
+    pub fn cell_size() -> Vec2 {
+        // TODO - turn these into lazy statics so that we don't have to compute this so many times.
+        let grid_cell_width = WINDOW_WIDTH / GRID_HORIZONTAL_DIVISIONS;
+        let n_vertical_divisions = GRID_HORIZONTAL_DIVISIONS * (WINDOW_HEIGHT / WINDOW_WIDTH);
+        let grid_cell_height = WINDOW_HEIGHT / n_vertical_divisions;
+
+        Vec2::new(grid_cell_width, grid_cell_height)
+    }
+
+    // Spatial queries
+    //
+    // None of these allocate, they walk the cells in place. The grid only knows which
+    // grid location an Entity is in, so queries that care about exact positions take a
+    // `position_of` lookup, usually a Query<&Transform>:
+    //  |entity| query.get(entity).ok().map(|transform| transform.translation.truncate())
+    // Entities the lookup returns None for are skipped, which doubles as a component
+    // filter, e.g. with a Query<&Transform, With<Team>>.
+
+    /// The entities in a grid location
     /// * `loc`: grid location in question
-    pub fn get_gladiators_in_grid_location(&self, loc: &GridLocation) -> Vec<Entity> {
-        match self.grid_map.get(loc) {
-            Some(vec) => vec.clone(),
-            None => vec![],
-        }
+    pub fn entities_at(&self, loc: &GridLocation) -> impl Iterator<Item = Entity> + '_ {
+        Self::cell_index(loc)
+            .map_or(&[][..], |cell| self.cells[cell].as_slice())
+            .iter()
+            .copied()
+    }
+
+    /// The grid locations that have entities in them, with those entities
+    pub fn occupied_locations(&self) -> impl Iterator<Item = (GridLocation, &[Entity])> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, entities)| !entities.is_empty())
+            .map(|(cell, entities)| (Self::cell_location(cell), entities.as_slice()))
+    }
+
+    /// The grid location an entity is in, None when it isn't in the grid
+    /// * `entity`: the entity in question
+    pub fn location_of(&self, entity: Entity) -> Option<GridLocation> {
+        self.slots
+            .get(&entity)
+            .map(|slot| Self::cell_location(slot.cell))
+    }
+
+    /// How many grid locations apart two entities are, counting like locations_around does,
+    /// so an entity is within a range of r of everything at most r apart. None when either
+    /// of them is not in the grid.
+    /// * `a`, `b`: the entities in question
+    pub fn distance_between(&self, a: Entity, b: Entity) -> Option<i32> {
+        let (a, b) = (self.location_of(a)?, self.location_of(b)?);
+        Some((a.x - b.x).abs().max((a.y - b.y).abs()))
     }
 
-    /// Returns a Vec off all adjacent grid locations to the given location
+    /// All grid locations at most `range` locations away from the given location in each
+    /// direction, the location itself included
     /// * `loc`: grid location in question
-    fn get_adjacent_grid_locations(loc: &GridLocation) -> Vec<GridLocation> {
-        let mut grid_locations = Vec::new();
-        // list the xs and the ys, do combinatorics, remove item == loc
-        let possible_x = vec![loc.x - 1, loc.x, loc.x + 1];
-        let possible_y = vec![loc.y - 1, loc.y, loc.y + 1];
-
-        for x in possible_x {
-            for y in &possible_y {
-                // Don't add the starting grid location
-                // if (x, y) == (loc.x, loc.y) { continue } // or add and remove later
-                grid_locations.push(GridLocation { x, y: y.clone() }) // thought Copy would avoid the clone()
+    /// * `range`: how many grid locations away to look
+    pub fn locations_around(loc: GridLocation, range: i32) -> impl Iterator<Item = GridLocation> {
+        (loc.x - range..=loc.x + range)
+            .flat_map(move |x| (loc.y - range..=loc.y + range).map(move |y| GridLocation { x, y }))
+    }
+
+    /// The entities in the grid locations at most `range` locations away from the given
+    /// location, see locations_around
+    pub fn entities_around(
+        &self,
+        loc: GridLocation,
+        range: i32,
+    ) -> impl Iterator<Item = Entity> + '_ {
+        Self::locations_around(loc, range).flat_map(move |location| self.entities_at(&location))
+    }
+
+    /// The grid locations exactly `ring` locations away from the given location in the
+    /// furthest direction, i.e. the border of locations_around
+    pub fn locations_in_ring(loc: GridLocation, ring: i32) -> impl Iterator<Item = GridLocation> {
+        let (min_x, max_x) = (loc.x - ring, loc.x + ring);
+        let (min_y, max_y) = (loc.y - ring, loc.y + ring);
+        // ring 0 is just the location itself, don't visit it twice
+        let n_rows = if ring == 0 { 1 } else { 2 };
+
+        let rows = (min_x..=max_x).flat_map(move |x| {
+            [min_y, max_y]
+                .into_iter()
+                .take(n_rows)
+                .map(move |y| GridLocation { x, y })
+        });
+        let columns = (min_y + 1..max_y).flat_map(move |y| {
+            [min_x, max_x]
+                .into_iter()
+                .map(move |x| GridLocation { x, y })
+        });
+        rows.chain(columns)
+    }
+
+    /// The grid locations that overlap a circle
+    /// * `center`: pixel coordinates of the center of the circle
+    /// * `radius`: radius of the circle in pixels
+    pub fn locations_in_radius(center: Vec2, radius: f32) -> impl Iterator<Item = GridLocation> {
+        let min = Self::get_grid_location(center.x - radius, center.y - radius);
+        let max = Self::get_grid_location(center.x + radius, center.y + radius);
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| GridLocation { x, y }))
+    }
+
+    /// The entities within a radius, with their positions
+    /// * `center`: pixel coordinates of the center of the circle
+    /// * `radius`: radius of the circle in pixels
+    /// * `position_of`: where an entity is, None to skip it
+    pub fn entities_in_radius<'a>(
+        &'a self,
+        center: Vec2,
+        radius: f32,
+        position_of: impl Fn(Entity) -> Option<Vec2> + 'a,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + 'a {
+        let radius_squared = radius * radius;
+        Self::locations_in_radius(center, radius)
+            .flat_map(move |location| self.entities_at(&location))
+            .filter_map(move |entity| position_of(entity).map(|position| (entity, position)))
+            .filter(move |(_, position)| position.distance_squared(center) <= radius_squared)
+    }
+
+    /// The K entities nearest to a point, searching outwards one ring of grid locations at
+    /// a time until nothing closer can be found
+    /// * `center`: pixel coordinates to search from
+    /// * `max_radius`: how far to search in pixels, never further than the arena
+    /// * `position_of`: where an entity is, None to skip it
+    pub fn nearest<const K: usize>(
+        &self,
+        center: Vec2,
+        max_radius: f32,
+        position_of: impl Fn(Entity) -> Option<Vec2>,
+    ) -> Nearest<K> {
+        let mut nearest = Nearest::default();
+        let center_location = Self::get_grid_location(center.x, center.y);
+        let cell_size = Self::cell_size();
+        let min_cell_size = cell_size.x.min(cell_size.y);
+
+        // twice the width of the arena in grid locations reaches everybody from anywhere
+        let arena_rings = 2 * GRID_HORIZONTAL_DIVISIONS as i32;
+        let max_ring = ((max_radius / min_cell_size).ceil() as i32)
+            .saturating_add(1)
+            .min(arena_rings);
+
+        for ring in 0..=max_ring {
+            // everything in this ring is at least this far away from the center
+            let ring_distance = (ring - 1).max(0) as f32 * min_cell_size;
+            let nothing_closer = nearest.furthest_distance().map_or(false, |furthest| {
+                nearest.is_full() && ring_distance > furthest
+            });
+            if ring_distance > max_radius || nothing_closer {
+                break;
+            }
+
+            for location in Self::locations_in_ring(center_location, ring) {
+                for entity in self.entities_at(&location) {
+                    let position = match position_of(entity) {
+                        Some(position) => position,
+                        None => continue,
+                    };
+                    let distance = position.distance(center);
+                    if distance <= max_radius {
+                        nearest.offer(entity, position, distance);
+                    }
+                }
             }
         }
 
-        // Removing the specific element from the Vec is probably slower, but
-        // I'll have to do this pattern for removing an Entity from the map in
-        // this struct, so I'm leaving this as an example for later. I will
-        // uncomment the above if when I'm done having the example here.
-        grid_locations.retain(|x| *x != *loc);
+        nearest
+    }
 
-        grid_locations
+    /// The grid locations a line crosses, in order from start to end
+    /// * `start`: pixel coordinates the line starts at
+    /// * `end`: pixel coordinates the line ends at, for a ray use start + direction * range
+    pub fn locations_on_line(start: Vec2, end: Vec2) -> LineLocations {
+        LineLocations::new(start, end)
     }
 
-    /// Returns a HashMap indicating the Entitys in each of the adjacent grid locations
+    /// The entities close to a line in the grid locations it crosses, roughly in order from
+    /// start to end, e.g. for line of sight or a projectile's path
+    /// * `start`: pixel coordinates the line starts at
+    /// * `end`: pixel coordinates the line ends at
+    /// * `half_width`: how close to the line an entity has to be, in pixels
+    /// * `position_of`: where an entity is, None to skip it
+    pub fn entities_on_line<'a>(
+        &'a self,
+        start: Vec2,
+        end: Vec2,
+        half_width: f32,
+        position_of: impl Fn(Entity) -> Option<Vec2> + 'a,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + 'a {
+        Self::locations_on_line(start, end)
+            .flat_map(move |location| self.entities_at(&location))
+            .filter_map(move |entity| position_of(entity).map(|position| (entity, position)))
+            .filter(move |(_, position)| distance_to_segment(*position, start, end) <= half_width)
+    }
+
+    // Maintenance, see GridPlugin
+
+    /// Puts an entity in a grid location, moving it there if it is elsewhere in the grid
+    /// * `entity`: the entity in question
+    /// * `location`: grid location it is in now
+    pub fn place(&mut self, entity: Entity, location: GridLocation) {
+        let cell = Self::clamped_cell_index(&location);
+        if let Some(slot) = self.slots.get(&entity).copied() {
+            if slot.cell == cell {
+                return;
+            }
+            self.remove_from_cell(slot);
+        }
+
+        self.slots.insert(
+            entity,
+            GridSlot {
+                cell,
+                index: self.cells[cell].len(),
+            },
+        );
+        self.cells[cell].push(entity);
+        self.mark_dirty(cell);
+    }
+
+    /// Takes an entity out of the grid, nothing happens if it isn't in there
+    /// * `entity`: the entity in question
+    pub fn remove(&mut self, entity: Entity) {
+        if let Some(slot) = self.slots.remove(&entity) {
+            self.remove_from_cell(slot);
+        }
+    }
+
+    /// Terrain of a grid location, open floor outside of the grid
     /// * `loc`: grid location in question
-    pub fn get_gladiators_in_adjacent_grid_locations(
-        &self,
-        loc: GridLocation,
-    ) -> HashMap<GridLocation, Vec<Entity>> {
-        let mut map = HashMap::new();
+    pub fn terrain_at(&self, loc: &GridLocation) -> Terrain {
+        Self::cell_index(loc).map_or(Terrain::Open, |cell| self.terrain[cell])
+    }
+
+    /// Whether gladiators can't walk into a grid location
+    /// * `loc`: grid location in question
+    pub fn is_blocked(&self, loc: &GridLocation) -> bool {
+        self.terrain_at(loc).is_blocked()
+    }
+
+    /// Changes the terrain of a grid location, nothing happens outside of the grid
+    /// * `loc`: grid location in question
+    /// * `terrain`: what the floor is like there now
+    pub fn set_terrain(&mut self, loc: GridLocation, terrain: Terrain) {
+        if let Some(cell) = Self::cell_index(&loc) {
+            self.terrain[cell] = terrain;
+        }
+    }
 
-        // populate the map with the Vec of Entitys currently at each location
-        let grid_locations = Self::get_adjacent_grid_locations(&loc);
-        for location in grid_locations {
-            let entities = match self.grid_map.get(&loc) {
-                Some(vec) => vec.clone(),
-                None => vec![],
-            };
+    /// Turns every grid location back into open floor
+    pub fn clear_terrain(&mut self) {
+        self.terrain.fill(Terrain::Open);
+    }
 
-            map.insert(location, entities);
+    /// Empties the grid, the terrain stays
+    pub fn clear(&mut self) {
+        for cell in self.cells.iter_mut() {
+            cell.clear();
         }
+        self.slots.clear();
+        self.clear_dirty();
+    }
 
-        map
+    /// Flags the grid location of an entity as changed, e.g. when it got free of an
+    /// engagement and may want to engage somebody else there
+    /// * `entity`: the entity in question
+    pub fn mark_dirty_at(&mut self, entity: Entity) {
+        if let Some(slot) = self.slots.get(&entity).copied() {
+            self.mark_dirty(slot.cell);
+        }
     }
 
-    fn update_entity_location(
-        &mut self,
-        entity: Entity,
-        prev_loc: &GridLocation,
-        curr_loc: &GridLocation,
-    ) {
-        match self.grid_map.get_mut(prev_loc) {
-            Some(entities_vec) => entities_vec.retain(|x| *x != entity),
-            None => warn!("{:?} does not exist, only updating new location", prev_loc),
+    /// Flags every occupied grid location as changed
+    pub fn mark_all_dirty(&mut self) {
+        for cell in 0..self.cells.len() {
+            if !self.cells[cell].is_empty() {
+                self.mark_dirty(cell);
+            }
         }
+    }
+
+    /// The grid locations that changed since the last clear_dirty
+    pub fn dirty_locations(&self) -> impl Iterator<Item = GridLocation> + '_ {
+        self.dirty.iter().map(|cell| Self::cell_location(*cell))
+    }
+
+    /// Whether any grid location at most `range` away from the given one changed since the
+    /// last clear_dirty
+    /// * `loc`: grid location in question
+    /// * `range`: how many grid locations away to look
+    pub fn is_dirty_around(&self, loc: GridLocation, range: i32) -> bool {
+        Self::locations_around(loc, range)
+            .filter_map(|location| Self::cell_index(&location))
+            .any(|cell| self.is_dirty[cell])
+    }
 
-        match self.grid_map.get_mut(curr_loc) {
-            Some(entities_vec) => {
-                entities_vec.push(entity);
+    pub fn clear_dirty(&mut self) {
+        for cell in self.dirty.drain(..) {
+            self.is_dirty[cell] = false;
+        }
+    }
+
+    /// Lists everything that doesn't match between the grid and where the entities are
+    /// * `expected`: every entity that should be in the grid with its grid location
+    pub fn verify(&self, expected: impl Iterator<Item = (Entity, GridLocation)>) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut n_expected = 0;
+        for (entity, location) in expected {
+            n_expected += 1;
+            let cell = Self::clamped_cell_index(&location);
+            match self.slots.get(&entity) {
+                Some(slot) if slot.cell != cell => problems.push(format!(
+                    "{:?} is in {:?} but the grid has it in {:?}",
+                    entity,
+                    location,
+                    Self::cell_location(slot.cell)
+                )),
+                Some(slot) if self.cells[slot.cell].get(slot.index) != Some(&entity) => problems
+                    .push(format!(
+                        "{:?} is not where the grid thinks it is in {:?}",
+                        entity, location
+                    )),
+                Some(_) => {}
+                None => problems.push(format!("{:?} is missing from the grid", entity)),
             }
-            None => {
-                self.grid_map.insert(curr_loc.clone(), vec![entity]);
+        }
+
+        let n_listed: usize = self.cells.iter().map(Vec::len).sum();
+        if self.slots.len() != n_expected || n_listed != n_expected {
+            problems.push(format!(
+                "the grid has {} entities indexed and {} listed, but there are {}",
+                self.slots.len(),
+                n_listed,
+                n_expected
+            ));
+        }
+        problems
+    }
+
+    /// Throws away the grid and builds it again
+    /// * `entities`: every entity that should be in the grid with its grid location
+    pub fn rebuild(&mut self, entities: impl Iterator<Item = (Entity, GridLocation)>) {
+        self.clear();
+        for (entity, location) in entities {
+            self.place(entity, location);
+        }
+    }
+
+    fn remove_from_cell(&mut self, slot: GridSlot) {
+        let entities = &mut self.cells[slot.cell];
+        entities.swap_remove(slot.index);
+        // the last entity took the place of the removed one
+        if let Some(moved) = entities.get(slot.index) {
+            if let Some(moved_slot) = self.slots.get_mut(moved) {
+                moved_slot.index = slot.index;
             }
         }
+        self.mark_dirty(slot.cell);
+    }
+
+    fn mark_dirty(&mut self, cell: usize) {
+        if !self.is_dirty[cell] {
+            self.is_dirty[cell] = true;
+            self.dirty.push(cell);
+        }
+    }
+
+    // Cell layout
+
+    /// The lowest and highest grid locations that are stored, twice the window in each
+    /// direction
+    fn bounds() -> (GridLocation, GridLocation) {
+        (
+            Self::get_grid_location(-WINDOW_WIDTH, -WINDOW_HEIGHT),
+            Self::get_grid_location(WINDOW_WIDTH, WINDOW_HEIGHT),
+        )
+    }
+
+    /// Index of a grid location in the cells, None when it is out of bounds
+    fn cell_index(loc: &GridLocation) -> Option<usize> {
+        let (min, max) = Self::bounds();
+        if loc.x < min.x || loc.x > max.x || loc.y < min.y || loc.y > max.y {
+            return None;
+        }
+        let width = max.x - min.x + 1;
+        Some(((loc.y - min.y) * width + (loc.x - min.x)) as usize)
+    }
+
+    /// Index of a grid location in the cells, locations out of bounds end up on the edge
+    fn clamped_cell_index(loc: &GridLocation) -> usize {
+        let (min, max) = Self::bounds();
+        let clamped = GridLocation {
+            x: loc.x.clamp(min.x, max.x),
+            y: loc.y.clamp(min.y, max.y),
+        };
+        Self::cell_index(&clamped).expect("A clamped grid location should be in bounds.")
+    }
+
+    fn cell_location(cell: usize) -> GridLocation {
+        let (min, max) = Self::bounds();
+        let width = (max.x - min.x + 1) as usize;
+        GridLocation {
+            x: min.x + (cell % width) as i32,
+            y: min.y + (cell / width) as i32,
+        }
+    }
+}
+
+/// The nearest entities found by ArenaGrid::nearest, kept in a fixed size array
+pub struct Nearest<const K: usize> {
+    /// Entity, position and distance, sorted nearest first
+    found: [Option<(Entity, Vec2, f32)>; K],
+}
+
+impl<const K: usize> Default for Nearest<K> {
+    fn default() -> Self {
+        Self { found: [None; K] }
+    }
+}
+
+impl<const K: usize> Nearest<K> {
+    /// The entities and their positions, nearest first
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        self.found
+            .iter()
+            .flatten()
+            .map(|(entity, position, _)| (*entity, *position))
+    }
+
+    /// The nearest entity and its position
+    pub fn first(&self) -> Option<(Entity, Vec2)> {
+        self.iter().next()
+    }
+
+    pub fn len(&self) -> usize {
+        self.found.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.found.first().map_or(true, Option::is_none)
+    }
+
+    fn is_full(&self) -> bool {
+        self.found.last().map_or(true, Option::is_some)
+    }
+
+    fn furthest_distance(&self) -> Option<f32> {
+        self.found
+            .iter()
+            .flatten()
+            .last()
+            .map(|(_, _, distance)| *distance)
+    }
+
+    /// Inserts an entity in order, the furthest one drops off when there are already K
+    fn offer(&mut self, entity: Entity, position: Vec2, distance: f32) {
+        let mut candidate = (entity, position, distance);
+        for slot in self.found.iter_mut() {
+            match slot {
+                None => {
+                    *slot = Some(candidate);
+                    return;
+                }
+                Some(existing) if candidate.2 < existing.2 => {
+                    std::mem::swap(existing, &mut candidate);
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+/// Walks the grid locations a line crosses (Amanatides & Woo), see
+/// ArenaGrid::locations_on_line
+pub struct LineLocations {
+    current: GridLocation,
+    step_x: i32,
+    step_y: i32,
+    /// How far along the line (0.0 to 1.0) the next vertical and horizontal borders are
+    next_border: Vec2,
+    /// How far along the line it is from one border to the next
+    border_spacing: Vec2,
+    remaining: usize,
+}
+
+impl LineLocations {
+    fn new(start: Vec2, end: Vec2) -> Self {
+        // in grid units grid location n covers [n, n + 1), like floor()
+        let cell_size = ArenaGrid::cell_size();
+        let from = start / cell_size + 0.5;
+        let to = end / cell_size + 0.5;
+        let delta = to - from;
+
+        let first = GridLocation {
+            x: from.x.floor() as i32,
+            y: from.y.floor() as i32,
+        };
+        let last = GridLocation {
+            x: to.x.floor() as i32,
+            y: to.y.floor() as i32,
+        };
+
+        let step = |delta: f32| {
+            if delta > 0.0 {
+                1
+            } else if delta < 0.0 {
+                -1
+            } else {
+                0
+            }
+        };
+        let next_border = |from: f32, delta: f32| {
+            if delta > 0.0 {
+                (from.floor() + 1.0 - from) / delta
+            } else if delta < 0.0 {
+                (from - from.floor()) / -delta
+            } else {
+                f32::INFINITY
+            }
+        };
+        let border_spacing = |delta: f32| {
+            if delta != 0.0 {
+                1.0 / delta.abs()
+            } else {
+                f32::INFINITY
+            }
+        };
+
+        Self {
+            current: first,
+            step_x: step(delta.x),
+            step_y: step(delta.y),
+            next_border: Vec2::new(next_border(from.x, delta.x), next_border(from.y, delta.y)),
+            border_spacing: Vec2::new(border_spacing(delta.x), border_spacing(delta.y)),
+            remaining: ((last.x - first.x).abs() + (last.y - first.y).abs() + 1) as usize,
+        }
+    }
+}
+
+impl Iterator for LineLocations {
+    type Item = GridLocation;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let location = self.current;
+        if self.next_border.x < self.next_border.y {
+            self.current.x += self.step_x;
+            self.next_border.x += self.border_spacing.x;
+        } else {
+            self.current.y += self.step_y;
+            self.next_border.y += self.border_spacing.y;
+        }
+        Some(location)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Distance in pixels from a point to the closest point of a line segment
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    let along = if length_squared > 0.0 {
+        ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(start + segment * along)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(x: i32, y: i32) -> GridLocation {
+        GridLocation { x, y }
+    }
+
+    fn line(start: GridLocation, end: GridLocation) -> Vec<GridLocation> {
+        ArenaGrid::locations_on_line(ArenaGrid::center_of(start), ArenaGrid::center_of(end))
+            .collect()
+    }
+
+    /// A grid with an entity at each position, and a lookup for where they are
+    fn grid_with(positions: &[Vec2]) -> (ArenaGrid, HashMap<Entity, Vec2>) {
+        let mut arena_grid = ArenaGrid::default();
+        let mut lookup = HashMap::default();
+        for (idx, position) in positions.iter().enumerate() {
+            let entity = Entity::from_raw(idx as u32);
+            arena_grid.place(entity, ArenaGrid::get_grid_location(position.x, position.y));
+            lookup.insert(entity, *position);
+        }
+        (arena_grid, lookup)
+    }
+
+    /// Consecutive locations share an edge, so a line never skips past a location
+    fn assert_connected(locations: &[GridLocation]) {
+        for pair in locations.windows(2) {
+            let steps = (pair[0].x - pair[1].x).abs() + (pair[0].y - pair[1].y).abs();
+            assert_eq!(steps, 1, "{:?} skips a location", pair);
+        }
+    }
+
+    #[test]
+    fn axis_aligned_lines_walk_a_row_or_column() {
+        assert_eq!(
+            line(location(0, 0), location(3, 0)),
+            vec![
+                location(0, 0),
+                location(1, 0),
+                location(2, 0),
+                location(3, 0)
+            ]
+        );
+        assert_eq!(
+            line(location(2, 1), location(2, -1)),
+            vec![location(2, 1), location(2, 0), location(2, -1)]
+        );
+        assert_eq!(line(location(5, 5), location(5, 5)), vec![location(5, 5)]);
+    }
+
+    #[test]
+    fn diagonal_lines_visit_every_location_they_cross() {
+        let locations = line(location(0, 0), location(4, -2));
+
+        assert_eq!(locations.len(), 7);
+        assert_eq!(locations.first(), Some(&location(0, 0)));
+        assert_eq!(locations.last(), Some(&location(4, -2)));
+        assert_connected(&locations);
+    }
+
+    #[test]
+    fn lines_through_a_corner_step_around_it() {
+        // exactly diagonal, so the line passes through the corners between locations
+        let locations = line(location(0, 0), location(2, 2));
+
+        assert_eq!(
+            locations,
+            vec![
+                location(0, 0),
+                location(0, 1),
+                location(1, 1),
+                location(1, 2),
+                location(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_leaving_the_arena_find_nothing_outside_it() {
+        let (arena_grid, lookup) = grid_with(&[ArenaGrid::center_of(location(40, 0))]);
+        let start = ArenaGrid::center_of(location(30, 0));
+        let end = ArenaGrid::center_of(location(60, 0));
+
+        let locations: Vec<_> = ArenaGrid::locations_on_line(start, end).collect();
+        assert_eq!(locations.len(), 31);
+        assert_eq!(locations.last(), Some(&location(60, 0)));
+        assert_connected(&locations);
+
+        let found: Vec<_> = arena_grid
+            .entities_on_line(start, end, 1.0, |entity| lookup.get(&entity).copied())
+            .map(|(entity, _)| entity)
+            .collect();
+        assert_eq!(found, vec![Entity::from_raw(0)]);
+
+        let outside = ArenaGrid::center_of(location(100, 100));
+        let far_outside = ArenaGrid::center_of(location(120, 100));
+        assert_eq!(
+            arena_grid
+                .entities_on_line(outside, far_outside, 1000.0, |entity| lookup
+                    .get(&entity)
+                    .copied())
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn entities_on_line_only_finds_entities_close_to_it() {
+        let (arena_grid, lookup) = grid_with(&[
+            Vec2::new(100.0, 2.0),   // on the line
+            Vec2::new(200.0, -20.0), // too far to the side
+            Vec2::new(-100.0, 0.0),  // behind the start
+            Vec2::new(150.0, -4.0),  // on the line
+        ]);
+
+        let found: Vec<_> = arena_grid
+            .entities_on_line(Vec2::ZERO, Vec2::new(300.0, 0.0), 5.0, |entity| {
+                lookup.get(&entity).copied()
+            })
+            .map(|(entity, _)| entity)
+            .collect();
+
+        assert_eq!(found, vec![Entity::from_raw(0), Entity::from_raw(3)]);
+    }
+
+    #[test]
+    fn distance_to_segment_uses_the_closest_point() {
+        let (start, end) = (Vec2::ZERO, Vec2::new(10.0, 0.0));
+
+        assert_eq!(distance_to_segment(Vec2::new(5.0, 3.0), start, end), 3.0);
+        assert_eq!(distance_to_segment(Vec2::new(-4.0, 3.0), start, end), 5.0);
+        assert_eq!(distance_to_segment(Vec2::new(13.0, -4.0), start, end), 5.0);
+        // a segment of zero length is a point
+        assert_eq!(distance_to_segment(Vec2::new(3.0, 4.0), start, start), 5.0);
+    }
+
+    #[test]
+    fn nearest_finds_the_closest_entities_in_order() {
+        let (arena_grid, lookup) = grid_with(&[
+            Vec2::new(300.0, 0.0),
+            Vec2::new(-40.0, 30.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(0.0, -200.0),
+        ]);
+
+        let nearest = arena_grid.nearest::<2>(Vec2::ZERO, WINDOW_WIDTH, |entity| {
+            lookup.get(&entity).copied()
+        });
+
+        let found: Vec<_> = nearest.iter().map(|(entity, _)| entity).collect();
+        assert_eq!(found, vec![Entity::from_raw(2), Entity::from_raw(1)]);
+    }
+
+    #[test]
+    fn nearest_respects_the_radius_and_skipped_entities() {
+        let (arena_grid, lookup) = grid_with(&[Vec2::new(10.0, 0.0), Vec2::new(100.0, 0.0)]);
+
+        let skip_first = |entity: Entity| {
+            (entity != Entity::from_raw(0))
+                .then(|| lookup.get(&entity).copied())
+                .flatten()
+        };
+        let nearest = arena_grid.nearest::<1>(Vec2::ZERO, WINDOW_WIDTH, skip_first);
+        assert_eq!(
+            nearest.first().map(|(entity, _)| entity),
+            Some(Entity::from_raw(1))
+        );
+
+        let nearest =
+            arena_grid.nearest::<3>(Vec2::ZERO, 50.0, |entity| lookup.get(&entity).copied());
+        assert_eq!(nearest.len(), 1);
+
+        let nearest =
+            arena_grid.nearest::<3>(Vec2::ZERO, 5.0, |entity| lookup.get(&entity).copied());
+        assert!(nearest.is_empty());
     }
 }
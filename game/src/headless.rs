@@ -0,0 +1,115 @@
+//! Running the game without a window, for `--headless` runs, the stress example, the
+//! benchmarks and the tests. HeadlessPlugins stands in for DefaultPlugins, and a
+//! SteppedApp drives an App one frame at a time with a clock that only moves when it
+//! steps:
+//!
+//!  let mut stepped = SteppedApp::default();
+//!  stepped.app.add_plugin(GladiatorPlugin);
+//!  stepped.steps(60);
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    app::{PluginGroupBuilder, ScheduleRunnerPlugin},
+    audio::AudioPlugin,
+    log::LogPlugin,
+    prelude::*,
+    render::{settings::WgpuSettings, RenderPlugin},
+    time::{FixedTimesteps, TimePlugin},
+    winit::WinitPlugin,
+};
+
+/// One frame of a game running at 60 FPS
+pub const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+/// DefaultPlugins without a window, a GPU or sound. Nothing is drawn, but the assets,
+/// sprites and atlases the gladiators are spawned with still load. The modes exit on their
+/// own when they are done, there is no window to close.
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        DefaultPlugins
+            .set(WindowPlugin {
+                add_primary_window: false,
+                exit_on_all_closed: false,
+                ..default()
+            })
+            .disable::<WinitPlugin>()
+            .disable::<AudioPlugin>()
+            .add_before::<RenderPlugin, _>(NoGpuPlugin)
+            .add(ScheduleRunnerPlugin)
+    }
+}
+
+/// The renderer reads its settings when it is built, so they go in right before it
+struct NoGpuPlugin;
+
+impl Plugin for NoGpuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WgpuSettings {
+            backends: None,
+            ..default()
+        });
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// A headless App with a clock that advances by exactly one FRAME per step, so the fixed
+/// timestep systems run as if the game was running at 60 FPS however long a step takes.
+/// Build it without the TimePlugin, the clock is kept here instead. The default one also
+/// goes without the LogPlugin, which can only be set up once per process and there can be
+/// several of these, e.g. one per test.
+pub struct SteppedApp {
+    pub app: App,
+    now: Instant,
+}
+
+impl SteppedApp {
+    /// * `plugins` - the plugins bevy itself brings, without the TimePlugin
+    pub fn new(plugins: impl PluginGroup) -> Self {
+        let mut app = App::new();
+        app.add_plugins(plugins)
+            .init_resource::<Time>()
+            .init_resource::<FixedTimesteps>();
+
+        Self {
+            app,
+            now: Instant::now(),
+        }
+    }
+
+    /// Runs the whole schedule once, one frame later than the last step
+    pub fn step(&mut self) {
+        self.now += FRAME;
+        self.app
+            .world
+            .resource_mut::<Time>()
+            .update_with_instant(self.now);
+        self.app.update();
+    }
+
+    pub fn steps(&mut self, n_steps: usize) {
+        for _ in 0..n_steps {
+            self.step();
+        }
+    }
+}
+
+impl Default for SteppedApp {
+    fn default() -> Self {
+        Self::new(
+            HeadlessPlugins
+                .build()
+                .disable::<TimePlugin>()
+                .disable::<LogPlugin>(),
+        )
+    }
+}
@@ -1,14 +1,31 @@
+use std::{env, fs, path::PathBuf};
+
 use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    damage::*,
+    gladiator::{gladiator_components::*, gladiator_status::*},
+    ASSETS_PATH,
+};
 
 /// Removes points from health based on attack damage and defense.
 /// * `health` - the health of the thing being attacked which is being reduced
 /// * `defense` - the defense of the thing
-/// * `attack_damage` - the attack damage the thing is suffering
-pub fn reduce_health_from_attack(health: &mut f32, defense: &f32, attack_damage: &f32) {
-    *health -= attack_damage - defense;
+/// * `attack` - the attack the thing is suffering
+/// * `resistances` - how well the thing resists each type of damage
+/// * `mitigation` - how defense reduces the damage, see the damage module
+pub fn reduce_health_from_attack(
+    health: &mut f32,
+    defense: &f32,
+    attack: &Attack,
+    resistances: &Resistances,
+    mitigation: &MitigationModel,
+) {
+    *health -= damage_taken(attack, *defense, resistances, mitigation);
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect, FromReflect, Serialize, Deserialize)]
 pub enum HealingItem {
     Potion,
     Berry,
@@ -18,7 +35,8 @@ pub enum HealingItem {
     GreenMushroom,
 }
 
-pub fn heal_from_item(health: &mut f32, healing_item_type: HealingItem) {
+/// Restores health with a healing item, up to the max health.
+pub fn heal_from_item(health: &mut Health, healing_item_type: HealingItem) {
     // determine how much healing comes from the given healing item type
     let healing_amount = match healing_item_type {
         HealingItem::Potion => 50.0,
@@ -26,11 +44,32 @@ pub fn heal_from_item(health: &mut f32, healing_item_type: HealingItem) {
         HealingItem::Leaves => 1.5,
         HealingItem::MedicineKit => 35.0,
         HealingItem::RedMushroom => 15.0,
-        HealingItem::GreenMushroom => -15.0,
+        HealingItem::GreenMushroom => 0.0, // poisonous, see HealingItem::status_effect
     };
 
     // we know what the 'healing_amount' is, so we can adjust the health value.
-    *health += healing_amount;
+    health.heal(healing_amount);
+}
+
+impl HealingItem {
+    /// Status effect on whoever uses the item, on top of the healing
+    pub fn status_effect(&self) -> Option<StatusEffect> {
+        match self {
+            HealingItem::Berry => Some(StatusEffect::new(
+                StatusEffectKind::Regeneration {
+                    health_per_second: 1.0,
+                },
+                5.0,
+            )),
+            HealingItem::GreenMushroom => Some(StatusEffect::new(
+                StatusEffectKind::Poison {
+                    damage_per_second: 3.0,
+                },
+                5.0,
+            )),
+            _ => None,
+        }
+    }
 }
 
 /// Determines attack damage
@@ -39,7 +78,57 @@ pub fn determine_attack_damage() -> f32 {
     todo!();
 }
 
-/// Adjust Level/XP
+/// Adjust Level/XP for a level of training: the gladiator goes up a level and starts over
+/// towards the next one. Their stats grow when they are spawned at the new level.
 pub fn gain_experience(level: &mut usize, xp: &mut f32) {
-    todo!();
+    *level += 1;
+    *xp = 0.0;
+}
+
+/// Inserts a bundle once commands apply, unless the entity has been despawned by then.
+/// EntityCommands::insert panics in that case, which happens when e.g. a gladiator is
+/// killed in the same frame.
+/// * `commands` - the system's Commands
+/// * `entity` - the entity to insert the bundle on
+/// * `bundle` - components to insert
+pub fn try_insert(commands: &mut Commands, entity: Entity, bundle: impl Bundle) {
+    commands.add(move |world: &mut World| {
+        if let Some(mut entity) = world.get_entity_mut(entity) {
+            entity.insert(bundle);
+        }
+    });
+}
+
+/// The value that follows a flag in the command line arguments, e.g. "ludus" for
+/// `--map ludus`. None without the flag or when nothing follows it.
+/// * `args` - command line arguments, usually std::env::args()
+/// * `flag` - the flag in question, e.g. "--map"
+pub fn arg_value(args: impl Iterator<Item = String>, flag: &str) -> Option<String> {
+    args.skip_while(|arg| arg != flag).nth(1)
+}
+
+/// Reads a RON data file from the assets folder, see read_data_file.
+/// * `path` - path of the file relative to the assets folder, e.g. "tournaments/classic.ron"
+pub fn load_data_file<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let contents = read_data_file(path)?;
+    ron::from_str(&contents).map_err(|err| format!("Could not parse {}: {}", path, err))
+}
+
+/// Reads a file from the assets folder. The assets folder is found the same way the
+/// AssetServer finds it: next to Cargo.toml when run with cargo, otherwise next to the
+/// executable.
+/// * `path` - path of the file relative to the assets folder, e.g. "scripts/berserker.rhai"
+pub fn read_data_file(path: &str) -> Result<String, String> {
+    let base_path = match env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) => PathBuf::from(manifest_dir),
+        Err(_) => env::current_exe()
+            .map_err(|err| err.to_string())?
+            .parent()
+            .map(PathBuf::from)
+            .unwrap_or_default(),
+    };
+    let full_path = base_path.join(ASSETS_PATH).join(path);
+
+    fs::read_to_string(&full_path)
+        .map_err(|err| format!("Could not read {}: {}", full_path.display(), err))
 }
@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use bevy_inspector_egui::quick::{ResourceInspectorPlugin, WorldInspectorPlugin};
+
+use crate::grid::*;
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+/// Live view of the ECS for development. Only built with the `inspector` feature:
+///  cargo run --features inspector
+/// The inspector can only display and edit types that are in the type registry. Each
+/// plugin registers its own gameplay types, so new components show up here as long as
+/// they derive Reflect and are registered by the plugin that owns them.
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(WorldInspectorPlugin)
+            .add_plugin(ResourceInspectorPlugin::<ArenaGrid>::default());
+    }
+}
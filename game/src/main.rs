@@ -1,10 +1,26 @@
 use bevy::{prelude::*, window::PresentMode};
 
 use crate::animation::AnimationPlugin;
+use crate::arena::ArenaPlugin;
+use crate::career::CareerPlugin;
+use crate::director::DirectorPlugin;
 use crate::engagements::EngagementManagerPlugin;
-use crate::gladiator::gladiator::GladiatorPlugin;
+use crate::gladiator::{
+    gladiator::{ArenaPopulation, GladiatorPlugin},
+    gladiator_rolls::CombatRng,
+};
 use crate::grid::GridPlugin;
+use crate::headless::HeadlessPlugins;
+use crate::helper_functions::arg_value;
+#[cfg(feature = "inspector")]
+use crate::inspector::InspectorPlugin;
 use crate::player::player::PlayerPlugin;
+use crate::scenario::{ActiveScenario, ScenarioPlugin};
+#[cfg(feature = "scripting")]
+use crate::scripting::ScriptingPlugin;
+use crate::survival::SurvivalPlugin;
+use crate::team::{TeamMode, TeamPlugin};
+use crate::tournament::TournamentPlugin;
 use game_lib::*;
 
 /// Road Map (in no particular order)
@@ -18,7 +34,8 @@ use game_lib::*;
 /// 8. Healing system
 /// 9. Items system
 /// 10. Start Game menu - Make easy to disable for development
-/// 11. Inspectable: https://rustrepo.com/repo/jakobhellermann-bevy-inspector-egui
+/// 11. Inspectable: https://rustrepo.com/repo/jakobhellermann-bevy-inspector-egui - DONE
+///   behind the `inspector` feature
 /// 11. Grid update event system - DONE
 ///   https://bevy-cheatbook.github.io/programming/events.html
 ///   Events seem like a many to many pub-sub system and the
@@ -26,9 +43,30 @@ use game_lib::*;
 ///
 
 /// This is the main function that runs the game.
+/// Pass `--scenario <name>` to play one of the arenas in assets/scenarios/
+/// instead of a randomly populated one, and `--teams <mode>` (ffa, class, 5v5, ...)
+/// to split the gladiators into teams. `--survival` plays waves of gladiators instead,
+/// and `--tournament <roster>` runs a bracket of duels with a roster from
+/// assets/tournaments/. `--career` fights a battle with the gladiators of the career
+/// saved in saves/career.ron, with a visit to the shop after every battle.
+/// `--headless` runs the simulation without a window, e.g. to play out a tournament, and
+/// `--seed <n>` rolls hits, crits and damage the same way as an earlier run (the rest of a
+/// battle, e.g. spawns and movement, is not seeded). `--map <name>` picks the
+/// layout of the arena from assets/maps/, a .ron map by name or a Tiled map by file name
+/// (e.g. ludus.tmj). `--gladiators <n>` sets how many gladiators fight in a free-for-all.
+/// `--director <schedule>` picks when spike traps, lions, the shrinking safe zone and the
+/// crowd get involved, from assets/director/ (not in tournaments).
+/// With the `scripting` feature, `--brain <script>` hands the AI gladiators a brain from
+/// assets/scripts/.
 fn main() {
-    App::new()
-        .add_plugins(
+    let args: Vec<String> = std::env::args().collect();
+    let headless = args.iter().any(|arg| arg == "--headless");
+
+    let mut app = App::new();
+    if headless {
+        app.add_plugins(HeadlessPlugins);
+    } else {
+        app.add_plugins(
             DefaultPlugins
                 .set(ImagePlugin::default_nearest())
                 .set(WindowPlugin {
@@ -42,31 +80,57 @@ fn main() {
                     },
                     ..default()
                 }),
-        )
+        );
+    }
+
+    app.insert_resource(CombatRng::from_args(args.iter().cloned()))
         .add_plugin(AnimationPlugin)
         .add_plugin(EngagementManagerPlugin)
         .add_plugin(GridPlugin)
+        .add_plugin(ArenaPlugin::from_args(args.iter().cloned()))
         .add_plugin(PlayerPlugin)
         .add_plugin(GladiatorPlugin)
-        .add_startup_system(setup)
-        .run();
+        .add_startup_system(setup);
+
+    let tournament_roster = arg_value(args.iter().cloned(), "--tournament");
+
+    // survival, tournament and career modes bring their own gladiators and end conditions
+    if args.iter().any(|arg| arg == "--survival") {
+        app.add_plugin(SurvivalPlugin);
+    } else if args.iter().any(|arg| arg == "--career") {
+        app.add_plugin(CareerPlugin {
+            exit_when_done: headless,
+            save_path: CAREER_SAVE_PATH.to_string(),
+        })
+        .add_plugin(TeamPlugin);
+    } else if let Some(roster) = &tournament_roster {
+        app.add_plugin(TournamentPlugin {
+            roster: roster.clone(),
+            exit_when_done: headless,
+        });
+    } else {
+        app.insert_resource(ArenaPopulation::from_args(args.iter().cloned()))
+            .insert_resource(ActiveScenario::from_args(args.iter().cloned()))
+            .add_plugin(ScenarioPlugin)
+            .insert_resource(TeamMode::from_args(args.iter().cloned()))
+            .add_plugin(TeamPlugin);
+    }
+
+    // duels are decided by the duelists alone
+    if tournament_roster.is_none() {
+        app.add_plugin(DirectorPlugin::from_args(args.iter().cloned()));
+    }
+
+    #[cfg(feature = "inspector")]
+    app.add_plugin(InspectorPlugin);
+
+    #[cfg(feature = "scripting")]
+    app.add_plugin(ScriptingPlugin::from_args(args.iter().cloned()));
+
+    app.run();
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // spawn camera
+fn setup(mut commands: Commands) {
+    // spawn camera, the background comes with the map (see ArenaPlugin)
     commands.spawn(Camera2dBundle::default());
-
-    // spawn background
-    commands.spawn(SpriteBundle {
-        texture: asset_server.load("arena.png"),
-        transform: Transform::from_scale(
-            //this. is a hack.
-            Vec3::new(
-                WINDOW_WIDTH / BACKGROUND_WIDTH,
-                WINDOW_HEIGHT / BACKGROUND_HEIGHT,
-                -900.0,
-            ),
-        ),
-        ..Default::default()
-    });
 }
@@ -1,4 +1,5 @@
 pub mod player;
 pub mod player_bundles;
 pub mod player_components;
+pub mod player_items;
 pub mod player_movement;
@@ -1,8 +1,12 @@
 use bevy::{prelude::*, time::FixedTimestep};
 
 use crate::{
-    gladiator::gladiator_components::*,
-    player::{player_bundles::*, player_movement::*},
+    gladiator::{
+        gladiator::{ArenaPopulation, Class},
+        gladiator_components::*,
+    },
+    player::{player_bundles::*, player_components::*, player_items::*, player_movement::*},
+    team::TeamMode,
     *, // game_lib
 };
 
@@ -26,11 +30,15 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_player).add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(MOVEMENT_STEP as f64))
-                .with_system(player_movement),
-        );
+        app.register_type::<Player>()
+            .register_type::<Inventory>()
+            .add_startup_system(spawn_player)
+            .add_system(use_healing_item)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(MOVEMENT_STEP as f64))
+                    .with_system(player_movement),
+            );
     }
 }
 
@@ -45,7 +53,13 @@ fn spawn_player(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    arena_population: Option<Res<ArenaPopulation>>,
+    team_mode: Option<Res<TeamMode>>,
 ) {
+    if !arena_population.map_or(true, |population| population.player) {
+        return;
+    }
+
     let texture_handle = asset_server.load("Puny-Characters/Mage-Red.png");
     // The values used in the next function are specific to the Puny Characters sprite sheets
     let texture_atlas = TextureAtlas::from_grid(
@@ -70,15 +84,24 @@ fn spawn_player(
     // Call spawn() again for a new entity.
     //
     // spawn player
-    commands
-        .spawn((SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle,
-            transform,
-            ..default()
-        },))
+    let team = team_mode.and_then(|team_mode| team_mode.player_team(Class::Mage));
+    let mut sprite = TextureAtlasSprite::default();
+    if let Some(team) = team {
+        sprite.color = team.color();
+    }
+
+    let mut player = commands.spawn((SpriteSheetBundle {
+        sprite,
+        texture_atlas: texture_atlas_handle,
+        transform,
+        ..default()
+    },));
+    // override default health value for player so player is more robust for now.
+    player
         .insert(PlayerBundle::new())
-        .insert(Health {
-            // override default health value for player so player is more robust for now.
-            value: 999.0,
-        });
+        .insert(Health::new(PLAYER_HEALTH));
+
+    if let Some(team) = team {
+        player.insert(team);
+    }
 }
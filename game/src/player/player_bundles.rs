@@ -11,9 +11,14 @@ pub struct PlayerBundle {
 
 impl PlayerBundle {
     pub fn new() -> Self {
+        Self::from_gladiator(GladiatorBundle::new(Class::Mage))
+    }
+
+    /// Makes the player out of an already configured gladiator
+    pub fn from_gladiator(gladiator: GladiatorBundle) -> Self {
         Self {
             player: Player,
-            gladiator: GladiatorBundle::new(Class::Mage),
+            gladiator,
         }
     }
 }
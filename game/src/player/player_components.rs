@@ -1,4 +1,15 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Component)]
+use crate::helper_functions::HealingItem;
+
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Player;
+
+/// Items the player carries into battle, bought in the shop between career battles
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Inventory {
+    pub healing_items: Vec<HealingItem>,
+}
@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+use crate::{
+    gladiator::{gladiator_components::*, gladiator_status::*},
+    helper_functions::*,
+    player::player_components::*,
+};
+
+/// Uses the next healing item in the player's inventory when H is pressed
+pub fn use_healing_item(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<(&mut Health, &mut Inventory, &mut StatusEffects), With<Player>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::H) {
+        return;
+    }
+
+    for (mut health, mut inventory, mut status_effects) in &mut query {
+        match inventory.healing_items.pop() {
+            Some(healing_item) => {
+                println!("Using {:?}!", healing_item);
+                heal_from_item(&mut health, healing_item);
+                if let Some(effect) = healing_item.status_effect() {
+                    status_effects.apply(effect);
+                }
+            }
+            None => println!("No healing items left!"),
+        }
+    }
+}
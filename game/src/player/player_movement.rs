@@ -2,6 +2,7 @@ use bevy::prelude::*;
 
 use crate::{
     animation::*,
+    arena::*,
     engagements::Engagement,
     gladiator::{gladiator_components::*, gladiator_movement::*},
     grid::*,
@@ -12,13 +13,13 @@ use crate::{
 /// Moves the gladiator controlled by the player
 pub fn player_movement(
     keyboard_input: Res<Input<KeyCode>>,
-    mut ev_grid_change: EventWriter<GridChangeEvent>,
+    arena_grid: Res<ArenaGrid>,
     mut query: Query<
-        (&mut Transform, &Movement, &mut Animation, Entity),
+        (&mut Transform, &Movement, &mut Animation),
         (With<Player>, Without<Engagement>),
     >,
 ) {
-    for (mut transform, movement, mut animation, entity) in &mut query {
+    for (mut transform, movement, mut animation) in &mut query {
         // get movement input
         let mut x_movement: i16 = 0;
         let mut y_movement: i16 = 0;
@@ -79,32 +80,10 @@ pub fn player_movement(
                 }
             };
 
-        // determine previous grid location
-        let prev_grid_location =
-            ArenaGrid::get_grid_location(transform.translation[0], transform.translation[1]);
-
-        // translate
-        let translation_delta =
-            Vec3::new(x_movement.into(), y_movement.into(), 0.0) * movement.speed as f32;
-        transform.translation += translation_delta;
-
-        // determine current grid location
-        let current_grid_location =
-            ArenaGrid::get_grid_location(transform.translation[0], transform.translation[1]);
-
-        // emit event if entering a new grid location
-        if current_grid_location != prev_grid_location {
-            // can replace this with Inspector plugin
-            // println!(
-            //     "player at: x {} | y: {}",
-            //     current_grid_location.x, current_grid_location.y
-            // );
-            ev_grid_change.send(GridChangeEvent {
-                entity,
-                prev_loc: prev_grid_location,
-                curr_loc: current_grid_location,
-            });
-        }
+        // translate around the terrain, the ArenaGrid picks it up from the changed Transform
+        let delta = Vec2::new(x_movement.into(), y_movement.into()) * movement.current_speed();
+        let step = terrain_step(&arena_grid, transform.translation.truncate(), delta);
+        transform.translation += step.extend(0.0);
     }
 
     // For the player, I don't think that I need to do anything else.
@@ -0,0 +1,158 @@
+//! Scenarios are authored arenas that replace the random placement of gladiators, which
+//! makes it possible to set up and repeat specific matchups.
+//!
+//! A scenario is a Bevy scene file in assets/scenarios/ where each entity is a gladiator
+//! with a Transform (position) and a GladiatorClass, plus optionally a Level, Details
+//! (name), Equipment, Behaviour, Team and the Player marker. Only those authored values
+//! live in the file; everything else (sprite sheet, stats, timers, ...) is filled in by
+//! finish_scenario_gladiators once the scene has been spawned into the world. With the
+//! `scripting` feature a gladiator can also have a Brain.
+//!
+//! Launch a scenario by name with:
+//!  cargo run -- --scenario player_vs_3_fighters
+
+use bevy::prelude::*;
+
+use crate::{
+    gladiator::{gladiator::*, gladiator_bundles::*, gladiator_components::*},
+    helper_functions::arg_value,
+    player::{player_bundles::*, player_components::*},
+    team::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveScenario>();
+
+        // a scenario decides which gladiators are in the arena and whether there is a player
+        if app.world.resource::<ActiveScenario>().is_active() {
+            app.insert_resource(ArenaPopulation {
+                random_gladiators: false,
+                player: false,
+                ..default()
+            });
+        }
+
+        app.add_startup_system(load_scenario)
+            .add_system(finish_scenario_gladiators);
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Queues the scene of the active scenario to be spawned once it has loaded.
+fn load_scenario(
+    active_scenario: Res<ActiveScenario>,
+    asset_server: Res<AssetServer>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+) {
+    if let Some(path) = active_scenario.scene_path() {
+        println!("Loading scenario {}", path);
+        scene_spawner.spawn_dynamic(asset_server.load(&path));
+    }
+}
+
+/// Gladiators coming out of a scenario file only have the authored components. This
+/// builds out the rest of the gladiator around them, keeping the authored position,
+/// level, XP and name.
+fn finish_scenario_gladiators(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    query: Query<
+        (
+            Entity,
+            &GladiatorClass,
+            &Transform,
+            Option<&Level>,
+            Option<&Team>,
+            Option<&Player>,
+        ),
+        Without<Gladiator>,
+    >,
+) {
+    for (idx, (entity, class, transform, level, team, player)) in query.iter().enumerate() {
+        let texture_atlas_handle = load_gladiator_texture_atlas(
+            class.class.sprite_file(idx),
+            &asset_server,
+            &mut texture_atlases,
+        );
+
+        // the player is drawn on top of the other gladiators
+        let z = if player.is_some() { 2.0 } else { 1.0 };
+        let mut sprite_transform = Transform::from_scale(Vec3::splat(GLADIATOR_SIZE));
+        sprite_transform.translation = transform.translation.truncate().extend(z);
+
+        // the authored Level is kept, only what the scene file left out comes from the class
+        let mut gladiator = GladiatorBundle::new(class.class);
+        if let Some(level) = level {
+            gladiator = gladiator.with_level(level.level.max(1)).with_xp(level.xp);
+            if level.class_xp_modifier > 0.0 {
+                gladiator = gladiator.with_class_xp_modifier(level.class_xp_modifier);
+            }
+        }
+
+        let mut sprite = TextureAtlasSprite::default();
+        if let Some(team) = team {
+            sprite.color = team.color();
+        }
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert(SpriteSheetBundle {
+            sprite,
+            texture_atlas: texture_atlas_handle,
+            transform: sprite_transform,
+            ..default()
+        });
+
+        if player.is_some() {
+            entity_commands
+                .insert(PlayerBundle::from_gladiator(gladiator))
+                // same override as spawn_player so the player is more robust for now.
+                .insert(Health::new(PLAYER_HEALTH));
+        } else {
+            entity_commands.insert(gladiator);
+        }
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// The scenario chosen on the command line, if any. When there is no scenario the arena
+/// is populated randomly by spawn_gladiators and spawn_player.
+#[derive(Resource, Default, Debug)]
+pub struct ActiveScenario {
+    pub name: Option<String>,
+}
+
+impl ActiveScenario {
+    /// Reads the scenario name from `--scenario <name>` in the command line arguments.
+    /// * `args` - command line arguments, usually std::env::args()
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let name = arg_value(args, "--scenario");
+
+        Self { name }
+    }
+
+    /// Asset path of the scene file for this scenario.
+    pub fn scene_path(&self) -> Option<String> {
+        self.name
+            .as_ref()
+            .map(|name| format!("{}{}.scn.ron", SCENARIOS_PATH, name))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.name.is_some()
+    }
+}
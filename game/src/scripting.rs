@@ -0,0 +1,383 @@
+use bevy::{
+    prelude::*,
+    time::FixedTimestep,
+    utils::{HashMap, HashSet},
+};
+use rhai::{module_resolvers::DummyModuleResolver, Array, Dynamic, Engine, Map, Scope, AST, FLOAT};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    engagements::*,
+    gladiator::{gladiator_ai::*, gladiator_components::*, gladiator_movement::*},
+    grid::*,
+    helper_functions::*,
+    player::player_components::*,
+    team::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+/// Gladiator brains written in Rhai, so new AI can be tried out without recompiling. Only
+/// built with the `scripting` feature:
+///  cargo run --features scripting -- --brain skirmisher.rhai
+///
+/// A gladiator with a Brain runs the `think(me, nearby)` function of its script in
+/// assets/scripts/ every AI_STEP, after the utility AI in gladiator_ai has made its own
+/// decision. The script gets a copy of the gladiator's stats and of the gladiators it can
+/// see in the ArenaGrid, and returns a map with the direction to move in and whether to
+/// attack. Whatever the script returns replaces the decision of the utility AI; scripts
+/// that fail or run out of budget leave it in place.
+///
+/// Scripts are sandboxed: they can't import modules or touch the game, and every call to
+/// `think` gets SCRIPT_MAX_OPERATIONS operations before it is cut off.
+///
+/// Brains can be authored in a scenario, and `--brain <script>` gives every AI gladiator
+/// without one the given script.
+pub struct ScriptingPlugin {
+    /// Script for every AI gladiator that doesn't have a Brain yet
+    pub default_brain: Option<String>,
+}
+
+impl ScriptingPlugin {
+    /// Reads the default brain from `--brain <script>` in the command line arguments.
+    /// * `args` - command line arguments, usually std::env::args()
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        let default_brain = arg_value(args, "--brain");
+
+        Self { default_brain }
+    }
+}
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Brain>()
+            .insert_resource(DefaultBrain(self.default_brain.clone()))
+            .init_resource::<ScriptEngine>()
+            .add_system(assign_default_brain)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(AI_STEP as f64))
+                    .with_system(run_brains.after(decide_actions)),
+            );
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Gives new AI gladiators the script passed with `--brain`
+fn assign_default_brain(
+    mut commands: Commands,
+    default_brain: Res<DefaultBrain>,
+    query: Query<Entity, (Added<Intent>, Without<Brain>)>,
+) {
+    let script = match &default_brain.0 {
+        Some(script) => script,
+        None => return,
+    };
+    for entity in &query {
+        commands.entity(entity).insert(Brain {
+            script: script.clone(),
+        });
+    }
+}
+
+/// Lets every scripted gladiator decide what to do
+fn run_brains(
+    mut script_engine: ResMut<ScriptEngine>,
+    arena_grid: Res<ArenaGrid>,
+    mut query: Query<
+        (
+            Entity,
+            &Brain,
+            &Transform,
+            &Health,
+            &Attack,
+            &Defense,
+            &Movement,
+            &Level,
+            Option<&Team>,
+            Option<&Engagement>,
+            &mut Intent,
+        ),
+        Without<Player>,
+    >,
+    neighbour_query: Query<
+        (
+            &Transform,
+            &Health,
+            &GladiatorClass,
+            Option<&Team>,
+            Option<&Engagement>,
+        ),
+        With<Gladiator>,
+    >,
+) {
+    for (
+        entity,
+        brain,
+        transform,
+        health,
+        attack,
+        defense,
+        movement,
+        level,
+        team,
+        engagement,
+        mut intent,
+    ) in &mut query
+    {
+        let position = transform.translation.truncate();
+
+        let mut me = Map::new();
+        me.insert("x".into(), Dynamic::from_float(position.x as FLOAT));
+        me.insert("y".into(), Dynamic::from_float(position.y as FLOAT));
+        me.insert("health".into(), Dynamic::from_float(health.value as FLOAT));
+        me.insert(
+            "max_health".into(),
+            Dynamic::from_float(health.max as FLOAT),
+        );
+        me.insert("damage".into(), Dynamic::from_float(attack.damage as FLOAT));
+        me.insert(
+            "defense".into(),
+            Dynamic::from_float(defense.value as FLOAT),
+        );
+        me.insert(
+            "speed".into(),
+            Dynamic::from_float(movement.current_speed() as FLOAT),
+        );
+        me.insert("level".into(), Dynamic::from_int(level.level as i64));
+        me.insert(
+            "team".into(),
+            Dynamic::from_int(team.map_or(-1, |team| team.0 as i64)),
+        );
+        me.insert("engaged".into(), Dynamic::from_bool(engagement.is_some()));
+
+        let grid_location = ArenaGrid::get_grid_location(position.x, position.y);
+        let nearby: Array = arena_grid
+            .entities_around(grid_location, AI_SIGHT)
+            .filter(|neighbour| *neighbour != entity)
+            .filter_map(|neighbour| neighbour_query.get(neighbour).ok())
+            .map(
+                |(other_transform, other_health, other_class, other_team, other_engagement)| {
+                    let other_position = other_transform.translation.truncate();
+                    let offset = other_position - position;
+
+                    let mut other = Map::new();
+                    other.insert("x".into(), Dynamic::from_float(other_position.x as FLOAT));
+                    other.insert("y".into(), Dynamic::from_float(other_position.y as FLOAT));
+                    other.insert("dx".into(), Dynamic::from_float(offset.x as FLOAT));
+                    other.insert("dy".into(), Dynamic::from_float(offset.y as FLOAT));
+                    other.insert(
+                        "distance".into(),
+                        Dynamic::from_float(offset.length() as FLOAT),
+                    );
+                    other.insert(
+                        "health".into(),
+                        Dynamic::from_float(other_health.value as FLOAT),
+                    );
+                    other.insert(
+                        "max_health".into(),
+                        Dynamic::from_float(other_health.max as FLOAT),
+                    );
+                    other.insert("class".into(), format!("{:?}", other_class.class).into());
+                    other.insert(
+                        "ally".into(),
+                        Dynamic::from_bool(Team::are_teammates(team, other_team)),
+                    );
+                    other.insert(
+                        "engaged".into(),
+                        Dynamic::from_bool(other_engagement.is_some()),
+                    );
+                    Dynamic::from_map(other)
+                },
+            )
+            .collect();
+
+        match script_engine.think(&brain.script, me, nearby) {
+            Ok(decision) => {
+                intent.action = decision.action;
+                intent.hold_attacks = !decision.attack;
+            }
+            Err(err) => {
+                // fall back to the utility AI, only complain once per script
+                intent.hold_attacks = false;
+                script_engine.report_error(&brain.script, &err);
+            }
+        }
+    }
+}
+
+/// Name of a direction as scripts see it
+fn direction_name(direction: GladiatorDirection) -> &'static str {
+    match direction {
+        GladiatorDirection::Down => "down",
+        GladiatorDirection::DownRight => "down_right",
+        GladiatorDirection::Right => "right",
+        GladiatorDirection::UpRight => "up_right",
+        GladiatorDirection::Up => "up",
+        GladiatorDirection::UpLeft => "up_left",
+        GladiatorDirection::Left => "left",
+        GladiatorDirection::DownLeft => "down_left",
+    }
+}
+
+/// The action that goes with a direction returned by a script
+fn action_from_name(name: &str) -> Result<AiAction, String> {
+    match name {
+        "down" => Ok(AiAction::Move(GladiatorDirection::Down)),
+        "down_right" => Ok(AiAction::Move(GladiatorDirection::DownRight)),
+        "right" => Ok(AiAction::Move(GladiatorDirection::Right)),
+        "up_right" => Ok(AiAction::Move(GladiatorDirection::UpRight)),
+        "up" => Ok(AiAction::Move(GladiatorDirection::Up)),
+        "up_left" => Ok(AiAction::Move(GladiatorDirection::UpLeft)),
+        "left" => Ok(AiAction::Move(GladiatorDirection::Left)),
+        "down_left" => Ok(AiAction::Move(GladiatorDirection::DownLeft)),
+        "hold" => Ok(AiAction::Hold),
+        "wander" => Ok(AiAction::Wander),
+        _ => Err(format!("Unknown direction \"{}\"", name)),
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// A gladiator whose decisions are made by a script
+#[derive(Component, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Brain {
+    /// File name of the script in assets/scripts/, e.g. "skirmisher.rhai"
+    pub script: String,
+}
+
+#[derive(Resource)]
+struct DefaultBrain(Option<String>);
+
+/// The sandboxed Rhai engine and the scripts it has compiled
+#[derive(Resource)]
+pub struct ScriptEngine {
+    engine: Engine,
+    /// Compiled scripts by file name, None when the script couldn't be loaded
+    scripts: HashMap<String, Option<AST>>,
+    /// Scripts that have already had an error reported
+    failed: HashSet<String>,
+}
+
+impl Default for ScriptEngine {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+
+        // scripts only get to see what they are handed
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.set_max_operations(SCRIPT_MAX_OPERATIONS);
+        engine.set_max_call_levels(SCRIPT_MAX_CALL_LEVELS);
+        engine.set_max_string_size(SCRIPT_MAX_STRING_SIZE);
+        engine.set_max_array_size(SCRIPT_MAX_COLLECTION_SIZE);
+        engine.set_max_map_size(SCRIPT_MAX_COLLECTION_SIZE);
+        engine.on_print(|text| println!("[script] {}", text));
+
+        // direction_to(dx, dy) snaps an offset to the name of one of the 8 directions
+        engine.register_fn("direction_to", |dx: FLOAT, dy: FLOAT| {
+            GladiatorDirection::toward(Vec2::new(dx as f32, dy as f32))
+                .map_or("wander", direction_name)
+                .to_string()
+        });
+
+        Self {
+            engine,
+            scripts: HashMap::default(),
+            failed: HashSet::default(),
+        }
+    }
+}
+
+impl ScriptEngine {
+    /// Runs the `think` function of a script
+    /// * `script` - file name of the script in assets/scripts/
+    /// * `me` - the stats of the gladiator that is thinking
+    /// * `nearby` - the gladiators it can see
+    pub fn think(&mut self, script: &str, me: Map, nearby: Array) -> Result<BrainDecision, String> {
+        self.load(script);
+        let ast = match self.scripts.get(script) {
+            Some(Some(ast)) => ast,
+            _ => return Err(format!("{} could not be loaded", script)),
+        };
+
+        let result: Map = self
+            .engine
+            .call_fn(&mut Scope::new(), ast, "think", (me, nearby))
+            .map_err(|err| err.to_string())?;
+        BrainDecision::from_map(&result)
+    }
+
+    /// Prints the first error of every script
+    pub fn report_error(&mut self, script: &str, err: &str) {
+        if self.failed.insert(script.to_string()) {
+            println!(
+                "Brain {} failed, falling back to the built-in AI: {}",
+                script, err
+            );
+        }
+    }
+
+    /// Reads and compiles a script the first time it is used
+    fn load(&mut self, script: &str) {
+        if self.scripts.contains_key(script) {
+            return;
+        }
+
+        let path = format!("{}{}", SCRIPTS_PATH, script);
+        let ast = read_data_file(&path).and_then(|source| {
+            self.engine
+                .compile(source)
+                .map_err(|err| format!("Could not compile {}: {}", path, err))
+        });
+        let ast = match ast {
+            Ok(ast) => Some(ast),
+            Err(err) => {
+                self.report_error(script, &err);
+                None
+            }
+        };
+        self.scripts.insert(script.to_string(), ast);
+    }
+}
+
+/// What a script decided, read from the map returned by `think`:
+///  #{ direction: "up_left", attack: true }
+/// direction is one of the 8 directions, "hold" or "wander" (the default), attack
+/// defaults to true.
+pub struct BrainDecision {
+    pub action: AiAction,
+    pub attack: bool,
+}
+
+impl BrainDecision {
+    fn from_map(map: &Map) -> Result<Self, String> {
+        let action = match map.get("direction") {
+            Some(direction) => {
+                let direction = direction
+                    .clone()
+                    .into_immutable_string()
+                    .map_err(|type_name| {
+                        format!("direction should be a string, not {}", type_name)
+                    })?;
+                action_from_name(&direction)?
+            }
+            None => AiAction::Wander,
+        };
+        let attack = match map.get("attack") {
+            Some(attack) => attack
+                .as_bool()
+                .map_err(|type_name| format!("attack should be a bool, not {}", type_name))?,
+            None => true,
+        };
+
+        Ok(Self { action, attack })
+    }
+}
@@ -0,0 +1,310 @@
+//! The shop opens between career battles. The player's gladiator (first on the roster)
+//! spends the gold they earned on healing items to carry into the next battle, weapons
+//! and armor, or training. What is for sale, the prices and how many of each item the
+//! shop has in stock are read from assets/shop/catalog.ron. Weapons, armor and trinkets
+//! are looked up by name in assets/items/items.ron when they are equipped.
+//!
+//! Press the number of an item to buy it and space to head into the next battle. The
+//! listing shows nine items at a time, the arrow keys turn to the previous or next page.
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{career::*, helper_functions::*};
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Restocks the shop from the catalog and shows what is for sale.
+pub fn open_shop(mut shop: ResMut<Shop>, career: Res<Career>) {
+    shop.items = shop.catalog.items.clone();
+    shop.page = 0;
+
+    match career.save.roster.first() {
+        Some(shopper) => println!("{}", shop.listing(shopper)),
+        None => println!("Nobody is left on the roster to go shopping."),
+    }
+}
+
+/// Buys items with the number keys, turns pages with the arrow keys and leaves the shop
+/// with space.
+pub fn shop_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut shop: ResMut<Shop>,
+    mut career: ResMut<Career>,
+    mut career_state: ResMut<State<CareerState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        career_state
+            .set(CareerState::Battle)
+            .expect("Should be able to leave the shop.");
+        return;
+    }
+
+    let shopper = match career.save.roster.first_mut() {
+        Some(shopper) => shopper,
+        None => return,
+    };
+
+    let page_turn = if keyboard_input.just_pressed(KeyCode::Right) {
+        Some(1)
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        Some(-1)
+    } else {
+        None
+    };
+    if let Some(page_turn) = page_turn {
+        shop.turn_page(page_turn);
+        println!("{}", shop.listing(shopper));
+        return;
+    }
+
+    let item_idx = match SHOP_KEYS
+        .iter()
+        .position(|key| keyboard_input.just_pressed(*key))
+    {
+        Some(key_idx) => shop.page * SHOP_KEYS.len() + key_idx,
+        None => return,
+    };
+
+    match shop.buy(item_idx, shopper) {
+        Ok(item_name) => {
+            println!("{} bought {}!", shopper.name, item_name);
+            println!("{}", shop.listing(shopper));
+        }
+        Err(err) => println!("{}", err),
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// Number keys used to pick items, the first key buys the first item on the page and so
+/// on. A page of the listing holds as many items as there are keys.
+const SHOP_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+#[derive(Resource)]
+pub struct Shop {
+    pub catalog: ShopCatalog,
+    /// Items on the shelves during this visit, with their remaining stock
+    pub items: Vec<ShopItem>,
+    /// Page of the listing the number keys buy from
+    pub page: usize,
+}
+
+impl Shop {
+    pub fn new(catalog: ShopCatalog) -> Self {
+        Self {
+            items: catalog.items.clone(),
+            catalog,
+            page: 0,
+        }
+    }
+
+    pub fn n_pages(&self) -> usize {
+        self.items.len().div_ceil(SHOP_KEYS.len()).max(1)
+    }
+
+    /// Turns the listing forwards or backwards, wrapping around at either end
+    /// * `by` - how many pages to turn, negative to turn back
+    pub fn turn_page(&mut self, by: isize) {
+        let n_pages = self.n_pages() as isize;
+        self.page = (self.page as isize + by).rem_euclid(n_pages) as usize;
+    }
+
+    /// Trades the shopper's gold for the item and hands it over. Returns the name of the
+    /// item that was bought.
+    /// * `item_idx` - index of the item in the whole listing, not just the page
+    /// * `shopper` - the roster gladiator that is buying
+    pub fn buy(
+        &mut self,
+        item_idx: usize,
+        shopper: &mut RosterGladiator,
+    ) -> Result<String, String> {
+        let item = self
+            .items
+            .get_mut(item_idx)
+            .ok_or_else(|| "There is no such item.".to_string())?;
+        if item.stock == 0 {
+            return Err(format!("{} is sold out.", item.name));
+        }
+        if shopper.gold < item.price {
+            return Err(format!(
+                "{} costs {} gold, {} only has {}.",
+                item.name, item.price, shopper.name, shopper.gold
+            ));
+        }
+
+        shopper.gold -= item.price;
+        item.stock -= 1;
+        match item.kind {
+            ShopItemKind::Healing(healing_item) => shopper.healing_items.push(healing_item),
+            ShopItemKind::Weapon | ShopItemKind::Armor | ShopItemKind::Trinket => {
+                shopper.equipment.push(item.name.clone())
+            }
+            ShopItemKind::Training => gain_experience(&mut shopper.level, &mut shopper.xp),
+        }
+
+        Ok(item.name.clone())
+    }
+
+    pub fn listing(&self, shopper: &RosterGladiator) -> String {
+        let mut listing = format!("Shop ({} has {} gold):\n", shopper.name, shopper.gold);
+        let page_items = self
+            .items
+            .iter()
+            .skip(self.page * SHOP_KEYS.len())
+            .take(SHOP_KEYS.len());
+        for (idx, item) in page_items.enumerate() {
+            listing.push_str(&format!(
+                "  [{}] {:<16} {:>4} gold | {} left\n",
+                idx + 1,
+                item.name,
+                item.price,
+                item.stock
+            ));
+        }
+        if self.n_pages() > 1 {
+            listing.push_str(&format!(
+                "  [left/right] page {} of {}\n",
+                self.page + 1,
+                self.n_pages()
+            ));
+        }
+        listing.push_str("  [space] next battle");
+        listing
+    }
+}
+
+/// Contents of assets/shop/catalog.ron
+#[derive(Clone, Deserialize)]
+pub struct ShopCatalog {
+    pub items: Vec<ShopItem>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ShopItem {
+    pub name: String,
+    pub kind: ShopItemKind,
+    pub price: u32,
+    pub stock: u32,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+pub enum ShopItemKind {
+    /// Carried into battle and used with H
+    Healing(HealingItem),
+    Weapon,
+    Armor,
+    Trinket,
+    /// Trains the gladiator up a level, with the health, damage and defense that come
+    /// with it
+    Training,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gladiator::gladiator::Class, SHOP_CATALOG_PATH};
+
+    fn shop_and_shopper(gold: u32) -> (Shop, RosterGladiator) {
+        let item = |name: &str, kind, price, stock| ShopItem {
+            name: name.to_string(),
+            kind,
+            price,
+            stock,
+        };
+        let shop = Shop::new(ShopCatalog {
+            items: vec![
+                item("Potion", ShopItemKind::Healing(HealingItem::Potion), 20, 2),
+                item("Gladius", ShopItemKind::Weapon, 60, 1),
+                item("Training", ShopItemKind::Training, 50, 1),
+            ],
+        });
+        let mut save = CareerSave::default();
+        save.recruit("Spartacus", Class::Fighter);
+        let mut shopper = save.roster.remove(0);
+        shopper.gold = gold;
+        (shop, shopper)
+    }
+
+    #[test]
+    fn buying_takes_gold_and_stock() {
+        let (mut shop, mut shopper) = shop_and_shopper(100);
+
+        assert_eq!(shop.buy(0, &mut shopper), Ok("Potion".to_string()));
+        assert_eq!(shopper.gold, 80);
+        assert_eq!(shop.items[0].stock, 1);
+        assert_eq!(shopper.healing_items, vec![HealingItem::Potion]);
+
+        assert_eq!(shop.buy(1, &mut shopper), Ok("Gladius".to_string()));
+        assert_eq!(shopper.gold, 20);
+        assert_eq!(shopper.equipment, vec!["Gladius".to_string()]);
+    }
+
+    #[test]
+    fn items_cannot_be_bought_without_enough_gold() {
+        let (mut shop, mut shopper) = shop_and_shopper(50);
+
+        assert!(shop.buy(1, &mut shopper).is_err());
+        assert_eq!(shopper.gold, 50);
+        assert_eq!(shop.items[1].stock, 1);
+        assert!(shopper.equipment.is_empty());
+    }
+
+    #[test]
+    fn items_sell_out() {
+        let (mut shop, mut shopper) = shop_and_shopper(100);
+
+        assert!(shop.buy(0, &mut shopper).is_ok());
+        assert!(shop.buy(0, &mut shopper).is_ok());
+        assert_eq!(shop.items[0].stock, 0);
+        assert!(shop.buy(0, &mut shopper).is_err());
+        assert_eq!(shopper.gold, 60);
+        assert_eq!(shopper.healing_items.len(), 2);
+
+        assert!(shop.buy(3, &mut shopper).is_err());
+    }
+
+    #[test]
+    fn training_raises_the_level() {
+        let (mut shop, mut shopper) = shop_and_shopper(50);
+        shopper.xp = 3.0;
+
+        assert_eq!(shop.buy(2, &mut shopper), Ok("Training".to_string()));
+        assert_eq!((shopper.level, shopper.xp), (2, 0.0));
+        assert_eq!(shopper.gold, 0);
+    }
+
+    #[test]
+    fn every_item_in_the_catalog_is_on_a_page() {
+        let catalog: ShopCatalog = load_data_file(SHOP_CATALOG_PATH).unwrap();
+        let n_items = catalog.items.len();
+        let mut shop = Shop::new(catalog);
+        let (_, shopper) = shop_and_shopper(0);
+
+        let mut listed = 0;
+        for _ in 0..shop.n_pages() {
+            listed += shop.listing(&shopper).matches(" gold | ").count();
+            shop.turn_page(1);
+        }
+        assert_eq!(listed, n_items);
+        assert_eq!(shop.page, 0);
+
+        shop.turn_page(-1);
+        assert_eq!(shop.page, shop.n_pages() - 1);
+        assert!(shop.listing(&shopper).contains("Training"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Survival mode: the player starts alone and waves of gladiators come in from the edges
+//! of the arena, each wave bigger and higher level than the last. Between waves there is
+//! a short rest where the player recovers health (the "delay between engagements" idea
+//! from player.rs). The score is the number of waves survived. The player is mortal here,
+//! with SURVIVAL_PLAYER_HEALTH instead of the PLAYER_HEALTH of the other modes.
+//!
+//! Play survival mode with:
+//!  cargo run -- --survival
+
+use bevy::prelude::*;
+use rand::{self, rngs::ThreadRng, Rng};
+
+use crate::{
+    gladiator::{gladiator::*, gladiator_ai::*, gladiator_components::*},
+    player::player_components::*,
+    team::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+pub struct SurvivalPlugin;
+
+impl Plugin for SurvivalPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Team>()
+            .insert_resource(ArenaPopulation {
+                random_gladiators: false,
+                player: true,
+                ..default()
+            })
+            .init_resource::<Survival>()
+            .add_system(set_player_health)
+            .add_system(hunt_player)
+            .add_system(run_waves);
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Moves survival mode between resting and fighting, spawning the next wave when the
+/// rest is over and ending the run when the player has died.
+fn run_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut survival: ResMut<Survival>,
+    enemy_query: Query<(), (With<Gladiator>, Without<Player>)>,
+    mut player_query: Query<&mut Health, With<Player>>,
+) {
+    let survival = &mut *survival;
+    if matches!(survival.state, WaveState::GameOver) {
+        return;
+    }
+
+    // the player is despawned by the death handler
+    if player_query.is_empty() {
+        println!("Game over! You survived {} waves.", survival.waves_survived);
+        survival.state = WaveState::GameOver;
+        return;
+    }
+
+    match &mut survival.state {
+        WaveState::Resting(timer) => {
+            timer.tick(time.delta());
+            for mut health in &mut player_query {
+                health.heal(SURVIVAL_REST_HEALING * time.delta_seconds());
+            }
+
+            if timer.finished() {
+                survival.wave += 1;
+                println!("Wave {} is coming!", survival.wave);
+                spawn_wave(
+                    survival.wave,
+                    &mut commands,
+                    &asset_server,
+                    &mut texture_atlases,
+                );
+                survival.state = WaveState::Fighting;
+            }
+        }
+        WaveState::Fighting => {
+            if enemy_query.is_empty() {
+                survival.waves_survived += 1;
+                println!(
+                    "Wave {} defeated! Score: {}",
+                    survival.wave, survival.waves_survived
+                );
+                survival.state = WaveState::resting();
+            }
+        }
+        WaveState::GameOver => {}
+    }
+}
+
+/// The player starts a survival run with SURVIVAL_PLAYER_HEALTH
+fn set_player_health(mut query: Query<&mut Health, Added<Player>>) {
+    for mut health in &mut query {
+        *health = Health::new(SURVIVAL_PLAYER_HEALTH);
+    }
+}
+
+/// Enemies that would otherwise wander head for the player. Once the player is in sight
+/// the AI decides how to fight them.
+fn hunt_player(
+    player_query: Query<&Transform, With<Player>>,
+    mut enemy_query: Query<&mut Intent, Without<Player>>,
+) {
+    let player_position = match player_query.iter().next() {
+        Some(transform) => transform.translation.truncate(),
+        None => return,
+    };
+    for mut intent in &mut enemy_query {
+        if matches!(intent.action, AiAction::Wander) {
+            intent.action = AiAction::MoveToward(player_position);
+        }
+    }
+}
+
+/// Spawns all of the gladiators of a wave along the edges of the arena. They are all on
+/// the same team so they never fight each other, only the player.
+/// * `wave` - wave number, starting at 1
+fn spawn_wave(
+    wave: usize,
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+) {
+    let n_gladiators = SURVIVAL_FIRST_WAVE_SIZE + (wave - 1) * SURVIVAL_WAVE_GROWTH;
+    let gladiator_spawn = GladiatorSpawn {
+        class: None,
+        team: Some(Team(SURVIVAL_ENEMY_TEAM)),
+        level: 1 + (wave - 1) / SURVIVAL_LEVEL_UP_WAVES,
+        ..default()
+    };
+
+    let mut rng = rand::thread_rng();
+    for idx in 0..n_gladiators {
+        spawn_one_gladiator(
+            random_edge_location(&mut rng),
+            idx,
+            &gladiator_spawn,
+            commands,
+            asset_server,
+            texture_atlases,
+        );
+    }
+}
+
+/// Picks a random spot on one of the four edges of the arena
+fn random_edge_location(rng: &mut ThreadRng) -> Vec2 {
+    let half_width = WINDOW_WIDTH / 2.;
+    let half_height = WINDOW_HEIGHT / 2.;
+    let x = rng.gen_range(-half_width..half_width);
+    let y = rng.gen_range(-half_height..half_height);
+
+    match rng.gen_range(0..4) {
+        0 => Vec2::new(x, half_height),
+        1 => Vec2::new(x, -half_height),
+        2 => Vec2::new(half_width, y),
+        _ => Vec2::new(-half_width, y),
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+#[derive(Resource)]
+pub struct Survival {
+    /// The wave currently being fought, 0 before the first wave arrives
+    pub wave: usize,
+    /// The score
+    pub waves_survived: usize,
+    pub state: WaveState,
+}
+
+impl Default for Survival {
+    fn default() -> Self {
+        Self {
+            wave: 0,
+            waves_survived: 0,
+            state: WaveState::resting(),
+        }
+    }
+}
+
+pub enum WaveState {
+    /// Time left until the next wave
+    Resting(Timer),
+    Fighting,
+    GameOver,
+}
+
+impl WaveState {
+    fn resting() -> Self {
+        Self::Resting(Timer::from_seconds(SURVIVAL_REST_DURATION, TimerMode::Once))
+    }
+}
@@ -0,0 +1,271 @@
+//! Teams are optional. A gladiator without a Team fights anyone it runs into, which is
+//! how free-for-all works. Gladiators that share a Team never engage each other, and the
+//! battle is won once only one team (or one lone gladiator) is left standing.
+//!
+//! Choose the team composition with:
+//!  cargo run -- --teams 5v5     (any number of teams, e.g. 3v3v3)
+//!  cargo run -- --teams class   (archers vs mages vs fighters)
+//!  cargo run -- --teams ffa     (default)
+
+use bevy::{prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gladiator::{gladiator::*, gladiator_components::*},
+    helper_functions::arg_value,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+pub struct TeamPlugin;
+
+impl Plugin for TeamPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Team>()
+            .init_resource::<TeamMode>()
+            .add_event::<VictoryEvent>()
+            .add_system(last_team_standing);
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Watches the gladiators that are still alive and announces the winner once only one
+/// team remains. Gladiators without a Team each count as their own faction. A battle
+/// starts once there are at least two factions in the arena, so game modes that fight
+/// several battles in a row get a VictoryEvent for each of them.
+fn last_team_standing(
+    query: Query<Option<&Team>, With<Gladiator>>,
+    mut ev_victory: EventWriter<VictoryEvent>,
+    mut battle_in_progress: Local<bool>,
+) {
+    let mut teams = HashSet::default();
+    let mut n_loners = 0;
+    for team in &query {
+        match team {
+            Some(team) => {
+                teams.insert(*team);
+            }
+            None => n_loners += 1,
+        }
+    }
+
+    let n_factions = teams.len() + n_loners;
+    if n_factions > 1 {
+        *battle_in_progress = true;
+    }
+
+    // nobody has been spawned yet (or everybody died at once), nothing to declare
+    if !*battle_in_progress || n_factions != 1 {
+        return;
+    }
+
+    let team = teams.into_iter().next();
+    match team {
+        Some(team) => println!("Team {} is the last team standing!", team.0),
+        None => println!("Last gladiator standing!"),
+    }
+    ev_victory.send(VictoryEvent { team });
+    *battle_in_progress = false;
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+#[derive(
+    Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Component)]
+pub struct Team(pub u32);
+
+impl Team {
+    /// Gladiators are only teammates when both of them are on the same Team.
+    pub fn are_teammates(a: Option<&Team>, b: Option<&Team>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Tint applied to the sprites of this team's gladiators
+    pub fn color(&self) -> Color {
+        TEAM_COLORS[self.0 as usize % TEAM_COLORS.len()]
+    }
+}
+
+/// Sent once when a single team is left in the arena. `team` is None when the winner
+/// is a lone gladiator without a team.
+#[derive(Clone, Debug)]
+pub struct VictoryEvent {
+    pub team: Option<Team>,
+}
+
+/// How the gladiators spawned at startup are split into teams.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TeamMode {
+    /// Every gladiator for themselves, no Team components at all
+    #[default]
+    FreeForAll,
+    /// `n_teams` teams of `team_size` gladiators each, e.g. 5v5
+    Teams { n_teams: usize, team_size: usize },
+    /// One team per class with `team_size` gladiators each
+    ClassVsClass { team_size: usize },
+}
+
+impl TeamMode {
+    const CLASSES: [Class; 3] = [Class::Archer, Class::Mage, Class::Fighter];
+
+    /// Reads the team composition from `--teams <mode>` in the command line arguments.
+    /// Falls back to free-for-all when the mode isn't recognized.
+    /// * `args` - command line arguments, usually std::env::args()
+    pub fn from_args(args: impl Iterator<Item = String>) -> Self {
+        match arg_value(args, "--teams") {
+            Some(mode) => Self::parse(&mode).unwrap_or_else(|err| {
+                println!("{} Falling back to free-for-all.", err);
+                Self::FreeForAll
+            }),
+            None => Self::FreeForAll,
+        }
+    }
+
+    /// Parses "ffa", "class" or team sizes separated by 'v' like "5v5" or "3v3v3".
+    pub fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "ffa" => Ok(Self::FreeForAll),
+            "class" => Ok(Self::ClassVsClass {
+                team_size: CLASS_TEAM_SIZE,
+            }),
+            _ => {
+                let sizes = mode
+                    .split('v')
+                    .map(|size| size.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| format!("Unknown team mode '{}'.", mode))?;
+
+                // uneven teams would need a list of sizes, keeping it simple for now
+                if sizes.len() < 2 || sizes.iter().any(|size| *size != sizes[0] || *size == 0) {
+                    return Err(format!("Teams in '{}' should be the same size.", mode));
+                }
+
+                Ok(Self::Teams {
+                    n_teams: sizes.len(),
+                    team_size: sizes[0],
+                })
+            }
+        }
+    }
+
+    /// Number of gladiators to spawn for this composition
+    pub fn n_gladiators(&self) -> usize {
+        match self {
+            Self::FreeForAll => N_GLADIATORS,
+            Self::Teams { n_teams, team_size } => n_teams * team_size,
+            Self::ClassVsClass { team_size } => Self::CLASSES.len() * team_size,
+        }
+    }
+
+    /// Class of the gladiator spawned at `gladiator_idx`, if this mode dictates one.
+    pub fn gladiator_class(&self, gladiator_idx: usize) -> Option<Class> {
+        match self {
+            Self::ClassVsClass { team_size } => Some(Self::CLASSES[gladiator_idx / team_size]),
+            _ => None,
+        }
+    }
+
+    /// Team of the gladiator spawned at `gladiator_idx`
+    pub fn team(&self, gladiator_idx: usize) -> Option<Team> {
+        match self {
+            Self::FreeForAll => None,
+            Self::Teams { team_size, .. } | Self::ClassVsClass { team_size } => {
+                Some(Team((gladiator_idx / team_size) as u32))
+            }
+        }
+    }
+
+    /// The player joins the first team, or the team of their class in class-vs-class.
+    pub fn player_team(&self, player_class: Class) -> Option<Team> {
+        match self {
+            Self::FreeForAll => None,
+            Self::Teams { .. } => Some(Team(0)),
+            Self::ClassVsClass { .. } => Self::CLASSES
+                .iter()
+                .position(|class| *class == player_class)
+                .map(|idx| Team(idx as u32)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_even_teams() {
+        assert_eq!(
+            TeamMode::parse("5v5"),
+            Ok(TeamMode::Teams {
+                n_teams: 2,
+                team_size: 5
+            })
+        );
+        assert_eq!(
+            TeamMode::parse("3v3v3"),
+            Ok(TeamMode::Teams {
+                n_teams: 3,
+                team_size: 3
+            })
+        );
+    }
+
+    #[test]
+    fn parses_the_named_modes() {
+        assert_eq!(TeamMode::parse("ffa"), Ok(TeamMode::FreeForAll));
+        assert_eq!(
+            TeamMode::parse("class"),
+            Ok(TeamMode::ClassVsClass {
+                team_size: CLASS_TEAM_SIZE
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_bad_team_modes() {
+        for mode in ["", "5", "5v4", "0v0", "5vfive", "teams", "5v5v"] {
+            assert!(
+                TeamMode::parse(mode).is_err(),
+                "'{}' should not parse",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_modes_on_the_command_line_fall_back_to_free_for_all() {
+        let args = ["main", "--teams", "5v4"].map(String::from);
+        assert_eq!(TeamMode::from_args(args.into_iter()), TeamMode::FreeForAll);
+
+        let args = ["main", "--teams", "2v2"].map(String::from);
+        assert_eq!(
+            TeamMode::from_args(args.into_iter()),
+            TeamMode::Teams {
+                n_teams: 2,
+                team_size: 2
+            }
+        );
+    }
+
+    #[test]
+    fn teams_are_filled_in_order() {
+        let mode = TeamMode::parse("2v2v2").unwrap();
+        assert_eq!(mode.n_gladiators(), 6);
+        let teams: Vec<_> = (0..6).map(|idx| mode.team(idx).unwrap().0).collect();
+        assert_eq!(teams, vec![0, 0, 1, 1, 2, 2]);
+        assert_eq!(TeamMode::FreeForAll.team(0), None);
+    }
+}
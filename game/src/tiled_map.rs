@@ -0,0 +1,535 @@
+//! Arenas can be authored in the Tiled map editor (https://www.mapeditor.org/) and saved in
+//! assets/maps/ as .tmx (XML) or .tmj (JSON). Both are read into a TiledMap, which is
+//! turned into the same ArenaMap as the .ron maps:
+//!
+//! - Tile layers are drawn as the background, bottom layer first, stretched so the map
+//!   fills the arena. Hidden layers aren't drawn.
+//! - Tile layers with a `terrain` property (Sand, Water, Pillar or Wall) are collision
+//!   layers, every grid location under one of their tiles gets that terrain. Hide the
+//!   layer to use it for collision only.
+//! - Objects in an object layer named `spawns` are spawn points, with an optional int
+//!   `team` property for the team that spawns there.
+//! - Objects in an object layer named `items` are item spawners, with a string `item`
+//!   property naming an item from assets/items/items.ron and an optional float `interval`
+//!   property, the seconds until a picked up item is replaced.
+//!
+//! Tile layer data has to be stored as CSV, which is what Tiled does by default. Tilesets
+//! can be embedded or external (.tsx or .tsj), either one image or a collection of images.
+//! Play one with:
+//!  cargo run -- --map ludus.tmj
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    arena::*,
+    helper_functions::*,
+    team::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Reads a Tiled map into an ArenaMap
+/// * `path` - path of the map file relative to the assets folder, e.g. "maps/ludus.tmj"
+pub fn load_tiled_map(path: &str) -> Result<ArenaMap, String> {
+    let map_dir = parent_path(path);
+    let contents = read_data_file(path)?;
+    let mut map = if is_xml(path) {
+        tmx::parse_map(&contents)
+    } else {
+        serde_json::from_str::<TiledMap>(&contents).map_err(|err| err.to_string())
+    }
+    .map_err(|err| format!("Could not parse {}: {}", path, err))?;
+
+    for tileset in map.tilesets.iter_mut() {
+        let firstgid = tileset.firstgid;
+        let tileset_dir = match tileset.source.take() {
+            // images in an external tileset are relative to the tileset file
+            Some(source) => {
+                let tileset_path = join_path(&map_dir, &source);
+                let contents = read_data_file(&tileset_path)?;
+                *tileset = if is_xml(&tileset_path) {
+                    tmx::parse_tileset(&contents)
+                } else {
+                    serde_json::from_str::<TiledTileset>(&contents).map_err(|err| err.to_string())
+                }
+                .map_err(|err| format!("Could not parse {}: {}", tileset_path, err))?;
+                tileset.firstgid = firstgid;
+                parent_path(&tileset_path)
+            }
+            None => map_dir.clone(),
+        };
+        tileset.resolve_images(&tileset_dir);
+    }
+
+    Ok(map.into_arena_map())
+}
+
+fn is_xml(path: &str) -> bool {
+    path.ends_with(".tmx") || path.ends_with(".tsx")
+}
+
+/// Folder of a file in the assets folder, e.g. "maps" for "maps/ludus.tmj"
+fn parent_path(path: &str) -> String {
+    path.rsplit_once('/')
+        .map_or(String::new(), |(parent, _)| parent.to_string())
+}
+
+/// Path of a file relative to a folder in the assets folder, as the AssetServer wants it,
+/// e.g. "Puny-Characters/Environment/Dirt.png" for "../Puny-Characters/Environment/Dirt.png"
+/// in "maps"
+fn join_path(dir: &str, relative: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|part| !part.is_empty()).collect();
+    for part in relative.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// The flip and rotation flags Tiled stores in the top bits of a tile id
+const GID_FLAGS: u32 = 0xF000_0000;
+
+/// The parts of a Tiled map the arena uses, laid out like a .tmj file
+#[derive(Deserialize)]
+pub struct TiledMap {
+    /// Number of tiles across
+    pub width: u32,
+    /// Number of tiles down
+    pub height: u32,
+    pub tilewidth: u32,
+    pub tileheight: u32,
+    #[serde(default)]
+    pub layers: Vec<TiledLayer>,
+    #[serde(default)]
+    pub tilesets: Vec<TiledTileset>,
+}
+
+impl TiledMap {
+    pub fn into_arena_map(self) -> ArenaMap {
+        let mut arena_map = ArenaMap::default();
+
+        // Tiled has its origin in the top left corner with y going down, the arena has it
+        // in the middle with y going up
+        let map_size = Vec2::new(
+            (self.width * self.tilewidth) as f32,
+            (self.height * self.tileheight) as f32,
+        );
+        let scale = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT) / map_size;
+        let to_arena = |point: Vec2| {
+            Vec2::new(
+                point.x * scale.x - WINDOW_WIDTH / 2.0,
+                WINDOW_HEIGHT / 2.0 - point.y * scale.y,
+            )
+        };
+        let tile_size = Vec2::new(self.tilewidth as f32, self.tileheight as f32);
+
+        let mut layers = Vec::new();
+        flatten_layers(&self.layers, &mut layers);
+        for (depth, layer) in layers.into_iter().enumerate() {
+            match layer.kind.as_str() {
+                "tilelayer" => {
+                    let terrain = layer.property("terrain").and_then(|terrain| {
+                        serde_json::from_value::<Terrain>(terrain.clone()).ok()
+                    });
+                    let layer_width = layer.width.unwrap_or(self.width).max(1) as usize;
+
+                    for (idx, gid) in layer.data.iter().enumerate() {
+                        let gid = gid & !GID_FLAGS;
+                        if gid == 0 {
+                            continue; // no tile here
+                        }
+                        let corner = Vec2::new(
+                            (idx % layer_width) as f32 * tile_size.x,
+                            (idx / layer_width) as f32 * tile_size.y,
+                        );
+                        let top_left = to_arena(corner);
+                        let bottom_right = to_arena(corner + tile_size);
+
+                        if layer.visible {
+                            if let Some(image) = self.tile_image(gid) {
+                                arena_map.tiles.push(BackgroundTile {
+                                    image,
+                                    position: (top_left + bottom_right) / 2.0,
+                                    size: tile_size * scale,
+                                    depth,
+                                });
+                            }
+                        }
+                        if let Some(terrain) = terrain {
+                            arena_map.terrain.push(TerrainArea::covering(
+                                terrain,
+                                top_left,
+                                bottom_right,
+                            ));
+                        }
+                    }
+                }
+                "objectgroup" => {
+                    for object in &layer.objects {
+                        let center = to_arena(Vec2::new(
+                            object.x + object.width / 2.0,
+                            object.y + object.height / 2.0,
+                        ));
+                        match layer.name.as_str() {
+                            "spawns" => arena_map.spawn_points.push(SpawnPoint {
+                                position: center,
+                                team: object
+                                    .property("team")
+                                    .and_then(Value::as_u64)
+                                    .map(|team| Team(team as u32)),
+                            }),
+                            "items" => match object.property("item").and_then(Value::as_str) {
+                                Some(item) => arena_map.item_spawners.push(ItemSpawnerDefinition {
+                                    position: center,
+                                    item: item.to_string(),
+                                    interval: object
+                                        .property("interval")
+                                        .and_then(Value::as_f64)
+                                        .map(|interval| interval as f32),
+                                }),
+                                None => println!(
+                                    "Item spawner {} has no item property, leaving it out.",
+                                    object.id
+                                ),
+                            },
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {} // image layers aren't supported
+            }
+        }
+        arena_map
+    }
+
+    /// Image of a tile, None when none of the tilesets has it
+    /// * `gid` - id of the tile in the map, without the flip flags
+    fn tile_image(&self, gid: u32) -> Option<TileImage> {
+        let tileset = self
+            .tilesets
+            .iter()
+            .filter(|tileset| tileset.firstgid <= gid)
+            .max_by_key(|tileset| tileset.firstgid)?;
+        let local_id = gid - tileset.firstgid;
+
+        match &tileset.image {
+            Some(image) => {
+                let columns = tileset.columns.max(1);
+                Some(TileImage::Sheet {
+                    image: image.clone(),
+                    tile_size: Vec2::new(tileset.tilewidth as f32, tileset.tileheight as f32),
+                    columns,
+                    rows: tileset.tilecount.div_ceil(columns),
+                    spacing: tileset.spacing as f32,
+                    margin: tileset.margin as f32,
+                    index: local_id as usize,
+                })
+            }
+            None => tileset
+                .tiles
+                .iter()
+                .find(|tile| tile.id == local_id)
+                .and_then(|tile| tile.image.clone())
+                .map(TileImage::Single),
+        }
+    }
+}
+
+/// Lists the layers in drawing order, with the layers of group layers in place of the group
+fn flatten_layers<'a>(layers: &'a [TiledLayer], flattened: &mut Vec<&'a TiledLayer>) {
+    for layer in layers {
+        if layer.kind == "group" {
+            flatten_layers(&layer.layers, flattened);
+        } else {
+            flattened.push(layer);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TiledLayer {
+    /// tilelayer, objectgroup, imagelayer or group
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "visible_by_default")]
+    pub visible: bool,
+    /// Width in tiles of a tile layer, the map's width if left out
+    #[serde(default)]
+    pub width: Option<u32>,
+    /// Tile ids of a tile layer, row by row, 0 for no tile
+    #[serde(default)]
+    pub data: Vec<u32>,
+    #[serde(default)]
+    pub objects: Vec<TiledObject>,
+    /// Layers of a group layer
+    #[serde(default)]
+    pub layers: Vec<TiledLayer>,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+impl TiledLayer {
+    pub fn property(&self, name: &str) -> Option<&Value> {
+        TiledProperty::find(&self.properties, name)
+    }
+}
+
+fn visible_by_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct TiledObject {
+    #[serde(default)]
+    pub id: u32,
+    /// Position of the top left corner in pixels
+    pub x: f32,
+    pub y: f32,
+    /// Size in pixels, 0 for a point
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default)]
+    pub properties: Vec<TiledProperty>,
+}
+
+impl TiledObject {
+    pub fn property(&self, name: &str) -> Option<&Value> {
+        TiledProperty::find(&self.properties, name)
+    }
+}
+
+/// A custom property of a layer or object
+#[derive(Deserialize)]
+pub struct TiledProperty {
+    pub name: String,
+    pub value: Value,
+}
+
+impl TiledProperty {
+    fn find<'a>(properties: &'a [TiledProperty], name: &str) -> Option<&'a Value> {
+        properties
+            .iter()
+            .find(|property| property.name == name)
+            .map(|property| &property.value)
+    }
+}
+
+#[derive(Default, Deserialize)]
+pub struct TiledTileset {
+    /// Tile id in the map of the first tile of the tileset
+    #[serde(default)]
+    pub firstgid: u32,
+    /// File of an external tileset, relative to the map
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub tilewidth: u32,
+    #[serde(default)]
+    pub tileheight: u32,
+    #[serde(default)]
+    pub tilecount: u32,
+    #[serde(default)]
+    pub columns: u32,
+    #[serde(default)]
+    pub spacing: u32,
+    #[serde(default)]
+    pub margin: u32,
+    /// Image with all the tiles, None for a collection of images
+    #[serde(default)]
+    pub image: Option<String>,
+    /// Tiles with their own image in a collection of images
+    #[serde(default)]
+    pub tiles: Vec<TiledTile>,
+}
+
+impl TiledTileset {
+    /// Makes the image paths relative to the assets folder, like the AssetServer wants them
+    /// * `dir` - folder of the file the tileset is in, relative to the assets folder
+    fn resolve_images(&mut self, dir: &str) {
+        if let Some(image) = &mut self.image {
+            *image = join_path(dir, image);
+        }
+        for tile in self.tiles.iter_mut() {
+            if let Some(image) = &mut tile.image {
+                *image = join_path(dir, image);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TiledTile {
+    pub id: u32,
+    #[serde(default)]
+    pub image: Option<String>,
+}
+
+/// Reads .tmx maps and .tsx tilesets into the same structs as their JSON counterparts
+mod tmx {
+    use roxmltree::{Document, Node};
+    use serde_json::Value;
+
+    use super::*;
+
+    pub fn parse_map(contents: &str) -> Result<TiledMap, String> {
+        let document = Document::parse(contents).map_err(|err| err.to_string())?;
+        let map = document.root_element();
+        Ok(TiledMap {
+            width: number(&map, "width")?,
+            height: number(&map, "height")?,
+            tilewidth: number(&map, "tilewidth")?,
+            tileheight: number(&map, "tileheight")?,
+            layers: layers(&map)?,
+            tilesets: children(&map, "tileset")
+                .map(|tileset| tileset_from(&tileset))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn parse_tileset(contents: &str) -> Result<TiledTileset, String> {
+        let document = Document::parse(contents).map_err(|err| err.to_string())?;
+        tileset_from(&document.root_element())
+    }
+
+    fn tileset_from(tileset: &Node) -> Result<TiledTileset, String> {
+        let optional = |name: &str| number(tileset, name).unwrap_or(0);
+        Ok(TiledTileset {
+            firstgid: optional("firstgid"),
+            source: tileset.attribute("source").map(String::from),
+            tilewidth: optional("tilewidth"),
+            tileheight: optional("tileheight"),
+            tilecount: optional("tilecount"),
+            columns: optional("columns"),
+            spacing: optional("spacing"),
+            margin: optional("margin"),
+            image: image_of(tileset),
+            tiles: children(tileset, "tile")
+                .map(|tile| {
+                    Ok(TiledTile {
+                        id: number(&tile, "id")?,
+                        image: image_of(&tile),
+                    })
+                })
+                .collect::<Result<_, String>>()?,
+        })
+    }
+
+    fn layers(parent: &Node) -> Result<Vec<TiledLayer>, String> {
+        parent
+            .children()
+            .filter(Node::is_element)
+            .filter_map(|node| {
+                let kind = match node.tag_name().name() {
+                    "layer" => "tilelayer",
+                    "objectgroup" => "objectgroup",
+                    "group" => "group",
+                    _ => return None, // tilesets, properties, image layers, ...
+                };
+                Some(layer_from(&node, kind))
+            })
+            .collect()
+    }
+
+    fn layer_from(layer: &Node, kind: &str) -> Result<TiledLayer, String> {
+        let data = match children(layer, "data").next() {
+            Some(data) if data.attribute("encoding") == Some("csv") => data
+                .text()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|gid| !gid.is_empty())
+                .map(|gid| gid.parse::<u32>().map_err(|err| err.to_string()))
+                .collect::<Result<_, _>>()?,
+            Some(_) => {
+                return Err(format!(
+                    "Layer {} should be stored as CSV.",
+                    layer.attribute("name").unwrap_or_default()
+                ))
+            }
+            None => Vec::new(),
+        };
+
+        Ok(TiledLayer {
+            kind: kind.to_string(),
+            name: layer.attribute("name").unwrap_or_default().to_string(),
+            visible: layer.attribute("visible") != Some("0"),
+            width: number(layer, "width").ok(),
+            data,
+            objects: children(layer, "object")
+                .map(|object| {
+                    Ok(TiledObject {
+                        id: number(&object, "id").unwrap_or(0),
+                        x: number(&object, "x")?,
+                        y: number(&object, "y")?,
+                        width: number(&object, "width").unwrap_or(0.0),
+                        height: number(&object, "height").unwrap_or(0.0),
+                        properties: properties(&object),
+                    })
+                })
+                .collect::<Result<_, String>>()?,
+            layers: layers(layer)?,
+            properties: properties(layer),
+        })
+    }
+
+    fn properties(node: &Node) -> Vec<TiledProperty> {
+        children(node, "properties")
+            .flat_map(|properties| children(&properties, "property").collect::<Vec<_>>())
+            .filter_map(|property| {
+                let name = property.attribute("name")?.to_string();
+                // long strings are stored as the text of the property instead
+                let text = property
+                    .attribute("value")
+                    .or_else(|| property.text())
+                    .unwrap_or_default();
+                let value = match property.attribute("type").unwrap_or("string") {
+                    "int" => text.parse::<i64>().map(Value::from).ok()?,
+                    "float" => text.parse::<f64>().map(Value::from).ok()?,
+                    "bool" => Value::from(text == "true"),
+                    _ => Value::from(text),
+                };
+                Some(TiledProperty { name, value })
+            })
+            .collect()
+    }
+
+    fn image_of(node: &Node) -> Option<String> {
+        children(node, "image")
+            .next()
+            .and_then(|image| image.attribute("source"))
+            .map(String::from)
+    }
+
+    fn children<'a, 'input>(
+        node: &Node<'a, 'input>,
+        name: &'a str,
+    ) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        node.children()
+            .filter(move |child| child.has_tag_name(name))
+    }
+
+    fn number<T: std::str::FromStr>(node: &Node, name: &str) -> Result<T, String> {
+        node.attribute(name)
+            .ok_or_else(|| format!("<{}> has no {}", node.tag_name().name(), name))?
+            .parse::<T>()
+            .map_err(|_| format!("{} of <{}> is not a number", name, node.tag_name().name()))
+    }
+}
@@ -0,0 +1,606 @@
+//! Tournament mode: a roster of gladiators fights a bracket of one-on-one duels. Only the
+//! two duelists are in the arena at a time. The winner of each duel keeps the XP and
+//! levels they earned for their next duel, and the bracket is printed between duels. The
+//! TournamentStandings resource always holds the bracket as it stands, it is shown in a
+//! window of its own with the `inspector` feature.
+//!
+//! Rosters live in assets/tournaments/. Run one with:
+//!  cargo run -- --tournament classic
+//! and add --headless to just play it out and print the winner. A roster that can't be
+//! loaded is replaced by DEFAULT_TOURNAMENT_ROSTER.
+
+use std::{collections::VecDeque, fmt};
+
+use bevy::{app::AppExit, prelude::*};
+use serde::Deserialize;
+
+use crate::{
+    engagements::*,
+    gladiator::{gladiator::*, gladiator_combat::*, gladiator_components::*, gladiator_events::*},
+    helper_functions::*,
+    *, // game_lib
+};
+
+///////////////////////////////////////////////////////
+/// Plugin
+///////////////////////////////////////////////////////
+
+pub struct TournamentPlugin {
+    /// Name of the roster file in assets/tournaments/, without the extension
+    pub roster: String,
+    /// Close the app once the tournament has a champion, used when running headless
+    pub exit_when_done: bool,
+}
+
+impl Plugin for TournamentPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TournamentStandings>()
+            .init_resource::<TournamentStandings>()
+            .insert_resource(ArenaPopulation {
+                random_gladiators: false,
+                player: false,
+                ..default()
+            });
+
+        let roster = Roster::load(&self.roster).or_else(|err| {
+            println!(
+                "{} Playing the {} roster instead.",
+                err, DEFAULT_TOURNAMENT_ROSTER
+            );
+            Roster::load(DEFAULT_TOURNAMENT_ROSTER)
+        });
+        let roster = match roster {
+            Ok(roster) => roster,
+            Err(err) => {
+                println!("{} There is no tournament to play.", err);
+                app.add_startup_system(exit_app);
+                return;
+            }
+        };
+
+        app.insert_resource(Tournament::new(roster, self.exit_when_done))
+            .add_system(run_tournament)
+            .add_system(duel_results.after(gladiator_death_handler))
+            .add_system(update_standings.after(run_tournament).after(duel_results));
+
+        #[cfg(feature = "inspector")]
+        app.add_plugin(bevy_inspector_egui::quick::ResourceInspectorPlugin::<
+            TournamentStandings,
+        >::default());
+    }
+}
+
+///////////////////////////////////////////////////////
+/// Functions
+///////////////////////////////////////////////////////
+
+/// Waits out the break between duels, then sends in the next two duelists, or crowns
+/// the champion once the bracket is decided.
+fn run_tournament(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut tournament: ResMut<Tournament>,
+    mut ev_exit: EventWriter<AppExit>,
+) {
+    let tournament = &mut *tournament;
+    let intermission = match &mut tournament.state {
+        TournamentState::Intermission(timer) => timer,
+        _ => return,
+    };
+
+    intermission.tick(time.delta());
+    if !intermission.finished() {
+        return;
+    }
+
+    match tournament.bracket.next_match() {
+        Some((a, b)) => {
+            println!(
+                "{} vs {}!",
+                tournament.bracket.entrants[a].name, tournament.bracket.entrants[b].name
+            );
+            spawn_duel(
+                (a, b),
+                &tournament.bracket,
+                &mut commands,
+                &asset_server,
+                &mut texture_atlases,
+            );
+            tournament.state = TournamentState::Dueling(a, b);
+        }
+        None => {
+            println!("{}", tournament.bracket);
+            if let Some(champion) = tournament.bracket.champion() {
+                println!(
+                    "{} is the tournament champion!",
+                    tournament.bracket.entrants[champion].name
+                );
+            }
+            tournament.state = TournamentState::Finished;
+
+            if tournament.exit_when_done {
+                ev_exit.send(AppExit);
+            }
+        }
+    }
+}
+
+/// Puts both duelists in the middle of the arena, already engaged with each other so
+/// nobody wanders off.
+fn spawn_duel(
+    (a, b): (usize, usize),
+    bracket: &Bracket,
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+) {
+    let locations = [
+        Vec2::new(-DUEL_DISTANCE / 2., 0.),
+        Vec2::new(DUEL_DISTANCE / 2., 0.),
+    ];
+
+    let duelists: Vec<Entity> = [a, b]
+        .iter()
+        .zip(locations)
+        .map(|(&entrant_idx, location)| {
+            let entrant = &bracket.entrants[entrant_idx];
+            let gladiator_spawn = GladiatorSpawn {
+                class: Some(entrant.class),
+                level: entrant.level,
+                xp: entrant.xp,
+                ..default()
+            };
+
+            let duelist = spawn_one_gladiator(
+                location,
+                entrant_idx,
+                &gladiator_spawn,
+                commands,
+                asset_server,
+                texture_atlases,
+            );
+            commands.entity(duelist).insert((
+                Duelist(entrant_idx),
+                Details {
+                    name: entrant.name.clone(),
+                    motivation: "Glory".to_string(),
+                },
+            ));
+            duelist
+        })
+        .collect();
+
+    commands.entity(duelists[0]).insert(Engagement {
+        target: duelists[1],
+    });
+    commands.entity(duelists[1]).insert(Engagement {
+        target: duelists[0],
+    });
+}
+
+/// Records the outcome of the current duel in the bracket. The duel is decided by which
+/// duelist died, whoever got credit for the kill, since a bleed or poison can be the end of
+/// a duelist too. Runs after the death handler so that the XP for the kill has already been
+/// awarded and can be carried over.
+fn duel_results(
+    mut commands: Commands,
+    mut ev_death: EventReader<DeathEvent>,
+    mut tournament: ResMut<Tournament>,
+    query: Query<(Entity, &Duelist, &Level)>,
+) {
+    let deaths: Vec<&DeathEvent> = ev_death.iter().collect();
+    for event in &deaths {
+        // if both duelists go down in the same frame, the first death decides the duel
+        let (a, b) = match tournament.state {
+            TournamentState::Dueling(a, b) => (a, b),
+            _ => continue,
+        };
+        let loser = match query.get(event.slain) {
+            Ok((_, loser, _)) => loser.0,
+            Err(_) => continue,
+        };
+        let winner = if loser == a { b } else { a };
+
+        if let Some((victor, _, level)) = query.iter().find(|(_, duelist, _)| duelist.0 == winner) {
+            let entrant = &mut tournament.bracket.entrants[winner];
+            entrant.level = level.level;
+            entrant.xp = level.xp;
+
+            // the loser is despawned by the death handler, and so is the winner when it died
+            // in the same frame. Otherwise the winner waits for the next round.
+            if !deaths.iter().any(|death| death.slain == victor) {
+                commands.entity(victor).despawn();
+            }
+        }
+        println!(
+            "{} wins the duel!",
+            tournament.bracket.entrants[winner].name
+        );
+
+        tournament.bracket.record(winner, loser);
+        println!("{}", tournament.bracket);
+        tournament.state = TournamentState::intermission();
+    }
+}
+
+/// Copies the bracket into the TournamentStandings whenever it changed
+fn update_standings(tournament: Res<Tournament>, mut standings: ResMut<TournamentStandings>) {
+    if tournament.is_changed() {
+        *standings = TournamentStandings::from(&tournament.bracket);
+    }
+}
+
+fn exit_app(mut ev_exit: EventWriter<AppExit>) {
+    ev_exit.send(AppExit);
+}
+
+///////////////////////////////////////////////////////
+/// Structs and Enums
+///////////////////////////////////////////////////////
+
+/// Marks a gladiator in the arena as the entrant at this index of the bracket
+#[derive(Component)]
+pub struct Duelist(pub usize);
+
+#[derive(Resource)]
+pub struct Tournament {
+    pub bracket: Bracket,
+    pub state: TournamentState,
+    pub exit_when_done: bool,
+}
+
+impl Tournament {
+    pub fn new(roster: Roster, exit_when_done: bool) -> Self {
+        Self {
+            bracket: Bracket::new(roster),
+            state: TournamentState::intermission(),
+            exit_when_done,
+        }
+    }
+}
+
+pub enum TournamentState {
+    /// Time left until the next duel
+    Intermission(Timer),
+    /// Indices of the two entrants that are fighting
+    Dueling(usize, usize),
+    Finished,
+}
+
+impl TournamentState {
+    fn intermission() -> Self {
+        Self::Intermission(Timer::from_seconds(DUEL_INTERMISSION, TimerMode::Once))
+    }
+}
+
+/// Contents of a roster file in assets/tournaments/
+#[derive(Deserialize)]
+pub struct Roster {
+    pub elimination: Elimination,
+    pub entrants: Vec<Entrant>,
+}
+
+impl Roster {
+    /// Reads a roster from assets/tournaments/
+    /// * `name` - name of the roster file, without the extension
+    pub fn load(name: &str) -> Result<Self, String> {
+        load_data_file(&format!("{}{}.ron", TOURNAMENTS_PATH, name))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Elimination {
+    Single,
+    Double,
+}
+
+impl Elimination {
+    /// How many duels an entrant can lose before they are out of the tournament
+    pub fn max_losses(&self) -> usize {
+        match self {
+            Self::Single => 1,
+            Self::Double => 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Entrant {
+    pub name: String,
+    pub class: Class,
+    #[serde(default = "first_level")]
+    pub level: usize,
+    #[serde(default)]
+    pub xp: f32,
+    #[serde(skip)]
+    pub wins: usize,
+    #[serde(skip)]
+    pub losses: usize,
+}
+
+fn first_level() -> usize {
+    1
+}
+
+/// Keeps track of who still has to fight whom. Entrants are grouped by how many duels
+/// they have lost, and each round pairs entrants with the same number of losses. In a
+/// double elimination bracket that gives a winners bracket and a losers bracket, and
+/// their two survivors meet in the final. If the finalist from the losers bracket wins,
+/// both have one loss and the final is played again.
+pub struct Bracket {
+    pub elimination: Elimination,
+    pub entrants: Vec<Entrant>,
+    pub round: usize,
+    /// Duels left to play this round, as pairs of entrant indices
+    pub pending: VecDeque<(usize, usize)>,
+    /// Every duel played so far as (round, winner, loser)
+    pub results: Vec<(usize, usize, usize)>,
+}
+
+impl Bracket {
+    pub fn new(roster: Roster) -> Self {
+        Self {
+            elimination: roster.elimination,
+            entrants: roster.entrants,
+            round: 0,
+            pending: VecDeque::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Indices of the entrants that have not been eliminated
+    pub fn remaining(&self) -> Vec<usize> {
+        (0..self.entrants.len())
+            .filter(|&idx| self.entrants[idx].losses < self.elimination.max_losses())
+            .collect()
+    }
+
+    /// The last entrant standing, once the bracket is decided
+    pub fn champion(&self) -> Option<usize> {
+        match self.remaining().as_slice() {
+            [champion] => Some(*champion),
+            _ => None,
+        }
+    }
+
+    /// Returns the next duel to play, starting a new round when the current one is done.
+    /// Returns None once there is a champion.
+    pub fn next_match(&mut self) -> Option<(usize, usize)> {
+        if self.pending.is_empty() {
+            self.start_round();
+        }
+        self.pending.pop_front()
+    }
+
+    /// Writes the outcome of a duel into the bracket
+    pub fn record(&mut self, winner: usize, loser: usize) {
+        self.entrants[winner].wins += 1;
+        self.entrants[loser].losses += 1;
+        self.results.push((self.round, winner, loser));
+    }
+
+    fn start_round(&mut self) {
+        let remaining = self.remaining();
+        if remaining.len() < 2 {
+            return;
+        }
+        self.round += 1;
+
+        // group the remaining entrants by losses, odd ones out get a bye this round
+        let mut pools = vec![Vec::new(); self.elimination.max_losses()];
+        for idx in remaining {
+            pools[self.entrants[idx].losses].push(idx);
+        }
+
+        let n_pools_left = pools.iter().filter(|pool| !pool.is_empty()).count();
+        if pools.iter().all(|pool| pool.len() <= 1) && n_pools_left == 2 {
+            // the final, winners bracket vs losers bracket
+            let finalists: Vec<usize> = pools.into_iter().flatten().collect();
+            self.pending.push_back((finalists[0], finalists[1]));
+            return;
+        }
+
+        for pool in pools {
+            for pair in pool.chunks_exact(2) {
+                self.pending.push_back((pair[0], pair[1]));
+            }
+        }
+    }
+}
+
+impl fmt::Display for Bracket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "==================== Round {} ====================",
+            self.round
+        )?;
+        for &(round, winner, loser) in &self.results {
+            writeln!(
+                f,
+                "  round {}: {} defeated {}",
+                round, self.entrants[winner].name, self.entrants[loser].name
+            )?;
+        }
+        for &(a, b) in &self.pending {
+            writeln!(
+                f,
+                "  up next: {} vs {}",
+                self.entrants[a].name, self.entrants[b].name
+            )?;
+        }
+
+        writeln!(f, "  standings:")?;
+        for entrant in &self.entrants {
+            let status = if entrant.losses >= self.elimination.max_losses() {
+                "out"
+            } else {
+                "in"
+            };
+            writeln!(
+                f,
+                "    {:<12} {:?} lvl {} | {}W {}L | {}",
+                entrant.name, entrant.class, entrant.level, entrant.wins, entrant.losses, status
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The bracket as it stands, for anything that wants to show it
+#[derive(Resource, Default, Reflect)]
+#[reflect(Resource)]
+pub struct TournamentStandings {
+    pub round: usize,
+    /// Every duel played so far, e.g. "round 1: Brutus defeated Orion"
+    pub results: Vec<String>,
+    /// Duels left to play this round, e.g. "Diana vs Circe"
+    pub up_next: Vec<String>,
+    pub entrants: Vec<Standing>,
+    pub champion: Option<String>,
+}
+
+#[derive(Default, Reflect, FromReflect)]
+pub struct Standing {
+    pub name: String,
+    pub class: Class,
+    pub level: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub eliminated: bool,
+}
+
+impl From<&Bracket> for TournamentStandings {
+    fn from(bracket: &Bracket) -> Self {
+        let name = |idx: usize| bracket.entrants[idx].name.clone();
+        Self {
+            round: bracket.round,
+            results: bracket
+                .results
+                .iter()
+                .map(|&(round, winner, loser)| {
+                    format!("round {}: {} defeated {}", round, name(winner), name(loser))
+                })
+                .collect(),
+            up_next: bracket
+                .pending
+                .iter()
+                .map(|&(a, b)| format!("{} vs {}", name(a), name(b)))
+                .collect(),
+            entrants: bracket
+                .entrants
+                .iter()
+                .map(|entrant| Standing {
+                    name: entrant.name.clone(),
+                    class: entrant.class,
+                    level: entrant.level,
+                    wins: entrant.wins,
+                    losses: entrant.losses,
+                    eliminated: entrant.losses >= bracket.elimination.max_losses(),
+                })
+                .collect(),
+            champion: bracket.champion().map(name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bracket(elimination: Elimination, n_entrants: usize) -> Bracket {
+        let entrants = (0..n_entrants)
+            .map(|idx| Entrant {
+                name: format!("Entrant {}", idx),
+                class: Class::Fighter,
+                level: 1,
+                xp: 0.0,
+                wins: 0,
+                losses: 0,
+            })
+            .collect();
+        Bracket::new(Roster {
+            elimination,
+            entrants,
+        })
+    }
+
+    /// Plays a duel that the bracket has up next, the first entrant of the pair wins
+    /// unless `upset` says otherwise
+    fn play(bracket: &mut Bracket, expected: (usize, usize), upset: bool) {
+        let (a, b) = bracket
+            .next_match()
+            .expect("There should be a duel to play.");
+        assert_eq!((a, b), expected);
+        if upset {
+            bracket.record(b, a);
+        } else {
+            bracket.record(a, b);
+        }
+    }
+
+    #[test]
+    fn the_odd_one_out_gets_a_bye() {
+        let mut bracket = bracket(Elimination::Single, 3);
+
+        play(&mut bracket, (0, 1), false);
+        assert_eq!(bracket.round, 1);
+        // entrant 2 sat out the first round
+        play(&mut bracket, (0, 2), false);
+        assert_eq!(bracket.round, 2);
+
+        assert_eq!(bracket.next_match(), None);
+        assert_eq!(bracket.champion(), Some(0));
+    }
+
+    #[test]
+    fn single_elimination_knocks_out_the_losers() {
+        let mut bracket = bracket(Elimination::Single, 4);
+
+        play(&mut bracket, (0, 1), false);
+        play(&mut bracket, (2, 3), true);
+        assert_eq!(bracket.remaining(), vec![0, 3]);
+        play(&mut bracket, (0, 3), true);
+
+        assert_eq!(bracket.next_match(), None);
+        assert_eq!(bracket.champion(), Some(3));
+        assert_eq!(bracket.results, vec![(1, 0, 1), (1, 3, 2), (2, 3, 0)]);
+    }
+
+    #[test]
+    fn losers_get_a_second_chance_in_the_losers_bracket() {
+        let mut bracket = bracket(Elimination::Double, 4);
+
+        play(&mut bracket, (0, 1), false);
+        play(&mut bracket, (2, 3), false);
+        // the winners and the losers of the first round meet among themselves
+        play(&mut bracket, (0, 2), false);
+        play(&mut bracket, (1, 3), false);
+        assert_eq!(bracket.remaining(), vec![0, 1, 2]);
+        // the winners bracket has one entrant left, who waits for the losers bracket
+        play(&mut bracket, (1, 2), false);
+        assert_eq!(bracket.remaining(), vec![0, 1]);
+
+        // the final, winners bracket vs losers bracket
+        play(&mut bracket, (0, 1), false);
+        assert_eq!(bracket.next_match(), None);
+        assert_eq!(bracket.champion(), Some(0));
+    }
+
+    #[test]
+    fn the_final_is_played_again_when_the_losers_bracket_wins_it() {
+        let mut bracket = bracket(Elimination::Double, 2);
+
+        play(&mut bracket, (0, 1), false);
+        // entrant 1 comes back through the losers bracket and wins the final
+        play(&mut bracket, (0, 1), true);
+        assert_eq!(bracket.champion(), None);
+
+        play(&mut bracket, (0, 1), true);
+        assert_eq!(bracket.next_match(), None);
+        assert_eq!(bracket.champion(), Some(1));
+        assert_eq!(bracket.round, 3);
+    }
+}
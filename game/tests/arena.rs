@@ -0,0 +1,81 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    arena::{terrain_step, Terrain},
+    gladiator::gladiator::Class,
+    grid::{ArenaGrid, GridLocation},
+    SAND_SPEED_MODIFIER,
+};
+
+#[test]
+fn sand_slows_gladiators_down() {
+    let mut arena_grid = ArenaGrid::default();
+    let location = GridLocation { x: 2, y: 2 };
+    arena_grid.set_terrain(location, Terrain::Sand);
+
+    let step = terrain_step(
+        &arena_grid,
+        ArenaGrid::center_of(location),
+        Vec2::new(2.0, 0.0),
+    );
+
+    assert_eq!(step, Vec2::new(2.0 * SAND_SPEED_MODIFIER, 0.0));
+}
+
+#[test]
+fn gladiators_sidestep_a_pillar_they_walk_into() {
+    let mut arena_grid = ArenaGrid::default();
+    let pillar = GridLocation { x: 1, y: 0 };
+    arena_grid.set_terrain(pillar, Terrain::Pillar);
+
+    // right at the edge of the pillar's grid location, heading straight for it
+    let position = ArenaGrid::center_of(GridLocation { x: 0, y: 0 }) + Vec2::new(14.0, 0.0);
+    let step = terrain_step(&arena_grid, position, Vec2::new(2.0, 0.0));
+
+    let next = position + step;
+    assert_ne!(ArenaGrid::get_grid_location(next.x, next.y), pillar);
+    assert!(
+        step.x == 0.0 && step.y != 0.0,
+        "should sidestep along the pillar"
+    );
+}
+
+#[test]
+fn gladiators_can_walk_out_of_a_blocked_location() {
+    let mut arena_grid = ArenaGrid::default();
+    let water = GridLocation { x: 0, y: 0 };
+    arena_grid.set_terrain(water, Terrain::Water);
+
+    let step = terrain_step(
+        &arena_grid,
+        ArenaGrid::center_of(water),
+        Vec2::new(0.0, 2.0),
+    );
+
+    assert_eq!(step, Vec2::new(0.0, 2.0));
+}
+
+#[test]
+fn walled_in_gladiators_stay_put() {
+    let mut arena = TestArena::battle();
+    let cell = GridLocation { x: 5, y: 5 };
+    {
+        let mut arena_grid = arena.app.world.resource_mut::<ArenaGrid>();
+        for location in ArenaGrid::locations_in_ring(cell, 1) {
+            arena_grid.set_terrain(location, Terrain::Wall);
+        }
+    }
+    let gladiator = arena.spawn_gladiator(ArenaGrid::center_of(cell), Class::Fighter, None);
+
+    for _ in 0..120 {
+        arena.step();
+        let translation = arena.get::<Transform>(gladiator).unwrap().translation;
+        assert_eq!(
+            ArenaGrid::get_grid_location(translation.x, translation.y),
+            cell
+        );
+    }
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    career::{Career, CareerGladiator, CareerPlugin, CareerSave, CareerState},
+    gladiator::{
+        gladiator::Class,
+        gladiator_components::{Gladiator, Gold, Level},
+    },
+    team::TeamPlugin,
+    GOLD_FOR_SURVIVING,
+};
+
+/// A career battle of two roster gladiators, saved to a file of its own
+fn career_battle(test_name: &str) -> (TestArena, PathBuf) {
+    let save_path =
+        std::env::temp_dir().join(format!("career_{}_{}.ron", test_name, std::process::id()));
+    let mut save = CareerSave::default();
+    save.recruit("Spartacus", Class::Fighter);
+    save.recruit("Crixus", Class::Archer);
+    save.save(save_path.to_str().unwrap()).unwrap();
+
+    let mut arena = TestArena::battle()
+        .with_plugin(TeamPlugin)
+        .with_plugin(CareerPlugin {
+            exit_when_done: false,
+            save_path: save_path.to_str().unwrap().to_string(),
+        });
+    // one step to spawn the battle, one for the teams to be counted
+    arena.steps(2);
+    (arena, save_path)
+}
+
+/// The gladiators in the arena, the roster's and the strangers'
+fn gladiators(arena: &mut TestArena) -> (Vec<Entity>, Vec<Entity>) {
+    let mut query = arena
+        .app
+        .world
+        .query_filtered::<(Entity, Option<&CareerGladiator>), With<Gladiator>>();
+    let (roster, strangers): (Vec<_>, Vec<_>) = query
+        .iter(&arena.app.world)
+        .partition(|(_, career_gladiator)| career_gladiator.is_some());
+    (
+        roster.into_iter().map(|(entity, _)| entity).collect(),
+        strangers.into_iter().map(|(entity, _)| entity).collect(),
+    )
+}
+
+fn career_state(arena: &TestArena) -> CareerState {
+    *arena.resource::<State<CareerState>>().current()
+}
+
+#[test]
+fn survivors_carry_their_level_xp_and_gold_back_to_the_roster() {
+    let (mut arena, save_path) = career_battle("survivors");
+    let (roster, strangers) = gladiators(&mut arena);
+    assert_eq!((roster.len(), strangers.len()), (2, 2));
+
+    for entity in &roster {
+        let mut level = arena.get_mut::<Level>(*entity).unwrap();
+        level.level = 4;
+        level.xp = 2.5;
+        arena.get_mut::<Gold>(*entity).unwrap().value = 30;
+    }
+    for stranger in strangers {
+        arena.app.world.despawn(stranger);
+    }
+    arena.steps(3);
+
+    assert_eq!(career_state(&arena), CareerState::Shop);
+    let save = &arena.resource::<Career>().save;
+    assert_eq!(save.battles, 1);
+    for roster_gladiator in &save.roster {
+        assert_eq!(roster_gladiator.level, 4);
+        assert_eq!(roster_gladiator.xp, 2.5);
+        assert_eq!(roster_gladiator.gold, 30 + GOLD_FOR_SURVIVING);
+        assert_eq!((roster_gladiator.wins, roster_gladiator.losses), (1, 0));
+    }
+    let saved = CareerSave::load(save_path.to_str().unwrap());
+    std::fs::remove_file(&save_path).unwrap();
+    assert_eq!(saved.as_ref(), Ok(save));
+}
+
+#[test]
+fn the_career_is_over_when_the_whole_roster_falls() {
+    let (mut arena, save_path) = career_battle("fallen");
+    let (roster, _) = gladiators(&mut arena);
+
+    for entity in roster {
+        arena.app.world.despawn(entity);
+    }
+    arena.steps(3);
+    std::fs::remove_file(&save_path).unwrap();
+
+    assert_eq!(career_state(&arena), CareerState::GameOver);
+    let save = &arena.resource::<Career>().save;
+    assert!(save.roster.is_empty());
+    assert_eq!(save.fallen.len(), 2);
+    assert!(save.fallen.iter().all(|fallen| fallen.losses == 1));
+}
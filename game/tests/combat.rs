@@ -0,0 +1,173 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    engagements::Engagement,
+    gladiator::{
+        gladiator::Class,
+        gladiator_components::{Attack, DamageType, Gold, Health, Level},
+        gladiator_equipment::BaseStats,
+        gladiator_events::{AttackEvent, DeathEvent},
+    },
+    grid::ArenaGrid,
+    GOLD_PER_KILL,
+};
+
+/// An attack that always hits and kills whoever it hits
+const LETHAL_ATTACK: Attack = Attack {
+    damage: 10_000.0,
+    damage_type: DamageType::Slash,
+    accuracy: 10.0,
+    crit_chance: 0.0,
+};
+
+/// Two fighters in the middle of the arena that are engaged with each other
+fn engaged_pair(arena: &mut TestArena) -> (Entity, Entity) {
+    let a = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    let b = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    let engaged = arena.run_until(1.0, |world| {
+        world.get::<Engagement>(a).is_some() && world.get::<Engagement>(b).is_some()
+    });
+    assert!(engaged, "the two gladiators should have engaged");
+    (a, b)
+}
+
+fn lethal_attack(attacker: Entity, target: Entity) -> AttackEvent {
+    AttackEvent {
+        target,
+        attacker: Some(attacker),
+        attack: LETHAL_ATTACK,
+        on_hit: None,
+    }
+}
+
+#[test]
+fn engaged_gladiators_damage_each_other() {
+    let mut arena = TestArena::battle();
+    arena.record::<AttackEvent>();
+    let (a, b) = engaged_pair(&mut arena);
+
+    let wounded = |world: &World, gladiator: Entity| {
+        world
+            .get::<Health>(gladiator)
+            .map_or(true, |health| health.value < health.max)
+    };
+    let damaged = arena.run_until(10.0, |world| wounded(world, a) || wounded(world, b));
+
+    assert!(
+        damaged,
+        "at least one of the gladiators should have been hurt"
+    );
+    assert!(arena
+        .recorded::<AttackEvent>()
+        .iter()
+        .any(|attack| (attack.attacker, attack.target) == (Some(a), b)
+            || (attack.attacker, attack.target) == (Some(b), a)));
+}
+
+#[test]
+fn the_victor_gets_xp_and_gold_for_a_kill() {
+    let mut arena = TestArena::battle();
+    arena.record::<DeathEvent>();
+    let (a, b) = engaged_pair(&mut arena);
+    let (level_before, xp_before) = {
+        let level = arena.get::<Level>(a).unwrap();
+        (level.level, level.xp)
+    };
+    let gold_before = arena.get::<Gold>(a).unwrap().value;
+
+    arena.send_event(lethal_attack(a, b));
+    arena.steps(2);
+
+    assert!(!arena.exists(b), "the slain gladiator should be despawned");
+    let deaths = arena.recorded::<DeathEvent>();
+    assert_eq!(deaths.len(), 1);
+    assert_eq!(deaths[0].slain, b);
+    assert_eq!(deaths[0].victor, Some(a));
+
+    let level = arena.get::<Level>(a).unwrap();
+    assert!(level.level > level_before || level.xp > xp_before);
+    assert_eq!(
+        arena.get::<Gold>(a).unwrap().value,
+        gold_before + GOLD_PER_KILL
+    );
+    assert!(
+        arena.get::<Engagement>(a).is_none(),
+        "the victor should be free to find a new opponent"
+    );
+}
+
+#[test]
+fn a_level_up_makes_the_victor_stronger() {
+    let mut arena = TestArena::battle();
+    let (a, b) = engaged_pair(&mut arena);
+    // a veteran is worth enough XP for a level
+    arena.get_mut::<Level>(b).unwrap().level = 10;
+    let max_health_before = arena.get::<Health>(a).unwrap().max;
+    let damage_before = arena.get::<BaseStats>(a).unwrap().damage;
+
+    arena.send_event(lethal_attack(a, b));
+    arena.steps(2);
+
+    assert_eq!(arena.get::<Level>(a).unwrap().level, 2);
+    let growth = Level::stat_multiplier(2);
+    assert!(growth > 1.0);
+    assert_eq!(
+        arena.get::<Health>(a).unwrap().max,
+        max_health_before * growth
+    );
+    assert_eq!(
+        arena.get::<BaseStats>(a).unwrap().damage,
+        damage_before * growth
+    );
+}
+
+/// Both gladiators of an engagement dying in the same frame used to panic, because the
+/// victor of one death was the slain of the other
+#[test]
+fn two_gladiators_can_kill_each_other_in_the_same_frame() {
+    let mut arena = TestArena::battle();
+    arena.record::<DeathEvent>();
+    let (a, b) = engaged_pair(&mut arena);
+
+    arena.send_event(lethal_attack(a, b));
+    arena.send_event(lethal_attack(b, a));
+    arena.steps(2);
+
+    assert!(!arena.exists(a));
+    assert!(!arena.exists(b));
+    let deaths = arena.recorded::<DeathEvent>();
+    assert_eq!(deaths.len(), 2, "each gladiator only dies once");
+
+    // attacks still on their way from the dead don't bother anyone
+    arena.send_event(lethal_attack(a, b));
+    arena.steps(2);
+
+    let arena_grid = arena.resource::<ArenaGrid>();
+    assert!(arena_grid.location_of(a).is_none());
+    assert!(arena_grid.location_of(b).is_none());
+}
+
+#[test]
+fn several_gladiators_can_die_in_the_same_frame() {
+    let mut arena = TestArena::battle();
+    arena.record::<DeathEvent>();
+    let (a, b) = engaged_pair(&mut arena);
+    let c = arena.spawn_gladiator(Vec2::new(300.0, 200.0), Class::Fighter, None);
+
+    // one attacker kills two gladiators, one of which it is not even engaged with, while
+    // a second attack on the same gladiator comes in
+    arena.send_event(lethal_attack(a, b));
+    arena.send_event(lethal_attack(a, c));
+    arena.send_event(lethal_attack(a, b));
+    arena.steps(2);
+
+    assert!(arena.exists(a));
+    assert!(!arena.exists(b));
+    assert!(!arena.exists(c));
+    let deaths = arena.recorded::<DeathEvent>();
+    assert_eq!(deaths.len(), 2);
+    assert!(deaths.iter().all(|death| death.victor == Some(a)));
+}
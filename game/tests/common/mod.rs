@@ -0,0 +1,191 @@
+#![allow(dead_code)] // every test file uses a different part of the harness
+
+use std::ops::{Deref, DerefMut};
+
+use bevy::{
+    ecs::{event::Event, system::SystemState},
+    prelude::*,
+};
+
+use game_lib::{
+    engagements::EngagementManagerPlugin,
+    gladiator::{
+        gladiator::{spawn_one_gladiator, ArenaPopulation, Class, GladiatorPlugin, GladiatorSpawn},
+        gladiator_rolls::CombatRng,
+    },
+    grid::GridPlugin,
+    headless::{SteppedApp, FRAME},
+    team::Team,
+};
+
+/// Test harness that drives a headless App tick by tick (see SteppedApp). There is no
+/// window, renderer or real clock: every step advances the clock by exactly one frame, so
+/// the fixed timestep systems run as if the game was running at 60 FPS and a test plays
+/// out the same way every time.
+///
+///  let mut arena = TestArena::battle();
+///  let gladiator = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+///  arena.advance(1.0);
+///  assert!(arena.get::<Engagement>(gladiator).is_some());
+
+pub const TEST_SEED: u64 = 42;
+
+pub struct TestArena {
+    stepped: SteppedApp,
+    n_spawned: usize,
+}
+
+impl TestArena {
+    /// The headless App the game runs in and seeded combat rolls. Nothing is spawned at
+    /// startup, tests spawn their own gladiators.
+    pub fn headless() -> Self {
+        let mut stepped = SteppedApp::default();
+        stepped
+            .app
+            .insert_resource(CombatRng::new(TEST_SEED))
+            .insert_resource(ArenaPopulation {
+                random_gladiators: false,
+                player: false,
+                ..default()
+            });
+
+        Self {
+            stepped,
+            n_spawned: 0,
+        }
+    }
+
+    /// The plugins that make up a battle: the grid, engagements and the gladiators
+    pub fn battle() -> Self {
+        Self::headless()
+            .with_plugin(GridPlugin)
+            .with_plugin(EngagementManagerPlugin)
+            .with_plugin(GladiatorPlugin)
+    }
+
+    pub fn with_plugin(mut self, plugin: impl Plugin) -> Self {
+        self.stepped.app.add_plugin(plugin);
+        self
+    }
+
+    /// Spawns a gladiator the same way the game does and returns its Entity. It is in the
+    /// grid after the next step.
+    /// * `position` - exact starting position in pixels
+    /// * `class` - class of the gladiator
+    /// * `team` - team of the gladiator, None for a free-for-all
+    pub fn spawn_gladiator(&mut self, position: Vec2, class: Class, team: Option<Team>) -> Entity {
+        let mut system_state: SystemState<(
+            Commands,
+            Res<AssetServer>,
+            ResMut<Assets<TextureAtlas>>,
+        )> = SystemState::new(&mut self.stepped.app.world);
+        let (mut commands, asset_server, mut texture_atlases) =
+            system_state.get_mut(&mut self.stepped.app.world);
+
+        let gladiator_spawn = GladiatorSpawn {
+            class: Some(class),
+            team,
+            ..default()
+        };
+        let entity = spawn_one_gladiator(
+            position,
+            self.n_spawned,
+            &gladiator_spawn,
+            &mut commands,
+            &asset_server,
+            &mut texture_atlases,
+        );
+        system_state.apply(&mut self.stepped.app.world);
+
+        self.n_spawned += 1;
+        entity
+    }
+
+    /// Steps through the given amount of game time
+    pub fn advance(&mut self, seconds: f32) {
+        self.steps((seconds / FRAME.as_secs_f32()).ceil() as usize);
+    }
+
+    /// Steps until the condition holds, for at most the given amount of game time.
+    /// Returns whether the condition was met.
+    pub fn run_until(&mut self, max_seconds: f32, condition: impl Fn(&World) -> bool) -> bool {
+        let max_steps = (max_seconds / FRAME.as_secs_f32()).ceil() as usize;
+        for _ in 0..max_steps {
+            if condition(&self.stepped.app.world) {
+                return true;
+            }
+            self.step();
+        }
+        condition(&self.stepped.app.world)
+    }
+
+    pub fn exists(&self, entity: Entity) -> bool {
+        self.stepped.app.world.get_entity(entity).is_some()
+    }
+
+    pub fn get<C: Component>(&self, entity: Entity) -> Option<&C> {
+        self.stepped.app.world.get::<C>(entity)
+    }
+
+    pub fn get_mut<C: Component>(&mut self, entity: Entity) -> Option<Mut<'_, C>> {
+        self.stepped.app.world.get_mut::<C>(entity)
+    }
+
+    pub fn resource<R: Resource>(&self) -> &R {
+        self.stepped.app.world.resource::<R>()
+    }
+
+    /// Sends an event that the systems read during the next step
+    pub fn send_event<E: Event>(&mut self, event: E) {
+        self.stepped
+            .app
+            .world
+            .resource_mut::<Events<E>>()
+            .send(event);
+    }
+
+    /// Keeps every event of this type from now on, see recorded
+    pub fn record<E: Event + Clone>(&mut self) {
+        self.stepped
+            .app
+            .init_resource::<Recorded<E>>()
+            .add_system_to_stage(CoreStage::Last, record_events::<E>);
+    }
+
+    /// Every event of this type sent since record was called
+    pub fn recorded<E: Event + Clone>(&self) -> &[E] {
+        &self
+            .app
+            .world
+            .get_resource::<Recorded<E>>()
+            .expect("Events should be recorded before they are checked.")
+            .0
+    }
+}
+
+impl Deref for TestArena {
+    type Target = SteppedApp;
+
+    fn deref(&self) -> &SteppedApp {
+        &self.stepped
+    }
+}
+
+impl DerefMut for TestArena {
+    fn deref_mut(&mut self) -> &mut SteppedApp {
+        &mut self.stepped
+    }
+}
+
+#[derive(Resource)]
+struct Recorded<E>(Vec<E>);
+
+impl<E> Default for Recorded<E> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+fn record_events<E: Event + Clone>(mut events: EventReader<E>, mut recorded: ResMut<Recorded<E>>) {
+    recorded.0.extend(events.iter().cloned());
+}
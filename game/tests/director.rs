@@ -0,0 +1,174 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    arena::{ItemPickup, Terrain},
+    director::{
+        clear_hazards, ArenaDirector, DirectorPlugin, DirectorSchedule, Hazard, HazardEvent, Lion,
+        ScheduledHazard,
+    },
+    gladiator::{gladiator::Class, gladiator_events::DeathEvent},
+    grid::{ArenaGrid, GridLocation},
+};
+
+fn crowd_throw(count: usize) -> Hazard {
+    Hazard::CrowdThrow {
+        items: vec!["Gladius".to_string()],
+        count,
+    }
+}
+
+/// A battle with the director, but nothing on its schedule. Tests send the hazards.
+fn calm_arena() -> TestArena {
+    TestArena::battle().with_plugin(DirectorPlugin {
+        schedule: "calm".to_string(),
+    })
+}
+
+#[test]
+fn hazards_come_when_they_are_scheduled() {
+    let mut director = ArenaDirector::new(DirectorSchedule {
+        hazards: vec![
+            ScheduledHazard {
+                at: 10.0,
+                every: None,
+                hazard: crowd_throw(1),
+            },
+            ScheduledHazard {
+                at: 5.0,
+                every: Some(5.0),
+                hazard: crowd_throw(2),
+            },
+        ],
+    });
+
+    assert!(director.advance(4.0).is_empty());
+    assert_eq!(director.advance(1.0), vec![crowd_throw(2)]);
+    assert!(director.advance(4.0).is_empty());
+    // both are due, in the order of the schedule
+    assert_eq!(director.advance(1.0), vec![crowd_throw(1), crowd_throw(2)]);
+    // a long frame catches up on every repeat it skipped, the one-off doesn't come back
+    assert_eq!(director.advance(10.0), vec![crowd_throw(2), crowd_throw(2)]);
+}
+
+#[test]
+fn a_restarted_director_starts_the_schedule_over() {
+    let mut director = ArenaDirector::new(DirectorSchedule {
+        hazards: vec![ScheduledHazard {
+            at: 5.0,
+            every: None,
+            hazard: crowd_throw(1),
+        }],
+    });
+    assert_eq!(director.advance(5.0), vec![crowd_throw(1)]);
+
+    director.restart();
+
+    assert_eq!(director.elapsed, 0.0);
+    assert_eq!(director.advance(5.0), vec![crowd_throw(1)]);
+}
+
+#[test]
+fn cleared_hazards_leave_the_arena() {
+    let mut arena = calm_arena();
+    arena.send_event(HazardEvent(crowd_throw(2)));
+    arena.send_event(HazardEvent(Hazard::Lion {
+        damage: 0.0,
+        speed: 0.0,
+        duration: 60.0,
+    }));
+    arena.steps(2);
+
+    arena.app.add_system(clear_hazards);
+    arena.steps(2);
+
+    let world = &mut arena.app.world;
+    assert_eq!(world.query::<&ItemPickup>().iter(world).count(), 0);
+    assert_eq!(world.query::<&Lion>().iter(world).count(), 0);
+    assert!(world.resource::<ArenaDirector>().paused);
+}
+
+#[test]
+fn spike_traps_hurt_whoever_stands_on_them() {
+    let mut arena = calm_arena();
+    arena.record::<DeathEvent>();
+    let cell = GridLocation { x: 5, y: 5 };
+    {
+        // walled in, so the gladiator can't wander off the trap
+        let mut arena_grid = arena.app.world.resource_mut::<ArenaGrid>();
+        for location in ArenaGrid::locations_in_ring(cell, 1) {
+            arena_grid.set_terrain(location, Terrain::Wall);
+        }
+    }
+    let gladiator = arena.spawn_gladiator(ArenaGrid::center_of(cell), Class::Fighter, None);
+    arena.send_event(HazardEvent(Hazard::SpikeTraps {
+        locations: vec![cell],
+        random: 0,
+        damage: 10_000.0,
+        duration: 30.0,
+    }));
+
+    let killed = arena.run_until(20.0, |world| world.get_entity(gladiator).is_none());
+
+    assert!(killed, "the spikes should have killed the gladiator");
+    let deaths = arena.recorded::<DeathEvent>();
+    assert_eq!(deaths.len(), 1);
+    assert_eq!(deaths[0].slain, gladiator);
+    assert_eq!(
+        deaths[0].victor, None,
+        "the trap doesn't get credit for the kill"
+    );
+}
+
+#[test]
+fn the_shrinking_safe_zone_hurts_gladiators_outside_it() {
+    let mut arena = calm_arena();
+    arena.record::<DeathEvent>();
+    let outside = arena.spawn_gladiator(Vec2::new(600.0, 300.0), Class::Fighter, None);
+    let inside = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    arena.send_event(HazardEvent(Hazard::SafeZone {
+        to: 0.5,
+        over: 0.0,
+        damage_per_second: 10_000.0,
+    }));
+
+    arena.advance(1.0);
+
+    assert!(!arena.exists(outside));
+    assert!(arena.exists(inside));
+    let deaths = arena.recorded::<DeathEvent>();
+    assert_eq!(deaths.len(), 1);
+    assert_eq!(
+        deaths[0].victor, None,
+        "nobody gets credit for the arena's kills"
+    );
+}
+
+#[test]
+fn a_lion_hunts_down_gladiators() {
+    let mut arena = calm_arena();
+    let gladiator = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    arena.send_event(HazardEvent(Hazard::Lion {
+        damage: 10_000.0,
+        speed: 600.0,
+        duration: 60.0,
+    }));
+
+    let killed = arena.run_until(30.0, |world| world.get_entity(gladiator).is_none());
+
+    assert!(killed, "the lion should have caught the gladiator");
+}
+
+#[test]
+fn the_crowd_throws_items_into_the_arena() {
+    let mut arena = calm_arena();
+    arena.send_event(HazardEvent(crowd_throw(3)));
+    arena.steps(2);
+
+    let mut pickups = arena.app.world.query::<&ItemPickup>();
+    let items: Vec<&ItemPickup> = pickups.iter(&arena.app.world).collect();
+    assert_eq!(items.len(), 3);
+    assert!(items.iter().all(|pickup| pickup.item == "Gladius"));
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    engagements::Engagement,
+    gladiator::gladiator::Class,
+    grid::{ArenaGrid, GridLocation},
+    team::Team,
+};
+
+#[test]
+fn gladiators_in_the_same_location_engage_each_other() {
+    let mut arena = TestArena::battle();
+    let a = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    let b = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+
+    let engaged = arena.run_until(1.0, |world| {
+        world.get::<Engagement>(a).is_some() && world.get::<Engagement>(b).is_some()
+    });
+
+    assert!(engaged, "the two gladiators should have engaged");
+    assert_eq!(arena.get::<Engagement>(a).unwrap().target, b);
+    assert_eq!(arena.get::<Engagement>(b).unwrap().target, a);
+}
+
+#[test]
+fn teammates_never_engage() {
+    let mut arena = TestArena::battle();
+    let a = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, Some(Team(0)));
+    let b = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, Some(Team(0)));
+
+    arena.advance(1.0);
+
+    assert!(arena.get::<Engagement>(a).is_none());
+    assert!(arena.get::<Engagement>(b).is_none());
+}
+
+#[test]
+fn a_gladiator_is_engaged_with_one_opponent_at_a_time() {
+    let mut arena = TestArena::battle();
+    let gladiators: Vec<Entity> = (0..3)
+        .map(|_| arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None))
+        .collect();
+
+    arena.run_until(1.0, |world| {
+        gladiators
+            .iter()
+            .filter(|gladiator| world.get::<Engagement>(**gladiator).is_some())
+            .count()
+            >= 2
+    });
+
+    // the odd one out waits, the other two are engaged with each other
+    let engaged: Vec<Entity> = gladiators
+        .iter()
+        .copied()
+        .filter(|gladiator| arena.get::<Engagement>(*gladiator).is_some())
+        .collect();
+    assert_eq!(engaged.len(), 2);
+    assert_eq!(
+        arena.get::<Engagement>(engaged[0]).unwrap().target,
+        engaged[1]
+    );
+    assert_eq!(
+        arena.get::<Engagement>(engaged[1]).unwrap().target,
+        engaged[0]
+    );
+}
+
+#[test]
+fn a_ranged_engagement_only_engages_the_shooter() {
+    let mut arena = TestArena::battle();
+    let archer = arena.spawn_gladiator(
+        ArenaGrid::center_of(GridLocation { x: 0, y: 0 }),
+        Class::Archer,
+        None,
+    );
+    let fighter = arena.spawn_gladiator(
+        ArenaGrid::center_of(GridLocation { x: 2, y: 0 }),
+        Class::Fighter,
+        None,
+    );
+
+    let engaged = arena.run_until(1.0, |world| world.get::<Engagement>(archer).is_some());
+
+    assert!(engaged, "the archer should have taken aim at the fighter");
+    assert_eq!(arena.get::<Engagement>(archer).unwrap().target, fighter);
+    assert!(
+        arena.get::<Engagement>(fighter).is_none(),
+        "the fighter can't reach the archer"
+    );
+}
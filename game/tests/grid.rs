@@ -0,0 +1,99 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    gladiator::{gladiator::Class, gladiator_components::Gladiator},
+    grid::ArenaGrid,
+};
+
+/// Where the grid should have every gladiator, according to their Transforms
+fn expected_locations(arena: &mut TestArena) -> Vec<(Entity, game_lib::grid::GridLocation)> {
+    let mut query = arena
+        .app
+        .world
+        .query_filtered::<(Entity, &Transform), With<Gladiator>>();
+    query
+        .iter(&arena.app.world)
+        .map(|(entity, transform)| {
+            let location =
+                ArenaGrid::get_grid_location(transform.translation.x, transform.translation.y);
+            (entity, location)
+        })
+        .collect()
+}
+
+fn assert_grid_in_sync(arena: &mut TestArena) {
+    let expected = expected_locations(arena);
+    let problems = arena.resource::<ArenaGrid>().verify(expected.into_iter());
+    assert!(
+        problems.is_empty(),
+        "ArenaGrid is out of sync: {:?}",
+        problems
+    );
+}
+
+#[test]
+fn spawned_gladiators_are_placed_in_the_grid() {
+    let mut arena = TestArena::battle();
+    let gladiator = arena.spawn_gladiator(Vec2::new(200.0, -100.0), Class::Archer, None);
+
+    arena.step();
+
+    assert_eq!(
+        arena.resource::<ArenaGrid>().location_of(gladiator),
+        Some(ArenaGrid::get_grid_location(200.0, -100.0))
+    );
+}
+
+#[test]
+fn the_grid_follows_the_gladiators_around() {
+    let mut arena = TestArena::battle();
+    for idx in 0..20 {
+        let position = Vec2::new(idx as f32 * 60.0 - 600.0, idx as f32 * 30.0 - 300.0);
+        arena.spawn_gladiator(position, Class::Mage, None);
+    }
+
+    for _ in 0..120 {
+        arena.step();
+        assert_grid_in_sync(&mut arena);
+    }
+}
+
+#[test]
+fn moved_gladiators_change_location() {
+    let mut arena = TestArena::battle();
+    let gladiator = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    arena.step();
+
+    arena.get_mut::<Transform>(gladiator).unwrap().translation = Vec3::new(500.0, 300.0, 1.0);
+    arena.step();
+
+    let location = arena.resource::<ArenaGrid>().location_of(gladiator);
+    let transform = arena.get::<Transform>(gladiator).unwrap();
+    assert_eq!(
+        location,
+        Some(ArenaGrid::get_grid_location(
+            transform.translation.x,
+            transform.translation.y
+        ))
+    );
+    assert_ne!(location, Some(ArenaGrid::get_grid_location(0.0, 0.0)));
+}
+
+#[test]
+fn despawned_gladiators_leave_the_grid() {
+    let mut arena = TestArena::battle();
+    let gladiator = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    let bystander = arena.spawn_gladiator(Vec2::new(-400.0, 0.0), Class::Fighter, None);
+    arena.step();
+
+    arena.app.world.despawn(gladiator);
+    arena.step();
+
+    let arena_grid = arena.resource::<ArenaGrid>();
+    assert!(arena_grid.location_of(gladiator).is_none());
+    assert!(arena_grid.location_of(bystander).is_some());
+    assert_grid_in_sync(&mut arena);
+}
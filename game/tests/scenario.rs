@@ -0,0 +1,70 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    gladiator::{
+        gladiator::Class,
+        gladiator_components::{Gladiator, GladiatorClass, Health, Level},
+    },
+    scenario::ScenarioPlugin,
+};
+
+/// Spawns a gladiator the way a scene file does, with only the authored components
+fn spawn_authored(arena: &mut TestArena, level: Option<Level>) -> Entity {
+    let mut entity = arena.app.world.spawn((
+        GladiatorClass {
+            class: Class::Fighter,
+        },
+        Transform::from_xyz(100.0, 50.0, 0.0),
+    ));
+    if let Some(level) = level {
+        entity.insert(level);
+    }
+    entity.id()
+}
+
+#[test]
+fn authored_gladiators_keep_their_level_and_xp() {
+    let mut arena = TestArena::battle().with_plugin(ScenarioPlugin);
+    let veteran = spawn_authored(
+        &mut arena,
+        Some(Level {
+            level: 3,
+            xp: 12.5,
+            class_xp_modifier: 1.5,
+        }),
+    );
+    let recruit = spawn_authored(&mut arena, None);
+
+    arena.step();
+
+    assert!(arena.get::<Gladiator>(veteran).is_some());
+    let level = arena.get::<Level>(veteran).unwrap();
+    assert_eq!(
+        (level.level, level.xp, level.class_xp_modifier),
+        (3, 12.5, 1.5)
+    );
+    // the stats grow with the authored level
+    assert!(arena.get::<Health>(veteran).unwrap().max > arena.get::<Health>(recruit).unwrap().max);
+    assert_eq!(arena.get::<Level>(recruit).unwrap().level, 1);
+}
+
+#[test]
+fn missing_parts_of_an_authored_level_come_from_the_class() {
+    let mut arena = TestArena::battle().with_plugin(ScenarioPlugin);
+    let gladiator = spawn_authored(
+        &mut arena,
+        Some(Level {
+            level: 2,
+            ..default()
+        }),
+    );
+
+    arena.step();
+
+    let level = arena.get::<Level>(gladiator).unwrap();
+    assert_eq!(level.level, 2);
+    assert!(level.class_xp_modifier > 0.0);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    gladiator::{
+        gladiator::Class,
+        gladiator_components::{Gladiator, Health, Level},
+    },
+    player::player_components::Player,
+    survival::{Survival, SurvivalPlugin, WaveState},
+    team::Team,
+    SURVIVAL_ENEMY_TEAM, SURVIVAL_FIRST_WAVE_SIZE, SURVIVAL_LEVEL_UP_WAVES, SURVIVAL_PLAYER_HEALTH,
+    SURVIVAL_REST_DURATION, SURVIVAL_WAVE_GROWTH,
+};
+
+/// Survival mode with the player alone in the middle of the arena
+fn survival_arena() -> (TestArena, Entity) {
+    let mut arena = TestArena::battle().with_plugin(SurvivalPlugin);
+    let player = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+    arena.app.world.entity_mut(player).insert(Player);
+    (arena, player)
+}
+
+/// Team and level of every gladiator that isn't the player
+fn enemies(arena: &mut TestArena) -> Vec<(Entity, Option<Team>, usize)> {
+    let mut query = arena
+        .app
+        .world
+        .query_filtered::<(Entity, Option<&Team>, &Level), (With<Gladiator>, Without<Player>)>();
+    query
+        .iter(&arena.app.world)
+        .map(|(entity, team, level)| (entity, team.copied(), level.level))
+        .collect()
+}
+
+#[test]
+fn waves_arrive_after_a_rest_and_grow() {
+    let (mut arena, _) = survival_arena();
+
+    arena.advance(SURVIVAL_REST_DURATION - 0.5);
+    assert!(enemies(&mut arena).is_empty());
+    assert_eq!(arena.resource::<Survival>().wave, 0);
+
+    for wave in 1..=3 {
+        arena.advance(1.0);
+        let wave_enemies = enemies(&mut arena);
+        assert_eq!(
+            wave_enemies.len(),
+            SURVIVAL_FIRST_WAVE_SIZE + (wave - 1) * SURVIVAL_WAVE_GROWTH,
+            "wave {}",
+            wave
+        );
+        for (_, team, level) in &wave_enemies {
+            assert_eq!(*team, Some(Team(SURVIVAL_ENEMY_TEAM)));
+            assert_eq!(*level, 1 + (wave - 1) / SURVIVAL_LEVEL_UP_WAVES);
+        }
+        assert_eq!(arena.resource::<Survival>().wave, wave);
+
+        // the wave is defeated, the next one comes after another rest
+        for (enemy, _, _) in wave_enemies {
+            arena.app.world.despawn(enemy);
+        }
+        arena.step();
+        assert_eq!(arena.resource::<Survival>().waves_survived, wave);
+        assert!(matches!(
+            arena.resource::<Survival>().state,
+            WaveState::Resting(_)
+        ));
+        arena.advance(SURVIVAL_REST_DURATION - 1.0);
+        assert!(enemies(&mut arena).is_empty());
+    }
+}
+
+#[test]
+fn the_run_is_over_when_the_player_dies() {
+    let (mut arena, player) = survival_arena();
+    arena.step();
+
+    arena.app.world.despawn(player);
+    arena.step();
+
+    assert!(matches!(
+        arena.resource::<Survival>().state,
+        WaveState::GameOver
+    ));
+    arena.advance(SURVIVAL_REST_DURATION + 1.0);
+    assert!(enemies(&mut arena).is_empty());
+}
+
+#[test]
+fn only_a_new_player_gets_survival_health() {
+    let (mut arena, player) = survival_arena();
+    arena.step();
+
+    let health = arena.get::<Health>(player).unwrap();
+    assert_eq!(
+        (health.value, health.max),
+        (SURVIVAL_PLAYER_HEALTH, SURVIVAL_PLAYER_HEALTH)
+    );
+
+    // wounds stay, the player is only topped up by resting
+    arena.get_mut::<Health>(player).unwrap().value = 40.0;
+    arena.step();
+    let health = arena.get::<Health>(player).unwrap();
+    assert!(health.value > 40.0 && health.value < 41.0);
+
+    arena.advance(SURVIVAL_REST_DURATION);
+    for (enemy, _, _) in enemies(&mut arena) {
+        assert!(arena.get::<Health>(enemy).unwrap().max < SURVIVAL_PLAYER_HEALTH);
+    }
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    gladiator::gladiator::Class,
+    team::{Team, TeamPlugin, VictoryEvent},
+};
+
+fn victors(arena: &TestArena) -> Vec<Option<Team>> {
+    arena
+        .recorded::<VictoryEvent>()
+        .iter()
+        .map(|victory| victory.team)
+        .collect()
+}
+
+#[test]
+fn the_last_team_standing_wins_once() {
+    let mut arena = TestArena::battle().with_plugin(TeamPlugin);
+    arena.record::<VictoryEvent>();
+    // far apart, so nobody fights until the test says so
+    arena.spawn_gladiator(Vec2::new(-600.0, 300.0), Class::Fighter, Some(Team(1)));
+    arena.spawn_gladiator(Vec2::new(-600.0, -300.0), Class::Fighter, Some(Team(1)));
+    let loser = arena.spawn_gladiator(Vec2::new(600.0, 0.0), Class::Fighter, Some(Team(2)));
+    arena.steps(3);
+    assert!(victors(&arena).is_empty());
+
+    arena.app.world.despawn(loser);
+    arena.steps(10);
+
+    assert_eq!(victors(&arena), vec![Some(Team(1))]);
+}
+
+#[test]
+fn one_team_alone_has_not_won_a_battle() {
+    let mut arena = TestArena::battle().with_plugin(TeamPlugin);
+    arena.record::<VictoryEvent>();
+    arena.spawn_gladiator(Vec2::new(-600.0, 0.0), Class::Mage, Some(Team(1)));
+    arena.spawn_gladiator(Vec2::new(600.0, 0.0), Class::Mage, Some(Team(1)));
+
+    arena.steps(10);
+
+    assert!(victors(&arena).is_empty());
+}
+
+#[test]
+fn every_battle_in_a_row_gets_its_own_victory() {
+    let mut arena = TestArena::battle().with_plugin(TeamPlugin);
+    arena.record::<VictoryEvent>();
+    arena.spawn_gladiator(Vec2::new(-600.0, 0.0), Class::Archer, None);
+    let first = arena.spawn_gladiator(Vec2::new(600.0, 0.0), Class::Archer, None);
+    arena.step();
+    arena.app.world.despawn(first);
+    arena.steps(3);
+
+    let second = arena.spawn_gladiator(Vec2::new(600.0, 300.0), Class::Archer, None);
+    arena.step();
+    arena.app.world.despawn(second);
+    arena.steps(3);
+
+    assert_eq!(victors(&arena), vec![None, None]);
+}
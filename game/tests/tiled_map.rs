@@ -0,0 +1,82 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    arena::{ArenaMap, ArenaPlugin, ItemPickup, Terrain, TileImage},
+    gladiator::{gladiator::Class, gladiator_equipment::Equipment},
+    team::Team,
+    tiled_map::load_tiled_map,
+};
+
+fn terrain_of(arena_map: &ArenaMap, terrain: Terrain) -> usize {
+    arena_map
+        .locations()
+        .filter(|(_, location_terrain)| *location_terrain == terrain)
+        .count()
+}
+
+#[test]
+fn a_tmj_map_brings_its_tiles_spawns_and_items() {
+    let arena_map = load_tiled_map("maps/ludus.tmj").unwrap();
+
+    // a ground tile everywhere and the trees on top
+    assert!(arena_map.tiles.len() > 40 * 22);
+    assert!(arena_map.tiles.iter().all(|tile| matches!(
+        &tile.image,
+        TileImage::Single(image) if image.starts_with("Puny-Characters/Environment/")
+    )));
+    assert!(terrain_of(&arena_map, Terrain::Pillar) > 0);
+
+    let teams: Vec<Option<Team>> = arena_map
+        .spawn_points
+        .iter()
+        .map(|spawn_point| spawn_point.team)
+        .collect();
+    assert_eq!(teams.len(), 6);
+    assert!(teams.contains(&Some(Team(0))) && teams.contains(&Some(Team(1))));
+
+    let gladius = arena_map
+        .item_spawners
+        .iter()
+        .find(|spawner| spawner.item == "Gladius")
+        .unwrap();
+    assert_eq!(gladius.interval, Some(30.0));
+    // the middle of the map is the middle of the arena
+    assert!(gladius.position.length() < 1.0);
+}
+
+#[test]
+fn hidden_tmx_layers_only_block() {
+    let arena_map = load_tiled_map("maps/yard.tmx").unwrap();
+
+    // only the ground layer is drawn, the pond layer is hidden
+    assert_eq!(arena_map.tiles.len(), 20 * 11);
+    assert!(terrain_of(&arena_map, Terrain::Water) > 0);
+    assert_eq!(arena_map.spawn_points.len(), 2);
+    assert!(arena_map.spawn_points[0].position.x < 0.0);
+    assert!(arena_map.spawn_points[1].position.x > 0.0);
+}
+
+#[test]
+fn gladiators_pick_up_items_they_walk_over() {
+    let mut arena = TestArena::battle().with_plugin(ArenaPlugin {
+        map: "ludus.tmj".to_string(),
+    });
+    let gladiator = arena.spawn_gladiator(Vec2::ZERO, Class::Fighter, None);
+
+    let picked_up = arena.run_until(1.0, |world| {
+        world
+            .get::<Equipment>(gladiator)
+            .map_or(false, |equipment| {
+                equipment.weapon.as_deref() == Some("Gladius")
+            })
+    });
+
+    assert!(picked_up, "the gladiator should have picked up the gladius");
+    let mut pickups = arena.app.world.query::<&ItemPickup>();
+    assert!(pickups
+        .iter(&arena.app.world)
+        .all(|pickup| pickup.item != "Gladius"));
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use bevy::prelude::*;
+
+use common::TestArena;
+use game_lib::{
+    gladiator::gladiator_events::DeathEvent,
+    tournament::{
+        Bracket, Duelist, Roster, Tournament, TournamentPlugin, TournamentStandings,
+        TournamentState,
+    },
+    DUEL_INTERMISSION,
+};
+
+/// A tournament of the classic roster with its first duel under way, and the entities of
+/// its two duelists
+fn first_duel() -> (TestArena, Entity, Entity) {
+    let mut arena = TestArena::battle().with_plugin(TournamentPlugin {
+        roster: "classic".to_string(),
+        exit_when_done: false,
+    });
+    arena.advance(DUEL_INTERMISSION + 0.1);
+    assert!(matches!(
+        arena.resource::<Tournament>().state,
+        TournamentState::Dueling(..)
+    ));
+
+    let mut query = arena.app.world.query::<(Entity, &Duelist)>();
+    let mut duelists: Vec<(Entity, usize)> = query
+        .iter(&arena.app.world)
+        .map(|(entity, duelist)| (entity, duelist.0))
+        .collect();
+    duelists.sort_by_key(|(_, entrant_idx)| *entrant_idx);
+    assert_eq!(duelists.len(), 2);
+    (arena, duelists[0].0, duelists[1].0)
+}
+
+#[test]
+fn the_standings_follow_the_bracket() {
+    let mut bracket = Bracket::new(Roster::load("classic").unwrap());
+    let (a, b) = bracket.next_match().unwrap();
+    bracket.record(a, b);
+
+    let standings = TournamentStandings::from(&bracket);
+
+    assert_eq!(standings.round, 1);
+    assert_eq!(
+        standings.results,
+        vec![format!(
+            "round 1: {} defeated {}",
+            bracket.entrants[a].name, bracket.entrants[b].name
+        )]
+    );
+    assert_eq!(standings.up_next.len(), bracket.pending.len());
+    assert_eq!(standings.entrants[a].wins, 1);
+    assert!(standings.entrants[b].eliminated);
+    assert_eq!(standings.champion, None);
+}
+
+#[test]
+fn a_missing_roster_is_an_error() {
+    assert!(Roster::load("no_such_roster").is_err());
+}
+
+#[test]
+fn a_duelist_that_dies_without_a_killer_loses_the_duel() {
+    let (mut arena, first, second) = first_duel();
+
+    // e.g. a bleed that nobody gets the credit for
+    arena.send_event(DeathEvent {
+        victor: None,
+        xp_earned: 0.0,
+        slain: second,
+    });
+    arena.step();
+
+    let tournament = arena.resource::<Tournament>();
+    assert!(matches!(tournament.state, TournamentState::Intermission(_)));
+    assert_eq!(tournament.bracket.results, vec![(1, 0, 1)]);
+    // the winner leaves the arena until their next duel
+    assert!(!arena.exists(first));
+    assert!(!arena.exists(second));
+}
+
+#[test]
+fn the_first_death_decides_a_duel_when_both_duelists_die() {
+    let (mut arena, first, second) = first_duel();
+
+    for slain in [first, second] {
+        arena.send_event(DeathEvent {
+            victor: None,
+            xp_earned: 0.0,
+            slain,
+        });
+    }
+    arena.step();
+
+    let tournament = arena.resource::<Tournament>();
+    assert_eq!(tournament.bracket.results, vec![(1, 1, 0)]);
+    assert!(!arena.exists(first));
+    assert!(!arena.exists(second));
+
+    // the tournament goes on with the next duel
+    arena.advance(DUEL_INTERMISSION + 0.1);
+    assert!(matches!(
+        arena.resource::<Tournament>().state,
+        TournamentState::Dueling(2, 3)
+    ));
+}